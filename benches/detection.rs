@@ -0,0 +1,23 @@
+use av_scenechange::SceneDetectionSpeed;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+fn detection(c: &mut Criterion) {
+    let clip = common::hard_cuts_clip();
+    let mut group = c.benchmark_group("detect_scene_changes");
+    group.throughput(Throughput::Elements(4 * common::SCENE_LENGTH as u64));
+    for &(name, speed) in &[
+        ("standard", SceneDetectionSpeed::Standard),
+        ("fast", SceneDetectionSpeed::Fast),
+        ("histogram", SceneDetectionSpeed::Histogram),
+        ("edge-change-ratio", SceneDetectionSpeed::EdgeChangeRatio),
+    ] {
+        group.bench_function(name, |b| b.iter(|| common::detect(&clip, speed)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, detection);
+criterion_main!(benches);
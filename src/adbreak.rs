@@ -0,0 +1,83 @@
+use crate::black::{self, BlackFrameDetection};
+use crate::decoder::VideoDetails;
+
+/// The longest time, in seconds, between two black separators
+/// for them to be considered part of the same break.
+const MAX_SEPARATOR_SPACING: f64 = 65.0;
+/// The minimum number of black separators in a break.
+const MIN_SEPARATORS: usize = 3;
+/// How many times the average cut density of the clip
+/// the cut density of a break must reach.
+const MIN_CUT_DENSITY_RATIO: f64 = 1.5;
+
+/// A span of the video which is likely to be a commercial break.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct AdBreakCandidate {
+    /// The 0-indexed frame number where the break starts.
+    pub start_frame: usize,
+    /// The 0-indexed frame number after the last frame of the break.
+    pub end_frame: usize,
+    /// The number of runs of black frames within the break.
+    pub black_separators: usize,
+    /// The number of scene changes per minute within the break.
+    pub cut_density: f64,
+}
+
+/// Finds likely commercial breaks, as groups of closely spaced
+/// runs of black frames with more scene changes than the rest of the clip.
+///
+/// `mean_luma` holds the normalized mean luma of each frame of the clip.
+pub(crate) fn find_ad_breaks(
+    mean_luma: &[f32],
+    scene_changes: &[usize],
+    video_details: &VideoDetails,
+) -> Vec<AdBreakCandidate> {
+    let frames_per_minute =
+        60.0 * video_details.time_base.den as f64 / video_details.time_base.num as f64;
+    let cut_density = |start: usize, end: usize| {
+        let cuts = scene_changes
+            .iter()
+            .filter(|&&frameno| frameno >= start && frameno < end)
+            .count();
+        cuts as f64 * frames_per_minute / (end - start) as f64
+    };
+    if mean_luma.is_empty() {
+        return Vec::new();
+    }
+    let average_density = cut_density(0, mean_luma.len());
+    let max_spacing = (MAX_SEPARATOR_SPACING * frames_per_minute / 60.0) as usize;
+
+    let mut candidates = Vec::new();
+    let mut group: Vec<(usize, usize)> = Vec::new();
+    let mut try_push_group = |group: &[(usize, usize)]| {
+        if group.len() < MIN_SEPARATORS {
+            return;
+        }
+        let (start_frame, end_frame) = (group[0].0, group[group.len() - 1].1);
+        let density = cut_density(start_frame, end_frame);
+        if density > 0.0 && density >= average_density * MIN_CUT_DENSITY_RATIO {
+            candidates.push(AdBreakCandidate {
+                start_frame,
+                end_frame,
+                black_separators: group.len(),
+                cut_density: density,
+            });
+        }
+    };
+    // Breaks are always found with the default black frame detection,
+    // which the other constants are tuned for
+    let detection = BlackFrameDetection::default();
+    for run in black::black_runs(mean_luma, detection.threshold, detection.min_frames) {
+        let run = (run.start_frame, run.end_frame);
+        if let Some(&(_, last_end)) = group.last() {
+            if run.0 - last_end > max_spacing {
+                try_push_group(&group);
+                group.clear();
+            }
+        }
+        group.push(run);
+    }
+    try_push_group(&group);
+    candidates
+}
@@ -0,0 +1,175 @@
+use crate::pool::ReusedFramePool;
+use crate::{
+    detect_scene_changes_with_cuts, Decoder, DetectionError, DetectionOptions, DetectionResults,
+    ProgressCallback, ProgressEvent,
+};
+use rav1e::prelude::Pixel;
+use std::cell::Cell;
+use std::iter::Enumerate;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use std::vec;
+
+/// The progress of a batch of detection passes.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchProgressEvent {
+    /// The index of the input the progress is for.
+    pub input: usize,
+    /// The progress of the detection pass over that input.
+    pub progress: ProgressEvent,
+    /// The number of frames analyzed so far over all inputs.
+    pub total_frames_analyzed: usize,
+    /// The number of inputs which have been analyzed completely.
+    pub inputs_done: usize,
+    /// The number of inputs in the batch.
+    pub inputs: usize,
+}
+
+/// An optional callback that will fire after each frame of any input
+/// in a batch is analyzed, and once more after each input is done,
+/// on the thread analyzing that input.
+pub type BatchProgressCallback = Arc<dyn Fn(&BatchProgressEvent) + Send + Sync>;
+
+/// The progress shared between the inputs of a batch.
+struct BatchProgress {
+    callback: BatchProgressCallback,
+    total_frames_analyzed: AtomicUsize,
+    inputs_done: AtomicUsize,
+    inputs: usize,
+}
+
+type InputQueue<D> = Mutex<Enumerate<vec::IntoIter<D>>>;
+
+/// Runs `detect_scene_changes` over several inputs, such as the episodes
+/// of a series, analyzing up to `threads` of them at once in one process,
+/// and returns the results for each input in the same order.
+///
+/// Each input is decoded on its own thread while it is analyzed,
+/// so up to `2 * threads` threads are busy at once. Each worker decodes
+/// into the frames released while analyzing its previous input, as long as
+/// the inputs have the same frame size, so at most `threads` inputs' worth
+/// of frames are held at once. A failure in one input
+/// does not affect the others. Setting `cancel_flag` cancels all inputs
+/// which are still being analyzed, and those which have not started
+/// return cancelled results without any frames.
+pub fn detect_scene_changes_batch<D: Decoder + Send, T: Pixel>(
+    inputs: Vec<D>,
+    opts: DetectionOptions,
+    threads: usize,
+    progress_callback: Option<BatchProgressCallback>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+) -> Vec<Result<DetectionResults, DetectionError>> {
+    assert!(threads >= 1);
+
+    let input_count = inputs.len();
+    let queue = Mutex::new(inputs.into_iter().enumerate());
+    let progress = progress_callback.map(|callback| {
+        Arc::new(BatchProgress {
+            callback,
+            total_frames_analyzed: AtomicUsize::new(0),
+            inputs_done: AtomicUsize::new(0),
+            inputs: input_count,
+        })
+    });
+    let (queue, opts, progress, cancel_flag) = (&queue, &opts, &progress, &cancel_flag);
+    let mut results = thread::scope(|scope| {
+        let workers = (0..threads.min(input_count))
+            .map(move |_| {
+                scope.spawn(move || {
+                    run_worker::<D, T>(queue, opts, progress.as_ref(), cancel_flag.as_ref())
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Scene detection worker panicked"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Analyzes inputs from the queue until it is empty,
+/// returning the results of each along with its index.
+fn run_worker<D: Decoder + Send, T: Pixel>(
+    queue: &InputQueue<D>,
+    opts: &DetectionOptions,
+    progress: Option<&Arc<BatchProgress>>,
+    cancel_flag: Option<&Arc<AtomicBool>>,
+) -> Vec<(usize, Result<DetectionResults, DetectionError>)> {
+    let mut results = Vec::new();
+    let mut reused_frames = ReusedFramePool::new();
+    loop {
+        let next = queue.lock().expect("Input queue was poisoned").next();
+        let (index, mut dec) = match next {
+            Some(input) => input,
+            None => break,
+        };
+        let result = detect_scene_changes_with_cuts::<D, T>(
+            &mut dec,
+            opts.clone(),
+            progress.map(|progress| input_progress(progress, index)),
+            None,
+            None,
+            cancel_flag.cloned(),
+            &mut reused_frames,
+        );
+        if let Some(progress) = progress {
+            report_input_done(progress, index, &result);
+        }
+        results.push((index, result));
+    }
+    results
+}
+
+/// Counts an input as done, and reports the final progress of its detection pass,
+/// so that an event reports every input as done once the batch finishes.
+fn report_input_done(
+    progress: &BatchProgress,
+    input: usize,
+    result: &Result<DetectionResults, DetectionError>,
+) {
+    let inputs_done = progress.inputs_done.fetch_add(1, Ordering::Relaxed) + 1;
+    let results = match result {
+        Ok(results) => results,
+        Err(err) => err.partial_results(),
+    };
+    (progress.callback)(&BatchProgressEvent {
+        input,
+        progress: ProgressEvent {
+            frames_decoded: results.frame_count,
+            frames_analyzed: results.frame_count,
+            scene_changes: results.scene_changes.len(),
+            fps: results.speed,
+            eta: Some(Duration::ZERO),
+        },
+        total_frames_analyzed: progress.total_frames_analyzed.load(Ordering::Relaxed),
+        inputs_done,
+        inputs: progress.inputs,
+    });
+}
+
+/// Returns the progress callback for one input of a batch,
+/// which adds its progress to the progress of the batch.
+fn input_progress(progress: &Arc<BatchProgress>, input: usize) -> ProgressCallback {
+    let progress = Arc::clone(progress);
+    let frames_analyzed = Cell::new(0);
+    Box::new(move |event: &ProgressEvent| {
+        let new_frames = event
+            .frames_analyzed
+            .saturating_sub(frames_analyzed.replace(event.frames_analyzed));
+        let total_frames_analyzed = progress
+            .total_frames_analyzed
+            .fetch_add(new_frames, Ordering::Relaxed)
+            + new_frames;
+        (progress.callback)(&BatchProgressEvent {
+            input,
+            progress: *event,
+            total_frames_analyzed,
+            inputs_done: progress.inputs_done.load(Ordering::Relaxed),
+            inputs: progress.inputs,
+        });
+    })
+}
@@ -0,0 +1,63 @@
+/// The normalized mean luma below which a frame is considered black by default.
+pub(crate) const BLACK_FRAME_THRESHOLD: f32 = 0.1;
+/// The minimum number of consecutive black frames reported as a run by default.
+const MIN_BLACK_FRAMES: usize = 3;
+
+/// How runs of black frames are detected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
+pub struct BlackFrameDetection {
+    /// The mean luma, from `0.0` to `1.0`, below which a frame is black.
+    pub threshold: f32,
+    /// The minimum number of consecutive black frames in a run.
+    pub min_frames: usize,
+}
+
+impl Default for BlackFrameDetection {
+    fn default() -> Self {
+        BlackFrameDetection {
+            threshold: BLACK_FRAME_THRESHOLD,
+            min_frames: MIN_BLACK_FRAMES,
+        }
+    }
+}
+
+/// A run of consecutive black frames, such as those
+/// separating programs and commercials in broadcast captures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlackFrameRun {
+    /// The 0-indexed frame number of the first black frame.
+    pub start_frame: usize,
+    /// The 0-indexed frame number after the last black frame.
+    pub end_frame: usize,
+}
+
+/// Returns the runs of at least `min_frames` frames
+/// whose mean luma is below `threshold`.
+pub(crate) fn black_runs(
+    mean_luma: &[f32],
+    threshold: f32,
+    min_frames: usize,
+) -> Vec<BlackFrameRun> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    let is_black = mean_luma.iter().map(|&luma| luma < threshold);
+    for (frameno, is_black) in is_black.chain(Some(false)).enumerate() {
+        match (is_black, run_start) {
+            (true, None) => run_start = Some(frameno),
+            (false, Some(start)) => {
+                if frameno - start >= min_frames.max(1) {
+                    runs.push(BlackFrameRun {
+                        start_frame: start,
+                        end_frame: frameno,
+                    });
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
@@ -0,0 +1,188 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::{DecodeError, Decoder, VideoDetails};
+use rav1e::prelude::{CastFromPrimitive, ChromaSampling, Frame, Pixel, Plane};
+use std::io;
+use std::sync::mpsc;
+
+/// Decodes frames which the application supplies itself,
+/// such as from its own capture or decoding stack,
+/// through the `FrameSender` returned alongside it.
+///
+/// Detection runs on the decoder in one thread,
+/// while frames are pushed from another. The stream ends
+/// once every `FrameSender` has been dropped.
+pub struct BufferDecoder {
+    video_details: VideoDetails,
+    receiver: mpsc::Receiver<BufferedFrame>,
+    total_frames: Option<usize>,
+}
+
+/// Pushes frames to a `BufferDecoder`.
+#[derive(Clone)]
+pub struct FrameSender {
+    video_details: VideoDetails,
+    sender: mpsc::SyncSender<BufferedFrame>,
+}
+
+/// The planes of a pushed frame, with their rows packed one after another.
+enum BufferedFrame {
+    U8(Vec<Vec<u8>>),
+    U16(Vec<Vec<u16>>),
+}
+
+impl BufferDecoder {
+    /// Creates a decoder for frames with the given properties,
+    /// along with the sender to push them with.
+    ///
+    /// Up to `capacity` frames are buffered, after which pushing
+    /// blocks until detection catches up.
+    pub fn new(video_details: VideoDetails, capacity: usize) -> (Self, FrameSender) {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let dec = BufferDecoder {
+            video_details,
+            receiver,
+            total_frames: None,
+        };
+        let sender = FrameSender {
+            video_details,
+            sender,
+        };
+        (dec, sender)
+    }
+
+    /// Sets the total number of frames which will be pushed,
+    /// for use in progress reports.
+    pub fn with_total_frames(mut self, total_frames: usize) -> Self {
+        self.total_frames = Some(total_frames);
+        self
+    }
+}
+
+impl FrameSender {
+    /// Pushes the next frame of 8-bit video.
+    ///
+    /// `planes` holds the luma plane only for monochrome video,
+    /// and the luma and both chroma planes otherwise.
+    /// `strides` holds the distance between the starts
+    /// of consecutive rows of each plane, in samples.
+    ///
+    /// Fails if the planes do not match the video details,
+    /// or if the decoder has been dropped.
+    pub fn push_frame(&self, planes: &[&[u8]], strides: &[usize]) -> Result<(), DecodeError> {
+        if self.video_details.bit_depth != 8 {
+            return Err(DecodeError::InvalidInput(
+                "8-bit frame pushed for high bit depth video".to_string(),
+            ));
+        }
+        let planes = pack_planes(&self.video_details, planes, strides)?;
+        self.send(BufferedFrame::U8(planes))
+    }
+
+    /// Pushes the next frame of high bit depth video,
+    /// in the same layout as for `push_frame`.
+    pub fn push_frame_u16(&self, planes: &[&[u16]], strides: &[usize]) -> Result<(), DecodeError> {
+        if self.video_details.bit_depth == 8 {
+            return Err(DecodeError::InvalidInput(
+                "16-bit frame pushed for 8-bit video".to_string(),
+            ));
+        }
+        let planes = pack_planes(&self.video_details, planes, strides)?;
+        self.send(BufferedFrame::U16(planes))
+    }
+
+    fn send(&self, frame: BufferedFrame) -> Result<(), DecodeError> {
+        self.sender.send(frame).map_err(|_| {
+            DecodeError::Io(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the decoder has been dropped",
+            ))
+        })
+    }
+}
+
+impl Decoder for BufferDecoder {
+    fn get_video_details(&self) -> VideoDetails {
+        self.video_details
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.total_frames
+    }
+
+    fn read_video_frame<T: Pixel>(&mut self, cfg: &VideoDetails) -> Result<Frame<T>, DecodeError> {
+        let buffered = self.receiver.recv().map_err(|_| DecodeError::EndOfStream)?;
+        let mut f: Frame<T> =
+            Frame::new_with_padding(cfg.width, cfg.height, cfg.chroma_sampling, LUMA_PADDING);
+        match buffered {
+            BufferedFrame::U8(planes) => {
+                for (plane, samples) in f.planes.iter_mut().zip(&planes) {
+                    copy_plane(plane, samples);
+                }
+            }
+            BufferedFrame::U16(planes) => {
+                for (plane, samples) in f.planes.iter_mut().zip(&planes) {
+                    copy_plane(plane, samples);
+                }
+            }
+        }
+        Ok(f)
+    }
+}
+
+/// Copies the visible area of each plane into a packed buffer,
+/// checking that there are as many planes as the chroma sampling requires.
+fn pack_planes<P: Copy>(
+    details: &VideoDetails,
+    planes: &[&[P]],
+    strides: &[usize],
+) -> Result<Vec<Vec<P>>, DecodeError> {
+    let (chroma_width, chroma_height) = details
+        .chroma_sampling
+        .get_chroma_dimensions(details.width, details.height);
+    let dimensions = [
+        (details.width, details.height),
+        (chroma_width, chroma_height),
+        (chroma_width, chroma_height),
+    ];
+    let plane_count = if details.chroma_sampling == ChromaSampling::Cs400 {
+        1
+    } else {
+        3
+    };
+    if planes.len() != plane_count || strides.len() != plane_count {
+        return Err(DecodeError::InvalidInput(format!(
+            "expected {} planes, got {}",
+            plane_count,
+            planes.len()
+        )));
+    }
+
+    planes
+        .iter()
+        .zip(strides)
+        .zip(&dimensions)
+        .map(|((&samples, &stride), &(width, height))| {
+            if stride < width || samples.len() < stride * (height - 1) + width {
+                return Err(DecodeError::InvalidInput(
+                    "plane is smaller than its dimensions".to_string(),
+                ));
+            }
+            let mut packed = Vec::with_capacity(width * height);
+            for row in samples.chunks(stride).take(height) {
+                packed.extend_from_slice(&row[..width]);
+            }
+            Ok(packed)
+        })
+        .collect()
+}
+
+fn copy_plane<T: Pixel + CastFromPrimitive<P>, P: Copy>(plane: &mut Plane<T>, samples: &[P]) {
+    let width = plane.cfg.width;
+    let stride = plane.cfg.stride;
+    let data = plane.data_origin_mut();
+    for (y, row) in samples.chunks(width).enumerate() {
+        for (dst, &src) in data[y * stride..][..width].iter_mut().zip(row) {
+            *dst = T::cast_from(src);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! C API for running scene change detection on frames
+//! supplied by the caller, such as from an existing media pipeline.
+//!
+//! This is built as a C library with `cargo cbuild --features capi`,
+//! which also generates the `avsc.h` header.
+//!
+//! Panics are caught at the API boundary, since unwinding into C
+//! is undefined behavior, and reported as failures.
+
+use crate::push::PushPipeline;
+use crate::{DetectionOptions, SceneDetectionSpeed};
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+/// A scene change detector which frames are pushed to one at a time.
+pub struct AvscContext {
+    pipeline: PushPipeline,
+}
+
+/// Creates a detector for luma planes of the given size and bit depth.
+///
+/// `speed` selects the algorithm the same way as the `--speed` CLI option:
+/// 0 for the standard algorithm, 1 for the fast algorithm,
+/// 2 for histograms and 3 for the edge change ratio.
+///
+/// Returns null if any of the arguments are invalid.
+/// The detector must be freed with `avsc_free`.
+#[no_mangle]
+pub extern "C" fn avsc_new(
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    speed: c_int,
+) -> *mut AvscContext {
+    panic::catch_unwind(|| {
+        let analysis_speed = match speed {
+            0 => SceneDetectionSpeed::Standard,
+            1 => SceneDetectionSpeed::Fast,
+            2 => SceneDetectionSpeed::Histogram,
+            3 => SceneDetectionSpeed::EdgeChangeRatio,
+            _ => return std::ptr::null_mut(),
+        };
+        let opts = DetectionOptions {
+            analysis_speed,
+            ..Default::default()
+        };
+        match PushPipeline::new(width, height, bit_depth, opts) {
+            Ok(pipeline) => Box::into_raw(Box::new(AvscContext { pipeline })),
+            Err(_) => std::ptr::null_mut(),
+        }
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Pushes the luma plane of the next frame to the detector.
+///
+/// Samples are single bytes for 8-bit video, and little-endian
+/// 16-bit values otherwise. `stride` is the distance between
+/// the starts of consecutive rows, in bytes, and must be at least
+/// the size of a row of samples. The last row only needs to hold
+/// the samples of the row, not the full stride.
+///
+/// Returns 0 on success, or -1 if the arguments are invalid,
+/// `avsc_get_results` has already been called or detection failed.
+///
+/// # Safety
+///
+/// `ctx` must have been returned by `avsc_new` and not freed,
+/// and `data` must point to `stride * (height - 1) + width * bytes_per_sample`
+/// readable bytes, where `bytes_per_sample` is 1 for 8-bit video and 2 otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn avsc_push_frame(
+    ctx: *mut AvscContext,
+    data: *const u8,
+    stride: usize,
+) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let ctx = match ctx.as_mut() {
+            Some(ctx) => ctx,
+            None => return -1,
+        };
+        if data.is_null() {
+            return -1;
+        }
+        let details = ctx.pipeline.video_details();
+        let row_bytes = details.width * if details.bit_depth == 8 { 1 } else { 2 };
+        if stride < row_bytes {
+            return -1;
+        }
+        let len = match stride
+            .checked_mul(details.height - 1)
+            .and_then(|len| len.checked_add(row_bytes))
+        {
+            Some(len) => len,
+            None => return -1,
+        };
+        let data = slice::from_raw_parts(data, len);
+        match ctx.pipeline.push_luma(data, stride) {
+            Ok(()) => 0,
+            Err(_) => -1,
+        }
+    }))
+    .unwrap_or(-1)
+}
+
+/// Finishes analyzing the pushed frames, and copies the 0-indexed
+/// frame numbers of the scene changes into `scene_changes`,
+/// writing at most `capacity` of them.
+///
+/// Returns the total number of scene changes, which may be
+/// larger than `capacity`, or 0 if `ctx` is null or detection failed.
+/// This may be called again to retrieve the same results,
+/// but no further frames may be pushed.
+///
+/// # Safety
+///
+/// `ctx` must have been returned by `avsc_new` and not freed,
+/// and `scene_changes` must point to `capacity` writable values,
+/// or may be null if `capacity` is 0.
+#[no_mangle]
+pub unsafe extern "C" fn avsc_get_results(
+    ctx: *mut AvscContext,
+    scene_changes: *mut usize,
+    capacity: usize,
+) -> usize {
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        let ctx = match ctx.as_mut() {
+            Some(ctx) => ctx,
+            None => return 0,
+        };
+        let keyframes = ctx.pipeline.finish();
+        let count = keyframes.len().min(capacity);
+        if count > 0 {
+            slice::from_raw_parts_mut(scene_changes, count).copy_from_slice(&keyframes[..count]);
+        }
+        keyframes.len()
+    }))
+    .unwrap_or(0)
+}
+
+/// Frees a detector created by `avsc_new`.
+///
+/// # Safety
+///
+/// `ctx` must have been returned by `avsc_new` and not freed yet, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn avsc_free(ctx: *mut AvscContext) {
+    if !ctx.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(ctx))));
+    }
+}
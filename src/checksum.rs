@@ -0,0 +1,80 @@
+use crate::metrics;
+use rav1e::prelude::{Pixel, Plane};
+use xxhash_rust::xxh3::Xxh3;
+
+/// A checksum of the decoded luma of all frames in a scene.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneChecksum {
+    /// The 0-indexed frame number where the scene starts.
+    pub start_frame: usize,
+    /// The XXH3-64 hash of the visible luma samples of each frame
+    /// in the scene, in order, row by row. Samples are hashed as single bytes
+    /// for 8-bit input, and as little-endian 16-bit values otherwise.
+    pub checksum: u64,
+}
+
+/// Computes a checksum of each scene as its frames are analyzed.
+pub(crate) struct SceneChecksummer {
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    current: Option<(usize, Xxh3)>,
+    checksums: Vec<SceneChecksum>,
+    row_bytes: Vec<u8>,
+}
+
+impl SceneChecksummer {
+    pub fn new(width: usize, height: usize, bit_depth: usize) -> Self {
+        SceneChecksummer {
+            width,
+            height,
+            bit_depth,
+            current: None,
+            checksums: Vec::new(),
+            row_bytes: Vec::with_capacity(width * 2),
+        }
+    }
+
+    /// Adds the luma plane of the next frame,
+    /// starting a new scene if it is a scene change.
+    pub fn push<T: Pixel>(&mut self, frameno: usize, luma: &Plane<T>, is_keyframe: bool) {
+        if is_keyframe {
+            self.finish_scene();
+            self.current = Some((frameno, Xxh3::new()));
+        }
+        let hasher = match self.current {
+            Some((_, ref mut hasher)) => hasher,
+            None => return,
+        };
+
+        for y in 0..self.height {
+            self.row_bytes.clear();
+            for &px in metrics::row(luma, y, self.width) {
+                let value: u32 = px.into();
+                if self.bit_depth == 8 {
+                    self.row_bytes.push(value as u8);
+                } else {
+                    self.row_bytes
+                        .extend_from_slice(&(value as u16).to_le_bytes());
+                }
+            }
+            hasher.update(&self.row_bytes);
+        }
+    }
+
+    /// Returns the checksums of all scenes, including the final one.
+    pub fn finish(mut self) -> Vec<SceneChecksum> {
+        self.finish_scene();
+        self.checksums
+    }
+
+    fn finish_scene(&mut self) {
+        if let Some((start_frame, hasher)) = self.current.take() {
+            self.checksums.push(SceneChecksum {
+                start_frame,
+                checksum: hasher.digest(),
+            });
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::decoder::{FieldOrder, VideoDetails};
+use crate::metrics;
+use crate::FieldHandling;
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel};
+
+const SB_SIZE_LOG2: usize = 6;
+const SB_SIZE: usize = 1 << SB_SIZE_LOG2;
+const SUBPEL_FILTER_SIZE: usize = 8;
+const FRAME_MARGIN: usize = 16 + SUBPEL_FILTER_SIZE;
+/// The padding around the luma plane of frames given to the detector.
+pub(crate) const LUMA_PADDING: usize = SB_SIZE + FRAME_MARGIN;
+
+/// A rectangle of the luma plane to analyze, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CropRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl CropRect {
+    /// Converts a rectangle of the frame to the same area of the frames
+    /// with `source_details`, which are the fields of the frame if their
+    /// height is half of it, and clamps it to lie within them.
+    pub(crate) fn for_source(self, details: &VideoDetails, source_details: &VideoDetails) -> Self {
+        let (y, height) = if source_details.height < details.height {
+            (self.y / 2, self.height / 2)
+        } else {
+            (self.y, self.height)
+        };
+        let x = self.x.min(source_details.width - 1);
+        let y = y.min(source_details.height - 1);
+        CropRect {
+            x,
+            y,
+            width: self.width.clamp(1, source_details.width - x),
+            height: height.clamp(1, source_details.height - y),
+        }
+    }
+}
+
+/// Returns the details of the frames cropped to `crop`, which are monochrome.
+pub(crate) fn crop_details(details: &VideoDetails, crop: &CropRect) -> VideoDetails {
+    VideoDetails {
+        width: crop.width,
+        height: crop.height,
+        chroma_sampling: ChromaSampling::Cs400,
+        chroma_sample_position: ChromaSamplePosition::Unknown,
+        ..*details
+    }
+}
+
+/// Copies the area of the luma plane within `crop`
+/// into a monochrome frame, which must lie within the frame.
+pub(crate) fn crop_luma<T: Pixel>(frame: &Frame<T>, crop: &CropRect) -> Frame<T> {
+    let mut out: Frame<T> =
+        Frame::new_with_padding(crop.width, crop.height, ChromaSampling::Cs400, LUMA_PADDING);
+    let stride = out.planes[0].cfg.stride;
+    let data = out.planes[0].data_origin_mut();
+    for y in 0..crop.height {
+        let row = metrics::row(&frame.planes[0], crop.y + y, crop.x + crop.width);
+        data[y * stride..y * stride + crop.width].copy_from_slice(&row[crop.x..]);
+    }
+    out
+}
+
+/// Returns the details of the frames given to the detector,
+/// which are monochrome and downscaled by `downscale` if it is greater than 1.
+pub(crate) fn analysis_details(details: &VideoDetails, downscale: Option<usize>) -> VideoDetails {
+    match downscale {
+        Some(factor) if factor > 1 => VideoDetails {
+            width: (details.width / factor).max(1),
+            height: (details.height / factor).max(1),
+            chroma_sampling: ChromaSampling::Cs400,
+            ..*details
+        },
+        _ => *details,
+    }
+}
+
+/// Returns the details of the fields given to the detector instead of
+/// whole frames, if the video is interlaced and `field_handling` applies.
+pub(crate) fn field_details(
+    details: &VideoDetails,
+    field_handling: FieldHandling,
+) -> Option<VideoDetails> {
+    if details.field_order == FieldOrder::Progressive {
+        return None;
+    }
+    let height = match field_handling {
+        FieldHandling::Weave => return None,
+        FieldHandling::Bob => details.height / 2 * 2,
+        FieldHandling::FirstField => details.height / 2,
+    };
+    Some(VideoDetails {
+        height: height.max(1),
+        chroma_sampling: ChromaSampling::Cs400,
+        chroma_sample_position: ChromaSamplePosition::Unknown,
+        field_order: FieldOrder::Progressive,
+        ..*details
+    })
+}
+
+/// Copies the luma of the first field of an interlaced frame into a
+/// monochrome frame with `field_details`, repeating each line of the field
+/// if the field is to be as tall as the frame.
+pub(crate) fn extract_field<T: Pixel>(
+    frame: &Frame<T>,
+    details: &VideoDetails,
+    field_details: &VideoDetails,
+) -> Frame<T> {
+    let first_line = if details.field_order == FieldOrder::BottomFieldFirst {
+        1
+    } else {
+        0
+    };
+    let line_doubled = field_details.height > details.height / 2;
+    let mut out: Frame<T> = Frame::new_with_padding(
+        field_details.width,
+        field_details.height,
+        ChromaSampling::Cs400,
+        LUMA_PADDING,
+    );
+    let stride = out.planes[0].cfg.stride;
+    let data = out.planes[0].data_origin_mut();
+    for y in 0..field_details.height {
+        let field_line = if line_doubled { y / 2 } else { y };
+        let src_y = (first_line + 2 * field_line).min(details.height - 1);
+        data[y * stride..y * stride + details.width].copy_from_slice(metrics::row(
+            &frame.planes[0],
+            src_y,
+            details.width,
+        ));
+    }
+    out
+}
+
+/// Downscales the luma plane of a frame by `factor` in each dimension
+/// using a box filter, returning a monochrome frame.
+pub(crate) fn downscale_luma<T: Pixel>(
+    frame: &Frame<T>,
+    details: &VideoDetails,
+    factor: usize,
+) -> Frame<T> {
+    let scaled = analysis_details(details, Some(factor));
+    let mut out: Frame<T> = Frame::new_with_padding(
+        scaled.width,
+        scaled.height,
+        ChromaSampling::Cs400,
+        LUMA_PADDING,
+    );
+    let stride = out.planes[0].cfg.stride;
+    let data = out.planes[0].data_origin_mut();
+    for y in 0..scaled.height {
+        // Each output pixel averages the input pixels it covers,
+        // clamped to the input for frames smaller than `factor`
+        let rows = (y * factor..((y + 1) * factor).min(details.height))
+            .map(|src_y| metrics::row(&frame.planes[0], src_y, details.width))
+            .collect::<Vec<_>>();
+        for x in 0..scaled.width {
+            let columns = x * factor..((x + 1) * factor).min(details.width);
+            let mut sum = 0u32;
+            for row in &rows {
+                sum += row[columns.clone()]
+                    .iter()
+                    .map(|&px| Into::<u32>::into(px))
+                    .sum::<u32>();
+            }
+            let count = (rows.len() * columns.len()) as u32;
+            data[y * stride + x] = T::cast_from((sum + count / 2) / count);
+        }
+    }
+    out
+}
+
+/// Converts a frame to packed 8-bit RGB, assuming BT.709 limited range input.
+pub(crate) fn frame_to_rgb<T: Pixel>(frame: &Frame<T>, details: &VideoDetails) -> Vec<u8> {
+    let shift = details.bit_depth - 8;
+    let to_8bit = |px: T| (Into::<u32>::into(px) >> shift) as f32;
+    let luma = &frame.planes[0];
+    let mut rgb = Vec::with_capacity(details.width * details.height * 3);
+
+    if details.chroma_sampling == ChromaSampling::Cs400 {
+        for y in 0..details.height {
+            for x in 0..details.width {
+                let gray = clamp_u8((to_8bit(luma.p(x, y)) - 16.0) * 1.164_383);
+                rgb.extend_from_slice(&[gray, gray, gray]);
+            }
+        }
+        return rgb;
+    }
+
+    let (u_plane, v_plane) = (&frame.planes[1], &frame.planes[2]);
+    let (xdec, ydec) = (u_plane.cfg.xdec, u_plane.cfg.ydec);
+    for y in 0..details.height {
+        for x in 0..details.width {
+            let luma = (to_8bit(luma.p(x, y)) - 16.0) * 1.164_383;
+            let u = to_8bit(u_plane.p(x >> xdec, y >> ydec)) - 128.0;
+            let v = to_8bit(v_plane.p(x >> xdec, y >> ydec)) - 128.0;
+            rgb.extend_from_slice(&[
+                clamp_u8(luma + 1.792_741 * v),
+                clamp_u8(luma - 0.213_249 * u - 0.532_909 * v),
+                clamp_u8(luma + 2.112_402 * u),
+            ]);
+        }
+    }
+    rgb
+}
+
+fn clamp_u8(val: f32) -> u8 {
+    val.round().clamp(0.0, 255.0) as u8
+}
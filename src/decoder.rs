@@ -0,0 +1,363 @@
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel, PixelType, Rational};
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// A source of video frames to run scene change detection on.
+pub trait Decoder {
+    /// Returns the properties of the video being decoded.
+    fn get_video_details(&self) -> VideoDetails;
+
+    /// Returns the total number of frames in the video, if known.
+    ///
+    /// This is used to estimate the time remaining in progress reports.
+    fn total_frames(&self) -> Option<usize> {
+        None
+    }
+
+    /// Decodes the next frame of the video.
+    ///
+    /// Returns `DecodeError::EndOfStream` once all frames have been read.
+    fn read_video_frame<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<T>, DecodeError>;
+
+    /// Decodes the next frame of the video into `frame`, which was allocated
+    /// for the same video details, such as a frame the detector is done with,
+    /// so that no new frame has to be allocated.
+    ///
+    /// The default implementation decodes a new frame and replaces `frame`.
+    /// Decoders which copy their output into frames should override this.
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        *frame = self.read_video_frame(video_details)?;
+        Ok(())
+    }
+
+    /// Skips the next `count` frames of the video.
+    ///
+    /// The default implementation decodes and discards them.
+    /// Decoders which can seek should override this.
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError> {
+        let video_details = self.get_video_details();
+        for _ in 0..count {
+            if video_details.bit_depth > 8 {
+                self.read_video_frame::<u16>(&video_details)?;
+            } else {
+                self.read_video_frame::<u8>(&video_details)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the gaps found in the video since the last call, such as
+    /// frames which were skipped because they failed to decode, or missing
+    /// from the timestamps of the container, with the frames numbered
+    /// as returned by this decoder.
+    ///
+    /// The default implementation returns no gaps, for decoders
+    /// which cannot detect them.
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        Vec::new()
+    }
+
+    /// Returns the properties the container or codec gave
+    /// the last frame which was read, if any.
+    ///
+    /// The default implementation returns `None`, for decoders
+    /// whose input has no per-frame properties.
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        None
+    }
+}
+
+/// A `Decoder` whose concrete type is chosen at runtime,
+/// such as when selecting between several backends based on user input.
+pub struct BoxedDecoder {
+    inner: Box<dyn ErasedDecoder>,
+}
+
+impl BoxedDecoder {
+    pub fn new<D: Decoder + Send + 'static>(dec: D) -> Self {
+        BoxedDecoder {
+            inner: Box::new(dec),
+        }
+    }
+}
+
+impl Decoder for BoxedDecoder {
+    fn get_video_details(&self) -> VideoDetails {
+        self.inner.get_video_details()
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.inner.total_frames()
+    }
+
+    fn read_video_frame<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<T>, DecodeError> {
+        let frame: Box<dyn Any> = match T::type_enum() {
+            PixelType::U8 => Box::new(self.inner.read_video_frame_u8(video_details)?),
+            PixelType::U16 => Box::new(self.inner.read_video_frame_u16(video_details)?),
+        };
+        Ok(*frame
+            .downcast::<Frame<T>>()
+            .expect("Pixel type should match its type_enum"))
+    }
+
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        let frame: &mut dyn Any = frame;
+        match T::type_enum() {
+            PixelType::U8 => self.inner.read_video_frame_into_u8(
+                video_details,
+                frame
+                    .downcast_mut()
+                    .expect("Pixel type should match its type_enum"),
+            ),
+            PixelType::U16 => self.inner.read_video_frame_into_u16(
+                video_details,
+                frame
+                    .downcast_mut()
+                    .expect("Pixel type should match its type_enum"),
+            ),
+        }
+    }
+
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError> {
+        self.inner.skip_frames(count)
+    }
+
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        self.inner.take_frame_gaps()
+    }
+
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        self.inner.frame_meta()
+    }
+}
+
+/// An object-safe version of `Decoder`,
+/// with a separate method for each pixel type.
+trait ErasedDecoder: Send {
+    fn get_video_details(&self) -> VideoDetails;
+    fn total_frames(&self) -> Option<usize>;
+    fn read_video_frame_u8(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<u8>, DecodeError>;
+    fn read_video_frame_u16(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<u16>, DecodeError>;
+    fn read_video_frame_into_u8(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<u8>,
+    ) -> Result<(), DecodeError>;
+    fn read_video_frame_into_u16(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<u16>,
+    ) -> Result<(), DecodeError>;
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError>;
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap>;
+    fn frame_meta(&self) -> Option<FrameMeta>;
+}
+
+impl<D: Decoder + Send> ErasedDecoder for D {
+    fn get_video_details(&self) -> VideoDetails {
+        Decoder::get_video_details(self)
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        Decoder::total_frames(self)
+    }
+
+    fn read_video_frame_u8(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<u8>, DecodeError> {
+        self.read_video_frame(video_details)
+    }
+
+    fn read_video_frame_u16(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<u16>, DecodeError> {
+        self.read_video_frame(video_details)
+    }
+
+    fn read_video_frame_into_u8(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<u8>,
+    ) -> Result<(), DecodeError> {
+        self.read_video_frame_into(video_details, frame)
+    }
+
+    fn read_video_frame_into_u16(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<u16>,
+    ) -> Result<(), DecodeError> {
+        self.read_video_frame_into(video_details, frame)
+    }
+
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError> {
+        Decoder::skip_frames(self, count)
+    }
+
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        Decoder::take_frame_gaps(self)
+    }
+
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        Decoder::frame_meta(self)
+    }
+}
+
+/// What a decoder does when part of the input fails to decode,
+/// such as a corrupt packet in a satellite or broadcast capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorPolicy {
+    /// Stop decoding with an error.
+    Fail,
+    /// Skip packets which the codec rejects, and continue with the next one.
+    SkipPacket,
+    /// Skip packets which the codec rejects, as well as frames
+    /// which fail to decode, and continue with the next one.
+    SkipFrame,
+}
+
+/// Properties of a decoded frame given by the container or codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameMeta {
+    /// The presentation timestamp of the frame, if known.
+    pub pts: Option<Duration>,
+    /// How long the frame is displayed for, if known.
+    pub duration: Option<Duration>,
+    /// Whether the source marks the frame as a keyframe,
+    /// which decoding can start from.
+    pub is_decoder_keyframe: bool,
+}
+
+/// Which video stream is decoded from an input with several,
+/// such as a multi-angle remux or a file with cover art.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum VideoStream {
+    /// The stream the decoder picks when none is selected.
+    #[default]
+    Default,
+    /// The video stream with this 0-based index among
+    /// the video streams of the input, not counting cover art.
+    Index(usize),
+    /// The video stream tagged with this language, such as `eng`.
+    Language(String),
+    /// The video stream with this title.
+    Title(String),
+}
+
+/// Frames missing from the decoded video, so that frame numbers after it
+/// no longer match the frame numbers of the container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameGap {
+    /// The 0-indexed frame number of the first frame decoded after the gap,
+    /// which the first missing frame would have had.
+    pub frameno: usize,
+    /// The number of frames missing.
+    pub missing: usize,
+}
+
+/// An error encountered while decoding a frame.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// There are no more frames to read.
+    EndOfStream,
+    /// The input is malformed or uses an unsupported format.
+    InvalidInput(String),
+    /// Reading from the underlying input failed.
+    Io(io::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::EndOfStream => write!(f, "End of stream"),
+            DecodeError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            DecodeError::Io(err) => write!(f, "I/O error: {}", err),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecodeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(err: io::Error) -> Self {
+        DecodeError::Io(err)
+    }
+}
+
+/// The properties of a video needed for scene change detection.
+#[derive(Debug, Clone, Copy)]
+pub struct VideoDetails {
+    /// The width of each frame in pixels.
+    pub width: usize,
+    /// The height of each frame in pixels.
+    pub height: usize,
+    /// The number of bits per sample.
+    pub bit_depth: usize,
+    pub chroma_sampling: ChromaSampling,
+    pub chroma_sample_position: ChromaSamplePosition,
+    /// The duration of a single frame, in seconds.
+    pub time_base: Rational,
+    /// Whether the frames are interlaced, and which of their fields comes first.
+    pub field_order: FieldOrder,
+}
+
+impl Default for VideoDetails {
+    fn default() -> Self {
+        VideoDetails {
+            width: 640,
+            height: 480,
+            bit_depth: 8,
+            chroma_sampling: ChromaSampling::Cs420,
+            chroma_sample_position: ChromaSamplePosition::Unknown,
+            time_base: Rational { num: 30, den: 1 },
+            field_order: FieldOrder::Progressive,
+        }
+    }
+}
+
+/// Whether a video is interlaced, and which field of each frame comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldOrder {
+    /// The frames are progressive, or the field order is unknown.
+    Progressive,
+    /// The frames are interlaced, with the field of even lines first.
+    TopFieldFirst,
+    /// The frames are interlaced, with the field of odd lines first.
+    BottomFieldFirst,
+}
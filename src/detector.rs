@@ -0,0 +1,73 @@
+use crate::decoder::VideoDetails;
+use crate::ecr::EcrDetector;
+use crate::histogram::{FrameScore, HistogramDetector};
+use crate::{build_detector, scenecut_distances, DetectionOptions, SceneDetectionSpeed};
+use rav1e::prelude::{Frame, Pixel};
+use rav1e::scenechange::SceneChangeDetector;
+use std::sync::Arc;
+
+/// The scene change detection algorithm selected by `SceneDetectionSpeed`.
+pub(crate) enum Detector<T: Pixel> {
+    Rav1e(Box<SceneChangeDetector<T>>),
+    Histogram(HistogramDetector),
+    EdgeChangeRatio(EcrDetector),
+}
+
+impl<T: Pixel> Detector<T> {
+    pub fn new(video_details: &VideoDetails, opts: &DetectionOptions) -> Self {
+        let (min_scenecut_distance, max_scenecut_distance) =
+            scenecut_distances(video_details, opts);
+        let min_scenecut_distance = min_scenecut_distance.unwrap_or(0);
+        let max_scenecut_distance = max_scenecut_distance.unwrap_or(usize::MAX);
+        match opts.analysis_speed {
+            SceneDetectionSpeed::Fast | SceneDetectionSpeed::Standard => {
+                Detector::Rav1e(Box::new(build_detector(video_details, opts)))
+            }
+            SceneDetectionSpeed::Histogram => Detector::Histogram(HistogramDetector::new(
+                *video_details,
+                min_scenecut_distance,
+                max_scenecut_distance,
+                opts.score_smoothing,
+            )),
+            SceneDetectionSpeed::EdgeChangeRatio => Detector::EdgeChangeRatio(EcrDetector::new(
+                *video_details,
+                min_scenecut_distance,
+                max_scenecut_distance,
+            )),
+        }
+    }
+
+    /// Determines whether frame `frameno`, the second frame of `frame_set`,
+    /// starts a new scene. Any further frames are used as lookahead.
+    pub fn analyze(
+        &mut self,
+        frame_set: &[Arc<Frame<T>>],
+        frameno: usize,
+        previous_keyframe: usize,
+    ) -> bool {
+        match self {
+            Detector::Rav1e(detector) => {
+                detector.analyze_next_frame(frame_set, frameno as u64, previous_keyframe as u64)
+            }
+            Detector::Histogram(detector) => detector.analyze_next_frame(
+                &[&frame_set[0], &frame_set[1]],
+                frameno,
+                previous_keyframe,
+            ),
+            Detector::EdgeChangeRatio(detector) => detector.analyze_next_frame(
+                &[&frame_set[0], &frame_set[1]],
+                frameno,
+                previous_keyframe,
+            ),
+        }
+    }
+
+    /// The score of the most recently analyzed frame,
+    /// if the algorithm computes one.
+    pub fn last_score(&self) -> Option<FrameScore> {
+        match self {
+            Detector::Histogram(detector) => detector.last_score(),
+            _ => None,
+        }
+    }
+}
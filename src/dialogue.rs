@@ -0,0 +1,48 @@
+use crate::fingerprint;
+
+/// The minimum number of scenes in an alternating pattern
+/// for it to be considered a dialogue sequence.
+const MIN_DIALOGUE_SCENES: usize = 4;
+
+/// A run of scenes alternating between two shots,
+/// such as the shot-reverse-shot coverage of a dialogue.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct DialogueSequence {
+    /// The 0-indexed frame numbers where each scene in the sequence starts.
+    ///
+    /// Scenes at even indices show one shot,
+    /// and scenes at odd indices show the other.
+    pub scene_changes: Vec<usize>,
+}
+
+/// Finds alternating A/B/A/B patterns in a list of scenes,
+/// given as the frame number and fingerprint of the first frame of each scene.
+pub(crate) fn find_dialogue_sequences(scenes: &[(usize, u64)]) -> Vec<DialogueSequence> {
+    let similar = |a: usize, b: usize| fingerprint::is_similar(scenes[a].1, scenes[b].1);
+
+    let mut sequences = Vec::new();
+    let mut start = 0;
+    while start + MIN_DIALOGUE_SCENES <= scenes.len() {
+        let mut end = start + 1;
+        if !similar(start, start + 1) {
+            end += 1;
+            while end < scenes.len() && similar(end, end - 2) && !similar(end, end - 1) {
+                end += 1;
+            }
+        }
+
+        if end - start >= MIN_DIALOGUE_SCENES {
+            sequences.push(DialogueSequence {
+                scene_changes: scenes[start..end]
+                    .iter()
+                    .map(|&(frameno, _)| frameno)
+                    .collect(),
+            });
+            start = end;
+        } else {
+            start += 1;
+        }
+    }
+    sequences
+}
@@ -0,0 +1,33 @@
+/// A run of consecutive frames which are (near-)duplicates of the first,
+/// such as a freeze frame, or a held drawing in animation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateFrameRun {
+    /// The 0-indexed frame number of the first frame,
+    /// which the rest of the run duplicates.
+    pub start_frame: usize,
+    /// The 0-indexed frame number after the last duplicate frame.
+    pub end_frame: usize,
+}
+
+/// Returns the runs of duplicate frames, given whether each frame
+/// of the clip duplicates the frame before it.
+pub(crate) fn duplicate_runs(is_duplicate: &[bool]) -> Vec<DuplicateFrameRun> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    for (frameno, &is_duplicate) in is_duplicate.iter().chain(Some(&false)).enumerate() {
+        match (is_duplicate, run_start) {
+            // The run starts at the frame being duplicated
+            (true, None) => run_start = Some(frameno.saturating_sub(1)),
+            (false, Some(start)) => {
+                runs.push(DuplicateFrameRun {
+                    start_frame: start,
+                    end_frame: frameno,
+                });
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    runs
+}
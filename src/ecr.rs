@@ -0,0 +1,177 @@
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{Frame, Pixel};
+use std::ops::RangeInclusive;
+
+/// The Sobel gradient magnitude, scaled to 8-bit input,
+/// above which a pixel is considered part of an edge.
+const EDGE_THRESHOLD: u32 = 128;
+/// The distance within which an edge is considered
+/// to be the same edge in the next frame.
+const DILATION_RADIUS: usize = 2;
+/// The edge change ratio at or above which a frame is a hard cut,
+/// or a gradual transition is confirmed.
+const CUT_THRESHOLD: f64 = 0.4;
+/// The edge change ratio at or above which a frame
+/// may be part of a gradual transition.
+const GRADUAL_THRESHOLD: f64 = 0.01;
+/// The range of lengths, in frames, of runs of frames
+/// above `GRADUAL_THRESHOLD` which are considered gradual transitions.
+const GRADUAL_FRAMES: RangeInclusive<usize> = 3..=60;
+
+/// Detects scene changes using the edge change ratio,
+/// the fraction of edges which appear or disappear between two frames.
+///
+/// Hard cuts are frames with a high edge change ratio. Gradual transitions,
+/// such as dissolves, show up as a run of frames with a small but elevated
+/// ratio, where the frames on either side of the run have a high ratio
+/// between them. The first frame after such a run is reported
+/// as the scene change, so that it falls at the end of the transition.
+pub(crate) struct EcrDetector {
+    video_details: VideoDetails,
+    min_scenecut_distance: usize,
+    max_scenecut_distance: usize,
+    /// The number of consecutive frames above `GRADUAL_THRESHOLD`,
+    /// and the edge map of the frame preceding them.
+    transition: Option<(usize, EdgeMap)>,
+    /// The most recently computed edge map and its dilation,
+    /// along with their frame number.
+    previous: Option<(usize, EdgeMap)>,
+}
+
+struct EdgeMap {
+    edges: Vec<bool>,
+    dilated: Vec<bool>,
+    count: usize,
+}
+
+impl EcrDetector {
+    pub fn new(
+        video_details: VideoDetails,
+        min_scenecut_distance: usize,
+        max_scenecut_distance: usize,
+    ) -> Self {
+        EcrDetector {
+            video_details,
+            min_scenecut_distance,
+            max_scenecut_distance,
+            transition: None,
+            previous: None,
+        }
+    }
+
+    /// Determines whether frame `frameno`, the second frame of `frame_set`,
+    /// starts a new scene.
+    pub fn analyze_next_frame<T: Pixel>(
+        &mut self,
+        frame_set: &[&Frame<T>],
+        frameno: usize,
+        previous_keyframe: usize,
+    ) -> bool {
+        let previous = match self.previous.take() {
+            Some((previous_frameno, edge_map)) if previous_frameno + 1 == frameno => edge_map,
+            _ => self.edge_map(frame_set[0]),
+        };
+        let current = self.edge_map(frame_set[1]);
+        let ratio = edge_change_ratio(&previous, &current);
+
+        let mut ends_transition = false;
+        if (GRADUAL_THRESHOLD..CUT_THRESHOLD).contains(&ratio) {
+            match &mut self.transition {
+                Some((frames, _)) => *frames += 1,
+                None => self.transition = Some((1, previous)),
+            }
+        } else if let Some((frames, start)) = self.transition.take() {
+            ends_transition = GRADUAL_FRAMES.contains(&frames)
+                && edge_change_ratio(&start, &current) >= CUT_THRESHOLD;
+        }
+        self.previous = Some((frameno, current));
+
+        let distance = frameno - previous_keyframe;
+        if distance < self.min_scenecut_distance {
+            return false;
+        }
+        if distance >= self.max_scenecut_distance {
+            return true;
+        }
+        ratio >= CUT_THRESHOLD || ends_transition
+    }
+
+    fn edge_map<T: Pixel>(&self, frame: &Frame<T>) -> EdgeMap {
+        let (width, height) = (self.video_details.width, self.video_details.height);
+        let shift = self.video_details.bit_depth - 8;
+        let rows = (0..height)
+            .map(|y| metrics::row(&frame.planes[0], y, width))
+            .collect::<Vec<_>>();
+        let px = |x: usize, y: usize| (Into::<u32>::into(rows[y][x]) >> shift) as i32;
+
+        let mut edges = vec![false; width * height];
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let gx = px(x + 1, y - 1) + 2 * px(x + 1, y) + px(x + 1, y + 1)
+                    - px(x - 1, y - 1)
+                    - 2 * px(x - 1, y)
+                    - px(x - 1, y + 1);
+                let gy = px(x - 1, y + 1) + 2 * px(x, y + 1) + px(x + 1, y + 1)
+                    - px(x - 1, y - 1)
+                    - 2 * px(x, y - 1)
+                    - px(x + 1, y - 1);
+                edges[y * width + x] = gx.unsigned_abs() + gy.unsigned_abs() > EDGE_THRESHOLD;
+            }
+        }
+        let count = edges.iter().filter(|&&edge| edge).count();
+        let dilated = dilate(&edges, width, height);
+        EdgeMap {
+            edges,
+            dilated,
+            count,
+        }
+    }
+}
+
+/// Expands each edge pixel to a square of `DILATION_RADIUS` around it.
+fn dilate(edges: &[bool], width: usize, height: usize) -> Vec<bool> {
+    let mut horizontal = vec![false; edges.len()];
+    for y in 0..height {
+        let row = &edges[y * width..(y + 1) * width];
+        for x in 0..width {
+            let start = x.saturating_sub(DILATION_RADIUS);
+            let end = (x + DILATION_RADIUS + 1).min(width);
+            horizontal[y * width + x] = row[start..end].iter().any(|&edge| edge);
+        }
+    }
+    let mut dilated = vec![false; edges.len()];
+    for y in 0..height {
+        let start = y.saturating_sub(DILATION_RADIUS);
+        let end = (y + DILATION_RADIUS + 1).min(height);
+        for x in 0..width {
+            dilated[y * width + x] = (start..end).any(|src_y| horizontal[src_y * width + x]);
+        }
+    }
+    dilated
+}
+
+/// Returns the larger of the fractions of edges entering
+/// and exiting between two frames, from `0.0` to `1.0`.
+fn edge_change_ratio(previous: &EdgeMap, current: &EdgeMap) -> f64 {
+    let fraction = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64
+        }
+    };
+    let entering = current
+        .edges
+        .iter()
+        .zip(&previous.dilated)
+        .filter(|&(&edge, &near)| edge && !near)
+        .count();
+    let exiting = previous
+        .edges
+        .iter()
+        .zip(&current.dilated)
+        .filter(|&(&edge, &near)| edge && !near)
+        .count();
+    fraction(entering, current.count).max(fraction(exiting, previous.count))
+}
@@ -0,0 +1,197 @@
+use crate::{DecodeError, DetectionResults};
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// An error which stopped detection before the end of the clip.
+///
+/// Every variant carries the results for the frames
+/// which were analyzed before the error occurred.
+#[derive(Debug)]
+pub enum DetectionError {
+    /// The decoder did not produce a frame in time,
+    /// such as when reading through a `TimeoutReader` or a `TimeoutDecoder`.
+    DecoderStalled {
+        /// The last frame which was analyzed before the stall, if any.
+        last_analyzed_frame: Option<usize>,
+        /// The results for the frames analyzed before the stall.
+        partial_results: Box<DetectionResults>,
+    },
+    /// The decoder failed partway through the clip,
+    /// such as on a corrupt frame or a read error.
+    DecodeFailed {
+        /// The error returned by the decoder.
+        error: DecodeError,
+        /// The last frame which was analyzed before the error, if any.
+        last_analyzed_frame: Option<usize>,
+        /// The results for the frames analyzed before the error.
+        partial_results: Box<DetectionResults>,
+    },
+    /// Writing the output failed partway through the clip,
+    /// such as when tagging a y4m stream whose reader exited.
+    OutputFailed {
+        /// The error returned by the writer.
+        error: io::Error,
+        /// The last frame which was analyzed before the error, if any.
+        last_analyzed_frame: Option<usize>,
+        /// The results for the frames analyzed before the error.
+        partial_results: Box<DetectionResults>,
+    },
+    /// The options could not be applied, so no frames were analyzed.
+    InvalidOptions {
+        /// Why the options could not be applied.
+        reason: String,
+        /// The empty results, since detection did not start.
+        partial_results: Box<DetectionResults>,
+    },
+}
+
+impl DetectionError {
+    /// Creates the error for a decoder error which stopped detection,
+    /// given the results for the frames analyzed before it.
+    pub(crate) fn from_decode_error(error: DecodeError, partial_results: DetectionResults) -> Self {
+        let last_analyzed_frame = partial_results
+            .frame_count
+            .checked_sub(1)
+            .map(|last| partial_results.first_frame + last);
+        let partial_results = Box::new(partial_results);
+        match error {
+            DecodeError::Io(ref err) if err.kind() == io::ErrorKind::TimedOut => {
+                DetectionError::DecoderStalled {
+                    last_analyzed_frame,
+                    partial_results,
+                }
+            }
+            error => DetectionError::DecodeFailed {
+                error,
+                last_analyzed_frame,
+                partial_results,
+            },
+        }
+    }
+
+    /// Creates the error for an output error which stopped detection,
+    /// given the results for the frames analyzed before it.
+    pub(crate) fn from_output_error(error: io::Error, partial_results: DetectionResults) -> Self {
+        DetectionError::OutputFailed {
+            error,
+            last_analyzed_frame: partial_results
+                .frame_count
+                .checked_sub(1)
+                .map(|last| partial_results.first_frame + last),
+            partial_results: Box::new(partial_results),
+        }
+    }
+
+    /// Creates the error for options which could not be applied.
+    pub(crate) fn from_invalid_options(reason: &str) -> Self {
+        DetectionError::InvalidOptions {
+            reason: reason.to_string(),
+            partial_results: Box::new(DetectionResults::default()),
+        }
+    }
+
+    /// The last frame which was analyzed before the error, if any.
+    pub fn last_analyzed_frame(&self) -> Option<usize> {
+        match self {
+            DetectionError::DecoderStalled {
+                last_analyzed_frame,
+                ..
+            }
+            | DetectionError::DecodeFailed {
+                last_analyzed_frame,
+                ..
+            }
+            | DetectionError::OutputFailed {
+                last_analyzed_frame,
+                ..
+            } => *last_analyzed_frame,
+            DetectionError::InvalidOptions { .. } => None,
+        }
+    }
+
+    /// The results for the frames analyzed before the error.
+    pub fn partial_results(&self) -> &DetectionResults {
+        match self {
+            DetectionError::DecoderStalled {
+                partial_results, ..
+            }
+            | DetectionError::DecodeFailed {
+                partial_results, ..
+            }
+            | DetectionError::OutputFailed {
+                partial_results, ..
+            }
+            | DetectionError::InvalidOptions {
+                partial_results, ..
+            } => partial_results,
+        }
+    }
+
+    /// Consumes the error, returning the results
+    /// for the frames analyzed before it.
+    pub fn into_partial_results(self) -> DetectionResults {
+        match self {
+            DetectionError::DecoderStalled {
+                partial_results, ..
+            }
+            | DetectionError::DecodeFailed {
+                partial_results, ..
+            }
+            | DetectionError::OutputFailed {
+                partial_results, ..
+            }
+            | DetectionError::InvalidOptions {
+                partial_results, ..
+            } => *partial_results,
+        }
+    }
+}
+
+impl fmt::Display for DetectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DetectionError::DecoderStalled {
+                last_analyzed_frame: Some(frameno),
+                ..
+            } => write!(f, "Decoder stalled after frame {}", frameno),
+            DetectionError::DecoderStalled { .. } => {
+                write!(f, "Decoder stalled before the first frame")
+            }
+            DetectionError::DecodeFailed {
+                error,
+                last_analyzed_frame: Some(frameno),
+                ..
+            } => write!(f, "Decoding failed after frame {}: {}", frameno, error),
+            DetectionError::DecodeFailed { error, .. } => {
+                write!(f, "Decoding failed before the first frame: {}", error)
+            }
+            DetectionError::OutputFailed {
+                error,
+                last_analyzed_frame: Some(frameno),
+                ..
+            } => write!(
+                f,
+                "Writing output failed after frame {}: {}",
+                frameno, error
+            ),
+            DetectionError::OutputFailed { error, .. } => {
+                write!(f, "Writing output failed before the first frame: {}", error)
+            }
+            DetectionError::InvalidOptions { reason, .. } => {
+                write!(f, "Invalid detection options: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for DetectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DetectionError::DecoderStalled { .. } => None,
+            DetectionError::DecodeFailed { error, .. } => Some(error),
+            DetectionError::OutputFailed { error, .. } => Some(error),
+            DetectionError::InvalidOptions { .. } => None,
+        }
+    }
+}
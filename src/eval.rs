@@ -0,0 +1,279 @@
+//! Evaluation of detected scene changes against reference annotations,
+//! such as for comparing the detection algorithms on an annotated corpus.
+
+use crate::DetectionResults;
+use std::error::Error;
+use std::fmt;
+
+/// How well the detected scene changes match a reference cut list.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Evaluation {
+    /// The number of detected scene changes matching a reference cut.
+    pub true_positives: usize,
+    /// The number of detected scene changes not matching any reference cut.
+    pub false_positives: usize,
+    /// The number of reference cuts not matched by any detected scene change.
+    pub false_negatives: usize,
+    /// The fraction of detected scene changes which match a reference cut,
+    /// or `1.0` if none were detected.
+    pub precision: f64,
+    /// The fraction of reference cuts which were detected,
+    /// or `1.0` if there are none.
+    pub recall: f64,
+    /// The harmonic mean of `precision` and `recall`.
+    pub f1: f64,
+}
+
+/// Compares the scene changes of `results` with the `reference` cuts,
+/// given as sorted 0-indexed frame numbers of the first frame of each shot.
+///
+/// A scene change matches a reference cut at most `tolerance` frames away,
+/// and each reference cut is matched at most once.
+/// The start of the first scene is not counted as a cut,
+/// and reference cuts outside the frames analyzed are ignored.
+pub fn evaluate(results: &DetectionResults, reference: &[usize], tolerance: usize) -> Evaluation {
+    let start = results.first_frame;
+    let end = start + results.frame_count;
+    let is_cut = |&frameno: &usize| frameno > start && frameno < end;
+    let detected = results
+        .scene_changes
+        .iter()
+        .copied()
+        .filter(is_cut)
+        .collect::<Vec<_>>();
+    let reference = reference.iter().copied().filter(is_cut).collect::<Vec<_>>();
+
+    // Matching each scene change to the earliest reference cut in range
+    // is optimal, since the windows of both lists are in the same order
+    let mut true_positives = 0;
+    let mut next_reference = 0;
+    for &cut in &detected {
+        while next_reference < reference.len() && reference[next_reference] + tolerance < cut {
+            next_reference += 1;
+        }
+        if next_reference < reference.len() && reference[next_reference] <= cut + tolerance {
+            true_positives += 1;
+            next_reference += 1;
+        }
+    }
+
+    let precision = ratio(true_positives, detected.len());
+    let recall = ratio(true_positives, reference.len());
+    Evaluation {
+        true_positives,
+        false_positives: detected.len() - true_positives,
+        false_negatives: reference.len() - true_positives,
+        precision,
+        recall,
+        f1: if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        },
+    }
+}
+
+fn ratio(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Parses a cut list of frame numbers, separated by whitespace or commas,
+/// such as the `scene_changes` of a previous run.
+///
+/// Lines starting with `#` are ignored. The cuts are returned sorted.
+pub fn parse_frame_list(text: &str) -> Result<Vec<usize>, AnnotationError> {
+    let mut cuts = Vec::new();
+    for (i, line) in annotation_lines(text) {
+        for field in line.split(|c: char| c == ',' || c.is_whitespace()) {
+            if field.is_empty() {
+                continue;
+            }
+            cuts.push(field.parse().map_err(|_| AnnotationError::new(i, line))?);
+        }
+    }
+    cuts.sort_unstable();
+    Ok(cuts)
+}
+
+/// Parses a shot list with the first and last frame of a shot on each line,
+/// as used by the BBC Planet Earth and RAI shot boundary datasets,
+/// and returns the first frame of each shot as the cuts.
+///
+/// Lines starting with `#` are ignored. The cuts are returned sorted.
+pub fn parse_shot_list(text: &str) -> Result<Vec<usize>, AnnotationError> {
+    let mut cuts = Vec::new();
+    for (i, line) in annotation_lines(text) {
+        let mut fields = line.split_whitespace();
+        let first_frame = match (fields.next(), fields.next(), fields.next()) {
+            (Some(first), Some(last), None) if last.parse::<usize>().is_ok() => first.parse().ok(),
+            _ => None,
+        };
+        cuts.push(first_frame.ok_or_else(|| AnnotationError::new(i, line))?);
+    }
+    cuts.sort_unstable();
+    Ok(cuts)
+}
+
+/// Returns the 0-indexed line numbers and contents of the lines
+/// of an annotation file which are not empty or comments.
+fn annotation_lines(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+/// A line of an annotation file which could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationError {
+    /// The 1-based number of the line.
+    pub line: usize,
+    /// The contents of the line.
+    pub text: String,
+}
+
+impl AnnotationError {
+    fn new(index: usize, text: &str) -> Self {
+        AnnotationError {
+            line: index + 1,
+            text: text.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for AnnotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid annotation on line {}: {}", self.line, self.text)
+    }
+}
+
+impl Error for AnnotationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::results;
+
+    fn counts(evaluation: Evaluation) -> (usize, usize, usize) {
+        (
+            evaluation.true_positives,
+            evaluation.false_positives,
+            evaluation.false_negatives,
+        )
+    }
+
+    #[test]
+    fn tolerance_window_is_inclusive() {
+        assert_eq!(
+            counts(evaluate(&results(&[0, 52], 100), &[50], 2)),
+            (1, 0, 0)
+        );
+        assert_eq!(
+            counts(evaluate(&results(&[0, 48], 100), &[50], 2)),
+            (1, 0, 0)
+        );
+        assert_eq!(
+            counts(evaluate(&results(&[0, 53], 100), &[50], 2)),
+            (0, 1, 1)
+        );
+    }
+
+    #[test]
+    fn detection_near_two_reference_cuts() {
+        // A single scene change within range of two reference cuts
+        // only matches one of them
+        let evaluation = evaluate(&results(&[0, 10], 100), &[9, 11], 1);
+        assert_eq!(counts(evaluation), (1, 0, 1));
+        assert_eq!((evaluation.precision, evaluation.recall), (1.0, 0.5));
+        // Matching 10 to 11 rather than 9 would leave 12 unmatched
+        let evaluation = evaluate(&results(&[0, 10, 12], 100), &[9, 11], 1);
+        assert_eq!(counts(evaluation), (2, 0, 0));
+        let evaluation = evaluate(&results(&[0, 10, 12], 100), &[11, 13], 1);
+        assert_eq!(counts(evaluation), (2, 0, 0));
+    }
+
+    #[test]
+    fn reference_cut_near_two_detections() {
+        let evaluation = evaluate(&results(&[0, 49, 51], 100), &[50], 1);
+        assert_eq!(counts(evaluation), (1, 1, 0));
+        assert_eq!((evaluation.precision, evaluation.recall), (0.5, 1.0));
+    }
+
+    #[test]
+    fn cuts_outside_analyzed_frames_are_ignored() {
+        // The start of the first scene is not a cut, but the last frame can be
+        let evaluation = evaluate(&results(&[0, 99], 100), &[0, 99, 100], 0);
+        assert_eq!(counts(evaluation), (1, 0, 0));
+        let clip = DetectionResults {
+            first_frame: 100,
+            ..results(&[100, 120], 50)
+        };
+        let evaluation = evaluate(&clip, &[50, 100, 121, 150], 1);
+        assert_eq!(counts(evaluation), (1, 0, 0));
+    }
+
+    #[test]
+    fn scores_without_cuts() {
+        let evaluation = evaluate(&results(&[0], 100), &[], 2);
+        assert_eq!(
+            (evaluation.precision, evaluation.recall, evaluation.f1),
+            (1.0, 1.0, 1.0)
+        );
+        let evaluation = evaluate(&results(&[0, 10], 100), &[50], 2);
+        assert_eq!(
+            (evaluation.precision, evaluation.recall, evaluation.f1),
+            (0.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn frame_list() {
+        assert_eq!(parse_frame_list(""), Ok(Vec::new()));
+        assert_eq!(
+            parse_frame_list("# cuts\n0, 24\n\n 48 12\n"),
+            Ok(vec![0, 12, 24, 48])
+        );
+    }
+
+    #[test]
+    fn malformed_frame_list() {
+        for line in ["24, x", "-5", "1.5", "12;13"] {
+            assert_eq!(
+                parse_frame_list(&format!("# cuts\n\n0\n{}\n", line)),
+                Err(AnnotationError {
+                    line: 4,
+                    text: line.to_string(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn shot_list() {
+        assert_eq!(parse_shot_list(""), Ok(Vec::new()));
+        assert_eq!(
+            parse_shot_list("# first last\n24 50\n0 23\n"),
+            Ok(vec![0, 24])
+        );
+    }
+
+    #[test]
+    fn malformed_shot_list() {
+        for line in ["0", "0 23 5", "0 x", "x 23", "-1 5", "0,23"] {
+            assert_eq!(
+                parse_shot_list(line),
+                Err(AnnotationError {
+                    line: 1,
+                    text: line.to_string(),
+                })
+            );
+        }
+        let error = parse_shot_list("0 23\n\n  24 x  \n").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid annotation on line 3: 24 x");
+    }
+}
@@ -0,0 +1,95 @@
+use crate::pool::ReusedFramePool;
+use crate::{
+    detect_scene_changes_with_cuts, Decoder, DetectionError, DetectionOptions, DetectionResults,
+    ProgressEvent, SceneCut,
+};
+use rav1e::prelude::Pixel;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc};
+
+/// An event reported during a detection pass.
+#[derive(Debug, Clone)]
+pub enum DetectionEvent {
+    /// A frame was analyzed.
+    FrameAnalyzed(ProgressEvent),
+    /// A scene change was detected.
+    ///
+    /// Scene changes are reported as soon as they are detected, so some
+    /// may still be removed or moved by flash suppression, the scenecut
+    /// distances or post-processing before detection finishes.
+    /// Scene changes near the end of the clip are decided with less
+    /// lookahead, and have `SceneCut::flush_decided` set.
+    CutDetected(SceneCut),
+    /// Detection finished, was cancelled or failed.
+    Finished {
+        /// The number of frames analyzed.
+        frame_count: usize,
+        /// The final scene changes.
+        scene_changes: Vec<usize>,
+        /// Whether detection was cancelled before the end of the clip.
+        cancelled: bool,
+        /// The error which stopped detection, if any.
+        error: Option<String>,
+    },
+}
+
+/// Receives the events of a detection pass,
+/// such as to forward them to a front-end running on another thread.
+pub trait EventSink {
+    /// Receives the next event, in the order the events occurred.
+    fn send(&self, event: DetectionEvent);
+}
+
+impl EventSink for mpsc::Sender<DetectionEvent> {
+    fn send(&self, event: DetectionEvent) {
+        // Detection continues if the receiver has hung up
+        let _ = mpsc::Sender::send(self, event);
+    }
+}
+
+impl EventSink for mpsc::SyncSender<DetectionEvent> {
+    fn send(&self, event: DetectionEvent) {
+        let _ = mpsc::SyncSender::send(self, event);
+    }
+}
+
+/// Runs through a video clip like `detect_scene_changes`, sending
+/// its progress and scene changes to `sink` as events instead of calling
+/// callbacks, followed by a `DetectionEvent::Finished` event.
+///
+/// Passing the sending half of a channel lets a front-end on another thread
+/// receive the events without sharing any state with the detector.
+pub fn detect_scene_changes_with_events<D: Decoder + Send, T: Pixel, S: EventSink + 'static>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    sink: S,
+    cancel_flag: Option<Arc<AtomicBool>>,
+) -> Result<DetectionResults, DetectionError> {
+    let sink = Rc::new(sink);
+    let progress_sink = Rc::clone(&sink);
+    let mut send_cut = |cut: SceneCut| sink.send(DetectionEvent::CutDetected(cut));
+    let result = detect_scene_changes_with_cuts::<D, T>(
+        dec,
+        opts,
+        Some(Box::new(move |event: &ProgressEvent| {
+            progress_sink.send(DetectionEvent::FrameAnalyzed(*event))
+        })),
+        None,
+        Some(&mut send_cut),
+        cancel_flag,
+        &mut ReusedFramePool::new(),
+    );
+
+    let (results, error) = match result {
+        Ok(ref results) => (results, None),
+        Err(ref err) => (err.partial_results(), Some(err.to_string())),
+    };
+    sink.send(DetectionEvent::Finished {
+        frame_count: results.frame_count,
+        scene_changes: results.scene_changes.clone(),
+        cancelled: results.cancelled,
+        error,
+    });
+    result
+}
@@ -0,0 +1,421 @@
+//! Conversion of detection results into common cut-list formats.
+//!
+//! Timestamps are computed from frame numbers with the `FrameTiming` of the video,
+//! so that they round consistently regardless of the frame rate and follow
+//! the timestamps of variable frame rate video. The end of the last scene
+//! of a variable frame rate video is extrapolated from the duration of its
+//! last listed frame if the timestamp list ends there.
+
+use crate::timing::FrameTiming;
+use crate::DetectionResults;
+use std::fmt::{Display, Write};
+
+impl DetectionResults {
+    /// Formats the scenes as Matroska chapter XML, with one chapter per scene,
+    /// which can be muxed into an MKV file with `mkvmerge --chapters`.
+    ///
+    /// Each chapter's UID is its 1-based scene index,
+    /// so that it can be targeted by `to_matroska_tags`.
+    pub fn to_matroska_chapters(&self, timing: &FrameTiming) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(out, r#"<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">"#).unwrap();
+        writeln!(out, "<Chapters>").unwrap();
+        writeln!(out, "  <EditionEntry>").unwrap();
+        for (i, scene) in self.scenes().iter().enumerate() {
+            writeln!(out, "    <ChapterAtom>").unwrap();
+            writeln!(out, "      <ChapterUID>{}</ChapterUID>", i + 1).unwrap();
+            writeln!(
+                out,
+                "      <ChapterTimeStart>{}</ChapterTimeStart>",
+                format_nanos(timing.frame_to_nanos(scene.start_frame))
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "      <ChapterTimeEnd>{}</ChapterTimeEnd>",
+                format_nanos(timing.frame_to_nanos(scene.end_frame))
+            )
+            .unwrap();
+            writeln!(out, "      <ChapterDisplay>").unwrap();
+            writeln!(
+                out,
+                "        <ChapterString>Scene {}</ChapterString>",
+                i + 1
+            )
+            .unwrap();
+            writeln!(out, "        <ChapterLanguage>eng</ChapterLanguage>").unwrap();
+            writeln!(out, "      </ChapterDisplay>").unwrap();
+            writeln!(out, "    </ChapterAtom>").unwrap();
+        }
+        writeln!(out, "  </EditionEntry>").unwrap();
+        writeln!(out, "</Chapters>").unwrap();
+        out
+    }
+
+    /// Formats per-scene metadata as Matroska tag XML,
+    /// which can be muxed into an MKV file with `mkvmerge --tags`
+    /// alongside the chapters from `to_matroska_chapters`.
+    ///
+    /// Each scene's tags target the chapter for that scene, and include
+    /// its index, frame range and representative frame,
+    /// as well as its checksum if computed with the `checksum` feature.
+    pub fn to_matroska_tags(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+        writeln!(out, r#"<!DOCTYPE Tags SYSTEM "matroskatags.dtd">"#).unwrap();
+        writeln!(out, "<Tags>").unwrap();
+        for (i, scene) in self.scenes().iter().enumerate() {
+            writeln!(out, "  <Tag>").unwrap();
+            writeln!(out, "    <Targets>").unwrap();
+            writeln!(out, "      <TargetTypeValue>30</TargetTypeValue>").unwrap();
+            writeln!(out, "      <ChapterUID>{}</ChapterUID>", i + 1).unwrap();
+            writeln!(out, "    </Targets>").unwrap();
+            write_simple_tag(&mut out, "SCENE_INDEX", i);
+            write_simple_tag(&mut out, "START_FRAME", scene.start_frame);
+            write_simple_tag(&mut out, "END_FRAME", scene.end_frame);
+            write_simple_tag(&mut out, "FRAME_COUNT", scene.len);
+            write_simple_tag(&mut out, "REPRESENTATIVE_FRAME", scene.representative_frame);
+            #[cfg(feature = "checksum")]
+            if let Some(checksum) = self
+                .scene_checksums
+                .iter()
+                .find(|checksum| checksum.start_frame == scene.start_frame)
+            {
+                write_simple_tag(
+                    &mut out,
+                    "XXH3_CHECKSUM",
+                    format!("{:016x}", checksum.checksum),
+                );
+            }
+            writeln!(out, "  </Tag>").unwrap();
+        }
+        writeln!(out, "</Tags>").unwrap();
+        out
+    }
+}
+
+fn write_simple_tag(out: &mut String, name: &str, value: impl Display) {
+    writeln!(out, "    <Simple>").unwrap();
+    writeln!(out, "      <Name>{}</Name>", name).unwrap();
+    writeln!(out, "      <String>{}</String>", value).unwrap();
+    writeln!(out, "    </Simple>").unwrap();
+}
+
+/// Formats the scene changes as an x264/x265 `--qpfile`,
+/// forcing a keyframe at the start of each scene.
+pub fn to_qpfile(results: &DetectionResults) -> String {
+    let mut out = String::new();
+    for frameno in &results.scene_changes {
+        writeln!(out, "{} I -1", frameno).unwrap();
+    }
+    out
+}
+
+/// Formats the start time, in seconds, of each scene after the first
+/// as a comma-separated list, for use with ffmpeg's
+/// `-segment_times` or `-force_key_frames` options.
+pub fn to_ffmpeg_segment_times(results: &DetectionResults, timing: &FrameTiming) -> String {
+    results
+        .scene_changes
+        .iter()
+        .filter(|&&frameno| frameno > 0)
+        .map(|&frameno| format_seconds(frame_to_millis(frameno, timing)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Formats the scene changes as an expression for ffmpeg's `select` filter,
+/// which selects the first frame of each scene.
+pub fn to_ffmpeg_select(results: &DetectionResults) -> String {
+    results
+        .scene_changes
+        .iter()
+        .map(|frameno| format!("eq(n\\,{})", frameno))
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// Formats the scenes as a CMX 3600 edit decision list,
+/// with one event per scene.
+///
+/// Timecodes are non-drop-frame, counting frames at
+/// the frame rate rounded to the nearest integer.
+/// For variable frame rate video, they count frames at the average frame rate
+/// from the timestamp of each frame.
+pub fn to_edl(results: &DetectionResults, timing: &FrameTiming, title: &str) -> String {
+    let fps = timing.rounded_fps();
+    let mut out = String::new();
+    writeln!(out, "TITLE: {}", title).unwrap();
+    writeln!(out, "FCM: NON-DROP FRAME").unwrap();
+    for (i, scene) in results.scenes().iter().enumerate() {
+        let (start, end) = (
+            format_timecode(timecode_frame(scene.start_frame, timing, fps), fps),
+            format_timecode(timecode_frame(scene.end_frame, timing, fps), fps),
+        );
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "{:03}  AX       V     C        {} {} {} {}",
+            i + 1,
+            start,
+            end,
+            start,
+            end
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Formats the scenes as a CSV list of named regions,
+/// which can be imported as markers or chapters by most editors.
+pub fn to_chapter_csv(results: &DetectionResults, timing: &FrameTiming) -> String {
+    let mut out = String::from("#,Name,Start,End,Length\n");
+    for (i, scene) in results.scenes().iter().enumerate() {
+        let (start, end) = (
+            frame_to_millis(scene.start_frame, timing),
+            frame_to_millis(scene.end_frame, timing),
+        );
+        writeln!(
+            out,
+            "{},Scene {},{},{},{}",
+            i + 1,
+            i + 1,
+            format_timestamp(start),
+            format_timestamp(end),
+            format_timestamp(end - start)
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// Formats the scenes as an av1an `scenes.json` file,
+/// which av1an can use instead of running its own scene detection.
+///
+/// If `extra_split` is set, scenes longer than it are also split
+/// into the fewest scenes of equal length which are at most
+/// `extra_split` frames long, as av1an's `--extra-split` would.
+/// These are listed in `split_scenes`, alongside the unsplit `scenes`.
+pub fn to_av1an_scenes(results: &DetectionResults, extra_split: Option<usize>) -> String {
+    let scenes = results.scenes();
+    let split_scenes = scenes
+        .iter()
+        .flat_map(|scene| {
+            let parts = extra_split.map_or(1, |max_len| scene.len.div_ceil(max_len.max(1)));
+            (0..parts).map(move |i| {
+                (
+                    scene.start_frame + scene.len * i / parts,
+                    scene.start_frame + scene.len * (i + 1) / parts,
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = String::from("{\"scenes\":[");
+    write_av1an_scene_list(
+        &mut out,
+        scenes
+            .iter()
+            .map(|scene| (scene.start_frame, scene.end_frame)),
+    );
+    out.push_str("],\"split_scenes\":[");
+    write_av1an_scene_list(&mut out, split_scenes.into_iter());
+    write!(
+        out,
+        "],\"frames\":{}}}",
+        results.first_frame + results.frame_count
+    )
+    .unwrap();
+    out
+}
+
+fn write_av1an_scene_list(out: &mut String, scenes: impl Iterator<Item = (usize, usize)>) {
+    for (i, (start_frame, end_frame)) in scenes.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(
+            out,
+            "{{\"start_frame\":{},\"end_frame\":{},\"zone_overrides\":null}}",
+            start_frame, end_frame
+        )
+        .unwrap();
+    }
+}
+
+/// Converts a frame number to the nearest millisecond.
+fn frame_to_millis(frameno: usize, timing: &FrameTiming) -> u64 {
+    (timing.frame_to_nanos(frameno) + 500_000) / 1_000_000
+}
+
+/// Returns the frame a timecode counting `fps` frames per second
+/// gives for a frame, which is the frame itself at a constant frame rate.
+fn timecode_frame(frameno: usize, timing: &FrameTiming, fps: usize) -> usize {
+    match timing {
+        FrameTiming::Constant { .. } => frameno,
+        FrameTiming::Variable { .. } => {
+            ((u128::from(timing.frame_to_nanos(frameno)) * fps as u128 + 500_000_000)
+                / 1_000_000_000) as usize
+        }
+    }
+}
+
+/// Formats a timestamp as `HH:MM:SS.nnnnnnnnn`.
+fn format_nanos(nanos: u64) -> String {
+    let secs = nanos / 1_000_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:09}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        nanos % 1_000_000_000
+    )
+}
+
+fn format_seconds(millis: u64) -> String {
+    format!("{}.{:03}", millis / 1000, millis % 1000)
+}
+
+/// Formats a timestamp as `HH:MM:SS.mmm`.
+fn format_timestamp(millis: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Formats a frame number as an `HH:MM:SS:FF` timecode.
+fn format_timecode(frameno: usize, fps: usize) -> String {
+    let secs = frameno / fps;
+    format!(
+        "{:02}:{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        frameno % fps
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::results;
+    use rav1e::prelude::Rational;
+
+    /// 23.976 frames per second.
+    fn film_ntsc() -> FrameTiming {
+        FrameTiming::constant(Rational {
+            num: 1001,
+            den: 24000,
+        })
+    }
+
+    /// Frames lasting 40 and 60 milliseconds, with the end of the last frame
+    /// extrapolated from the duration of the frame before it.
+    fn variable() -> FrameTiming {
+        FrameTiming::variable(Rational { num: 1, den: 1000 }, vec![0, 40, 100, 140])
+    }
+
+    #[test]
+    fn matroska_chapters() {
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE Chapters SYSTEM "matroskachapters.dtd">
+<Chapters>
+  <EditionEntry>
+    <ChapterAtom>
+      <ChapterUID>1</ChapterUID>
+      <ChapterTimeStart>00:00:00.000000000</ChapterTimeStart>
+      <ChapterTimeEnd>00:00:01.001000000</ChapterTimeEnd>
+      <ChapterDisplay>
+        <ChapterString>Scene 1</ChapterString>
+        <ChapterLanguage>eng</ChapterLanguage>
+      </ChapterDisplay>
+    </ChapterAtom>
+    <ChapterAtom>
+      <ChapterUID>2</ChapterUID>
+      <ChapterTimeStart>00:00:01.001000000</ChapterTimeStart>
+      <ChapterTimeEnd>00:00:04.170833333</ChapterTimeEnd>
+      <ChapterDisplay>
+        <ChapterString>Scene 2</ChapterString>
+        <ChapterLanguage>eng</ChapterLanguage>
+      </ChapterDisplay>
+    </ChapterAtom>
+  </EditionEntry>
+</Chapters>
+"#;
+        assert_eq!(
+            results(&[0, 24], 100).to_matroska_chapters(&film_ntsc()),
+            expected
+        );
+    }
+
+    #[test]
+    fn qpfile() {
+        assert_eq!(
+            to_qpfile(&results(&[0, 24, 60], 100)),
+            "0 I -1\n24 I -1\n60 I -1\n"
+        );
+    }
+
+    #[test]
+    fn edl() {
+        let expected = "TITLE: clip
+FCM: NON-DROP FRAME
+
+001  AX       V     C        00:00:00:00 00:00:01:00 00:00:00:00 00:00:01:00
+
+002  AX       V     C        00:00:01:00 00:00:02:12 00:00:01:00 00:00:02:12
+";
+        assert_eq!(
+            to_edl(&results(&[0, 24], 60), &film_ntsc(), "clip"),
+            expected
+        );
+        // 21 frames per second on average, counted from each frame's timestamp
+        let expected = "TITLE: clip
+FCM: NON-DROP FRAME
+
+001  AX       V     C        00:00:00:00 00:00:00:02 00:00:00:00 00:00:00:02
+
+002  AX       V     C        00:00:00:02 00:00:00:04 00:00:00:02 00:00:00:04
+";
+        assert_eq!(to_edl(&results(&[0, 2], 4), &variable(), "clip"), expected);
+    }
+
+    #[test]
+    fn chapter_csv() {
+        let expected = "#,Name,Start,End,Length
+1,Scene 1,00:00:00.000,00:00:01.001,00:00:01.001
+2,Scene 2,00:00:01.001,00:00:02.503,00:00:01.502
+";
+        assert_eq!(
+            to_chapter_csv(&results(&[0, 24], 60), &film_ntsc()),
+            expected
+        );
+        let expected = "#,Name,Start,End,Length
+1,Scene 1,00:00:00.000,00:00:00.100,00:00:00.100
+2,Scene 2,00:00:00.100,00:00:00.180,00:00:00.080
+";
+        assert_eq!(to_chapter_csv(&results(&[0, 2], 4), &variable()), expected);
+    }
+
+    #[test]
+    fn av1an_scenes() {
+        let expected = concat!(
+            r#"{"scenes":["#,
+            r#"{"start_frame":0,"end_frame":24,"zone_overrides":null},"#,
+            r#"{"start_frame":24,"end_frame":60,"zone_overrides":null}"#,
+            r#"],"split_scenes":["#,
+            r#"{"start_frame":0,"end_frame":12,"zone_overrides":null},"#,
+            r#"{"start_frame":12,"end_frame":24,"zone_overrides":null},"#,
+            r#"{"start_frame":24,"end_frame":42,"zone_overrides":null},"#,
+            r#"{"start_frame":42,"end_frame":60,"zone_overrides":null}"#,
+            r#"],"frames":60}"#
+        );
+        assert_eq!(to_av1an_scenes(&results(&[0, 24], 60), Some(20)), expected);
+    }
+}
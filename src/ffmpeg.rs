@@ -0,0 +1,333 @@
+use crate::decoder::{DecodeError, Decoder, VideoDetails, VideoStream};
+use crate::timeout::TimeoutReader;
+use crate::y4m::Y4mDecoder;
+use rav1e::prelude::{Frame, Pixel};
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// Decodes any video the `ffmpeg` binary can read, by running it
+/// as a subprocess which writes y4m to a pipe, without linking to libav.
+///
+/// `ffmpeg` must be on the `PATH`. Its errors are written to stderr.
+/// If the video stream is selected by language or title,
+/// decoding fails if more than one stream matches.
+///
+/// Frames are rotated as given by the display matrix of the stream,
+/// such as for portrait phone footage, so the video details describe
+/// the frames as displayed.
+pub struct FfmpegPipeDecoder {
+    child: Child,
+    dec: Y4mDecoder<Box<dyn Read + Send>>,
+}
+
+/// Options for reading inputs with `FfmpegPipeDecoder`, such as
+/// network streams over HTTP, HLS or RTSP.
+#[derive(Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// The video stream to decode.
+    pub video_stream: VideoStream,
+    /// Options for the input passed to `ffmpeg` before the URL, without
+    /// the leading dash, such as `("rw_timeout", "5000000")` to time out
+    /// network reads after five seconds, or `("user_agent", "...")`.
+    pub input_options: Vec<(String, String)>,
+    /// The time after which a read from `ffmpeg` fails with
+    /// `io::ErrorKind::TimedOut` if no data arrives, if set,
+    /// the same as reading through a `TimeoutReader`.
+    pub stall_timeout: Option<Duration>,
+    /// The number of threads the video is decoded with,
+    /// or `None` to let `ffmpeg` pick one for the number of CPU cores.
+    pub threads: Option<usize>,
+    /// How decoding is split between threads, or `None` to use
+    /// frame threading where the codec supports it, and slice threading
+    /// otherwise. Codecs which support neither decode on one thread.
+    pub thread_type: Option<ThreadType>,
+    /// Whether frames are decoded as stored, without rotating them
+    /// as given by the display matrix, which saves time and rarely
+    /// changes the scene changes found.
+    pub skip_rotation: bool,
+    /// The `ffmpeg` pixel format frames are converted to, such as `yuv420p`
+    /// or `yuv420p10le`, or `None` to convert them to the nearest format
+    /// y4m can carry.
+    ///
+    /// Without it, formats such as NV12, P010, YUVJ420P and RGB are already
+    /// converted to planar YUV of the same bit depth. Formats with more than
+    /// 12 bits, such as `yuv420p16le` or `gray16le`, have no equivalent
+    /// this crate reads, and must be converted to one of at most 12 bits.
+    pub pixel_format: Option<String>,
+    /// The hardware decoding API to decode with, or `None` to decode in software.
+    ///
+    /// Decoded frames are copied back to system memory, usually as NV12
+    /// or P010, and converted like any other input. If the API is not
+    /// available or does not support the codec, `ffmpeg` falls back
+    /// to decoding in software.
+    pub hwaccel: Option<HwAccel>,
+    /// The device to decode on with `hwaccel`, such as `/dev/dri/renderD128`
+    /// for VAAPI or the index of the GPU for CUDA, or `None` for the default.
+    pub hwaccel_device: Option<String>,
+}
+
+/// How the decoder splits decoding between threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadType {
+    /// Decodes several frames at once, which is the fastest for most codecs,
+    /// at the cost of a delay of one frame per thread.
+    /// Codecs which do not support it decode on one thread.
+    Frame,
+    /// Decodes the slices of each frame at once, which can be faster
+    /// for intra-only codecs and files with many slices per frame.
+    Slice,
+}
+
+/// A hardware decoding API for `ffmpeg` to decode with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HwAccel {
+    /// Uses the first API which works on this system.
+    Auto,
+    /// VAAPI, for Intel and AMD GPUs on Linux.
+    Vaapi,
+    /// NVDEC, for NVIDIA GPUs through CUDA.
+    Cuda,
+    /// VideoToolbox, on macOS.
+    VideoToolbox,
+    /// Direct3D 11, on Windows.
+    D3d11va,
+    /// Intel Quick Sync Video.
+    Qsv,
+}
+
+impl HwAccel {
+    /// Returns the name `ffmpeg` uses for the API.
+    fn name(self) -> &'static str {
+        match self {
+            HwAccel::Auto => "auto",
+            HwAccel::Vaapi => "vaapi",
+            HwAccel::Cuda => "cuda",
+            HwAccel::VideoToolbox => "videotoolbox",
+            HwAccel::D3d11va => "d3d11va",
+            HwAccel::Qsv => "qsv",
+        }
+    }
+}
+
+impl FfmpegPipeDecoder {
+    /// Starts decoding the file at `path`, and reads the y4m header.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, DecodeError> {
+        Self::with_args(path, &[] as &[&str])
+    }
+
+    /// Starts decoding the file at `path` with additional output options
+    /// for `ffmpeg`, such as `["-vf", "extractplanes=y"]` to only decode luma,
+    /// and reads the y4m header.
+    pub fn with_args<P: AsRef<Path>, A: AsRef<OsStr>>(
+        path: P,
+        args: &[A],
+    ) -> Result<Self, DecodeError> {
+        Self::spawn(&file_url(path.as_ref()), args, &StreamOptions::default())
+    }
+
+    /// Starts decoding the file at `path` with options for the input,
+    /// such as which video stream to decode, and reads the y4m header.
+    pub fn with_options<P: AsRef<Path>>(
+        path: P,
+        options: &StreamOptions,
+    ) -> Result<Self, DecodeError> {
+        Self::spawn(&file_url(path.as_ref()), &[] as &[&str], options)
+    }
+
+    /// Starts decoding `url`, which can use any protocol `ffmpeg` supports,
+    /// such as `http`, `smb` or `file`, and reads the y4m header.
+    pub fn from_url(url: &str) -> Result<Self, DecodeError> {
+        Self::from_url_with_options(url, &StreamOptions::default())
+    }
+
+    /// Starts decoding `url` like `from_url`, with options for the stream.
+    pub fn from_url_with_options(url: &str, options: &StreamOptions) -> Result<Self, DecodeError> {
+        Self::spawn(OsStr::new(url), &[] as &[&str], options)
+    }
+
+    fn spawn<A: AsRef<OsStr>>(
+        input: &OsStr,
+        args: &[A],
+        options: &StreamOptions,
+    ) -> Result<Self, DecodeError> {
+        let input_options = options
+            .input_options
+            .iter()
+            .flat_map(|(key, value)| vec![format!("-{}", key), value.clone()])
+            .collect::<Vec<_>>();
+        // Decoder options are given before the input they apply to
+        let decoder_args = options
+            .threads
+            .map(|threads| vec!["-threads".to_string(), threads.to_string()])
+            .into_iter()
+            .chain(options.thread_type.map(|thread_type| {
+                let thread_type = match thread_type {
+                    ThreadType::Frame => "frame",
+                    ThreadType::Slice => "slice",
+                };
+                vec!["-thread_type".to_string(), thread_type.to_string()]
+            }))
+            .chain(
+                options
+                    .hwaccel
+                    .map(|hwaccel| vec!["-hwaccel".to_string(), hwaccel.name().to_string()]),
+            )
+            .chain(
+                options
+                    .hwaccel_device
+                    .as_ref()
+                    .filter(|_| options.hwaccel.is_some())
+                    .map(|device| vec!["-hwaccel_device".to_string(), device.clone()]),
+            )
+            .flatten()
+            .collect::<Vec<_>>();
+        let map_args = stream_specifier(&options.video_stream)
+            .map_or_else(Vec::new, |specifier| vec!["-map".to_string(), specifier]);
+        let pix_fmt_args = options
+            .pixel_format
+            .as_ref()
+            .map_or_else(Vec::new, |pix_fmt| {
+                vec!["-pix_fmt".to_string(), pix_fmt.clone()]
+            });
+        let mut child = Command::new("ffmpeg")
+            .args(&["-v", "error", "-nostdin"])
+            .args(&decoder_args)
+            .args(if options.skip_rotation {
+                &["-noautorotate"][..]
+            } else {
+                &[]
+            })
+            .args(&input_options)
+            .arg("-i")
+            .arg(input)
+            .args(&map_args)
+            .args(&pix_fmt_args)
+            .args(args)
+            // High bit depth y4m is not part of the standard format
+            .args(&["-f", "yuv4mpegpipe", "-strict", "-1", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(DecodeError::Io)?;
+        let stdout = child.stdout.take().unwrap();
+        let reader: Box<dyn Read + Send> = match options.stall_timeout {
+            Some(timeout) => Box::new(TimeoutReader::new(stdout, timeout)),
+            None => Box::new(stdout),
+        };
+        match Y4mDecoder::new(reader) {
+            Ok(dec) => Ok(FfmpegPipeDecoder { child, dec }),
+            // A stalled ffmpeg would never exit by itself
+            Err(DecodeError::Io(err)) if err.kind() == io::ErrorKind::TimedOut => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(DecodeError::Io(err))
+            }
+            // The header is missing if ffmpeg could not open the input
+            Err(DecodeError::EndOfStream) => {
+                Err(exit_error(&mut child).unwrap_or(DecodeError::EndOfStream))
+            }
+            // ffmpeg is still writing frames in a format which cannot be read
+            Err(DecodeError::InvalidInput(reason)) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(DecodeError::InvalidInput(format!(
+                    "{} (a supported pixel format can be set in the stream options)",
+                    reason
+                )))
+            }
+            Err(err) => Err(exit_error(&mut child).unwrap_or(err)),
+        }
+    }
+
+    /// Sets the total number of frames in the input, for use in progress reports.
+    pub fn with_total_frames(mut self, total_frames: usize) -> Self {
+        self.dec = self.dec.with_total_frames(total_frames);
+        self
+    }
+
+    /// Replaces the end of the stream with an error if `ffmpeg` failed,
+    /// so that decoding errors partway through are not mistaken for the end.
+    fn check_end(&mut self, err: DecodeError) -> DecodeError {
+        match err {
+            DecodeError::EndOfStream => exit_error(&mut self.child).unwrap_or(err),
+            err => err,
+        }
+    }
+}
+
+impl Decoder for FfmpegPipeDecoder {
+    fn get_video_details(&self) -> VideoDetails {
+        self.dec.get_video_details()
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.dec.total_frames()
+    }
+
+    fn read_video_frame<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<T>, DecodeError> {
+        self.dec
+            .read_video_frame(video_details)
+            .map_err(|err| self.check_end(err))
+    }
+
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        self.dec
+            .read_video_frame_into(video_details, frame)
+            .map_err(|err| self.check_end(err))
+    }
+}
+
+impl Drop for FfmpegPipeDecoder {
+    fn drop(&mut self) {
+        // Stop ffmpeg if detection ended before the end of the input
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Waits for `ffmpeg` to exit, returning an error if it failed.
+fn exit_error(child: &mut Child) -> Option<DecodeError> {
+    match child.wait() {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(DecodeError::InvalidInput(format!(
+            "ffmpeg exited with {}",
+            status
+        ))),
+        Err(err) => Some(DecodeError::Io(err)),
+    }
+}
+
+/// Returns the `ffmpeg` stream specifier selecting a video stream,
+/// or `None` to let `ffmpeg` pick one.
+fn stream_specifier(stream: &VideoStream) -> Option<String> {
+    // `V` only matches video streams which are not attached pictures,
+    // such as cover art
+    match stream {
+        VideoStream::Default => None,
+        VideoStream::Index(index) => Some(format!("0:V:{}", index)),
+        VideoStream::Language(language) => Some(format!("0:V:m:language:{}", language)),
+        VideoStream::Title(title) => Some(format!("0:V:m:title:{}", title)),
+    }
+}
+
+/// Returns the `file` protocol URL of a path, so that `ffmpeg` does not take
+/// the part before a colon in a file name for a protocol.
+///
+/// The path is passed through as is, without converting it to UTF-8,
+/// so that non-UTF-8 names, `\\?\` long paths and UNC paths on network shares
+/// are opened the same as by the operating system.
+fn file_url(path: &Path) -> OsString {
+    let mut url = OsString::from("file:");
+    url.push(path.as_os_str());
+    url
+}
@@ -0,0 +1,173 @@
+use crate::metrics;
+use rav1e::prelude::{Pixel, Plane};
+
+/// The largest number of differing bits between two fingerprints
+/// for the frames to be considered similar.
+pub(crate) const SIMILAR_FINGERPRINT_DISTANCE: u32 = 10;
+
+/// Perceptual hashes of a scene, for finding scenes repeated
+/// across videos, such as intros, recaps or commercials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneFingerprint {
+    /// The 0-indexed frame number where the scene starts.
+    pub start_frame: usize,
+    /// The 64-bit difference hash of the luma of the first frame of the scene.
+    pub first: u64,
+    /// The difference hash of the middle frame of the scene.
+    pub middle: u64,
+}
+
+impl SceneFingerprint {
+    /// Whether two scenes look alike, with few differing bits between
+    /// the hashes of both their first and their middle frames.
+    pub fn is_similar(&self, other: &SceneFingerprint) -> bool {
+        is_similar(self.first, other.first) && is_similar(self.middle, other.middle)
+    }
+}
+
+/// Computes a 64-bit difference hash of a plane.
+///
+/// The plane is averaged down to 9 by 8 blocks, and each bit records
+/// whether a block is brighter than its right-hand neighbor.
+/// Similar-looking frames produce fingerprints with few differing bits.
+pub(crate) fn dhash<T: Pixel>(plane: &Plane<T>, width: usize, height: usize) -> u64 {
+    const COLS: usize = 9;
+    const ROWS: usize = 8;
+
+    let mut sums = [0u64; COLS * ROWS];
+    let mut counts = [0u64; COLS * ROWS];
+    for y in 0..height {
+        let block_row = y * ROWS / height * COLS;
+        for (x, &px) in metrics::row(plane, y, width).iter().enumerate() {
+            let block = block_row + x * COLS / width;
+            sums[block] += u64::from(Into::<u32>::into(px));
+            counts[block] += 1;
+        }
+    }
+    let means = sums
+        .iter()
+        .zip(counts.iter())
+        .map(|(&sum, &count)| sum.checked_div(count).unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let mut hash = 0u64;
+    for y in 0..ROWS {
+        for x in 0..COLS - 1 {
+            hash <<= 1;
+            if means[y * COLS + x] > means[y * COLS + x + 1] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Whether two fingerprints are close enough
+/// for their frames to be considered the same shot.
+pub(crate) fn is_similar(a: u64, b: u64) -> bool {
+    (a ^ b).count_ones() <= SIMILAR_FINGERPRINT_DISTANCE
+}
+
+/// Returns the fingerprint of each scene, given the hash of each frame
+/// of a clip of `frame_count` frames.
+pub(crate) fn scene_fingerprints(
+    frame_hashes: &[u64],
+    scene_changes: &[usize],
+    frame_count: usize,
+) -> Vec<SceneFingerprint> {
+    scene_changes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &start_frame)| {
+            let end_frame = scene_changes.get(i + 1).copied().unwrap_or(frame_count);
+            Some(SceneFingerprint {
+                start_frame,
+                first: *frame_hashes.get(start_frame)?,
+                middle: *frame_hashes.get(start_frame + (end_frame - start_frame) / 2)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rav1e::prelude::{ChromaSampling, Frame};
+
+    const WIDTH: usize = 72;
+    const HEIGHT: usize = 16;
+
+    /// Hashes a plane with the given value at each column.
+    fn hash_columns(value: impl Fn(usize) -> u8) -> u64 {
+        let mut frame: Frame<u8> = Frame::new_with_padding(WIDTH, HEIGHT, ChromaSampling::Cs400, 0);
+        let stride = frame.planes[0].cfg.stride;
+        let data = frame.planes[0].data_origin_mut();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                data[y * stride + x] = value(x);
+            }
+        }
+        dhash(&frame.planes[0], WIDTH, HEIGHT)
+    }
+
+    #[test]
+    fn dhash_of_gradients() {
+        assert_eq!(hash_columns(|_| 128), 0);
+        assert_eq!(hash_columns(|x| x as u8), 0);
+        assert_eq!(hash_columns(|x| 255 - x as u8), u64::MAX);
+    }
+
+    #[test]
+    fn similarity_threshold() {
+        assert!(is_similar(0, (1 << SIMILAR_FINGERPRINT_DISTANCE) - 1));
+        assert!(!is_similar(
+            0,
+            (1 << (SIMILAR_FINGERPRINT_DISTANCE + 1)) - 1
+        ));
+
+        let scene = SceneFingerprint {
+            start_frame: 0,
+            first: 0,
+            middle: 0,
+        };
+        let different_middle = SceneFingerprint {
+            middle: u64::MAX,
+            ..scene
+        };
+        assert!(scene.is_similar(&scene));
+        assert!(!scene.is_similar(&different_middle));
+    }
+
+    #[test]
+    fn fingerprints_of_scenes() {
+        let hashes = [10, 11, 12, 13, 14];
+        assert!(scene_fingerprints(&[], &[], 0).is_empty());
+        assert_eq!(
+            scene_fingerprints(&hashes, &[0], 5),
+            [SceneFingerprint {
+                start_frame: 0,
+                first: 10,
+                middle: 12,
+            }]
+        );
+        // A scene starting at the last frame is its own middle frame
+        assert_eq!(
+            scene_fingerprints(&hashes, &[0, 4], 5),
+            [
+                SceneFingerprint {
+                    start_frame: 0,
+                    first: 10,
+                    middle: 12,
+                },
+                SceneFingerprint {
+                    start_frame: 4,
+                    first: 14,
+                    middle: 14,
+                }
+            ]
+        );
+        // Scenes without hashes are left out
+        assert_eq!(scene_fingerprints(&hashes[..3], &[0, 4], 5).len(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::decoder::VideoDetails;
+use crate::histogram;
+use rav1e::prelude::{Frame, Pixel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// The histogram difference below which the frames around a flash
+/// are considered the same scene by default.
+const FLASH_THRESHOLD: f64 = 0.2;
+/// The maximum length of a flash in frames by default.
+const MAX_FLASH_LENGTH: usize = 10;
+
+/// How flashes, such as strobes or camera flashes, are suppressed
+/// from the detected scene changes.
+///
+/// A flash is a scene change followed within `max_length` frames
+/// by another scene change back to content similar to that before the first.
+/// Both scene changes of a flash are removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
+pub struct FlashSuppression {
+    /// The histogram difference, from `0.0` to `1.0`, below which
+    /// the frames before and after a flash are considered the same scene.
+    pub threshold: f64,
+    /// The maximum length of a flash, in frames.
+    pub max_length: usize,
+}
+
+impl Default for FlashSuppression {
+    fn default() -> Self {
+        FlashSuppression {
+            threshold: FLASH_THRESHOLD,
+            max_length: MAX_FLASH_LENGTH,
+        }
+    }
+}
+
+/// Finds flashes as scene changes are detected,
+/// by comparing each scene change with the frames before recent ones.
+pub(crate) struct FlashTracker<T: Pixel> {
+    opts: FlashSuppression,
+    video_details: VideoDetails,
+    /// The recent scene changes, with the frame before each.
+    recent_cuts: VecDeque<(usize, Arc<Frame<T>>)>,
+    /// The scene changes found to start or end a flash.
+    flash_cuts: Vec<usize>,
+}
+
+impl<T: Pixel> FlashTracker<T> {
+    pub fn new(opts: FlashSuppression, video_details: VideoDetails) -> Self {
+        FlashTracker {
+            opts,
+            video_details,
+            recent_cuts: VecDeque::new(),
+            flash_cuts: Vec::new(),
+        }
+    }
+
+    /// Checks whether the scene change at `frameno` ends a flash.
+    pub fn push(&mut self, frameno: usize, previous: Option<&Arc<Frame<T>>>, frame: &Frame<T>) {
+        let max_length = self.opts.max_length;
+        self.recent_cuts
+            .retain(|&(cut, _)| frameno - cut <= max_length);
+
+        let flash_start = self.recent_cuts.iter().find(|(_, before)| {
+            histogram::frame_difference(before, frame, &self.video_details) < self.opts.threshold
+        });
+        if let Some(&(start, _)) = flash_start {
+            self.flash_cuts.push(start);
+            self.flash_cuts.push(frameno);
+            // The content after a flash is not compared with anything inside it
+            self.recent_cuts.clear();
+            return;
+        }
+        if let Some(previous) = previous {
+            self.recent_cuts.push_back((frameno, Arc::clone(previous)));
+        }
+    }
+
+    /// Returns `scene_changes` without the scene changes of any flashes.
+    pub fn remove_flashes(&self, scene_changes: &[usize]) -> Vec<usize> {
+        scene_changes
+            .iter()
+            .copied()
+            .filter(|frameno| !self.flash_cuts.contains(frameno))
+            .collect()
+    }
+}
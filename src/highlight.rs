@@ -0,0 +1,85 @@
+/// The normalized difference below which a frame
+/// is considered a duplicate of the one before it.
+const DUPLICATE_FRAME_THRESHOLD: f32 = 0.002;
+/// How many times the average motion of the clip
+/// the peak motion of a scene must reach to be considered a spike.
+const MIN_MOTION_SPIKE: f64 = 2.0;
+/// The minimum fraction of duplicate frames in a scene
+/// for it to be considered a slow-motion replay.
+const MIN_REPLAY_DUPLICATES: f64 = 0.25;
+
+/// A scene with a sudden spike in motion followed by a
+/// slow-motion segment, as is typical of a highlight and its replay.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct HighlightCandidate {
+    /// The 0-indexed frame number where the high-motion scene starts.
+    pub start_frame: usize,
+    /// The 0-indexed frame number where the replay-like scene starts.
+    pub replay_frame: usize,
+    /// The 0-indexed frame number after the last frame of the replay-like scene.
+    pub end_frame: usize,
+    /// How strongly the scenes resemble a highlight. Higher is more likely.
+    pub score: f64,
+}
+
+/// Finds highlight candidates, ranked from most to least likely.
+///
+/// `motion` holds the mean difference between each frame and the one before it,
+/// and `scene_changes` the frames where each scene starts.
+pub(crate) fn find_highlight_candidates(
+    motion: &[f32],
+    scene_changes: &[usize],
+) -> Vec<HighlightCandidate> {
+    if motion.len() < 2 {
+        return Vec::new();
+    }
+    let average_motion =
+        motion[1..].iter().map(|&m| f64::from(m)).sum::<f64>() / (motion.len() - 1) as f64;
+    if average_motion <= 0.0 {
+        return Vec::new();
+    }
+
+    let scene_bounds = scene_changes
+        .iter()
+        .zip(scene_changes[1..].iter().chain(Some(&motion.len())))
+        .map(|(&start, &end)| (start, end))
+        .collect::<Vec<_>>();
+    let mut candidates = scene_bounds
+        .windows(2)
+        .filter_map(|scenes| {
+            let (start_frame, replay_frame) = scenes[0];
+            let (_, end_frame) = scenes[1];
+
+            // The first frame of a scene differs from the previous scene,
+            // so it does not count towards the motion within the scene.
+            let peak_motion = motion[start_frame + 1..replay_frame]
+                .iter()
+                .fold(0.0f32, |peak, &m| peak.max(m));
+            let motion_spike = f64::from(peak_motion) / average_motion;
+
+            let replay = &motion[replay_frame + 1..end_frame];
+            if replay.is_empty() {
+                return None;
+            }
+            let duplicates = replay
+                .iter()
+                .filter(|&&m| m < DUPLICATE_FRAME_THRESHOLD)
+                .count() as f64
+                / replay.len() as f64;
+
+            if motion_spike >= MIN_MOTION_SPIKE && duplicates >= MIN_REPLAY_DUPLICATES {
+                Some(HighlightCandidate {
+                    start_frame,
+                    replay_frame,
+                    end_frame,
+                    score: motion_spike * duplicates,
+                })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    candidates
+}
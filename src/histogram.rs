@@ -0,0 +1,312 @@
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{ChromaSampling, Frame, Pixel, Plane};
+use std::collections::VecDeque;
+
+/// The number of histogram bins for the luma plane.
+const LUMA_BINS: usize = 64;
+/// The number of histogram bins for each chroma plane.
+const CHROMA_BINS: usize = 32;
+/// The minimum histogram difference, from `0.0` to `1.0`,
+/// for a frame to be considered a scene change.
+const MIN_DIFFERENCE: f64 = 0.25;
+/// How many times the smoothed difference of the preceding frames
+/// the difference of a frame must reach to be considered a scene change.
+const ADAPTIVE_RATIO: f64 = 2.5;
+/// The minimum histogram difference of either chroma plane, from `0.0` to `1.0`,
+/// for a frame to be considered a scene change by `ChromaDetector`.
+const MIN_CHROMA_DIFFERENCE: f64 = 0.35;
+
+/// How the differences of the frames preceding a frame are combined
+/// into the baseline its own difference is compared against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreSmoothing {
+    pub method: SmoothingMethod,
+    /// The number of preceding frames to combine.
+    pub window: usize,
+}
+
+impl Default for ScoreSmoothing {
+    fn default() -> Self {
+        ScoreSmoothing {
+            method: SmoothingMethod::Mean,
+            window: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SmoothingMethod {
+    Mean,
+    /// Ignores isolated spikes in the differences,
+    /// such as those caused by heavy grain or noise.
+    Median,
+}
+
+/// The score of a single analyzed frame.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameScore {
+    /// The 0-indexed frame number of the frame.
+    pub frameno: usize,
+    /// The difference of the frame from the frame before it, from `0.0` to `1.0`.
+    pub raw: f64,
+    /// The baseline which `raw` is compared against, such as
+    /// the smoothed difference of the frames before it.
+    pub smoothed: f64,
+}
+
+/// Detects scene changes by comparing the luma and chroma histograms
+/// of consecutive frames, similar to PySceneDetect's content detectors.
+///
+/// A frame is a scene change if its histogram difference from the previous
+/// frame is both above a fixed minimum, and well above the smoothed
+/// difference of the frames before it, so that sustained motion
+/// does not trigger scene changes.
+pub(crate) struct HistogramDetector {
+    video_details: VideoDetails,
+    min_scenecut_distance: usize,
+    max_scenecut_distance: usize,
+    smoothing: ScoreSmoothing,
+    recent_differences: VecDeque<f64>,
+    /// The most recently computed histograms, along with their frame number.
+    previous: Option<(usize, Vec<Vec<f64>>)>,
+    last_score: Option<FrameScore>,
+}
+
+impl HistogramDetector {
+    pub fn new(
+        video_details: VideoDetails,
+        min_scenecut_distance: usize,
+        max_scenecut_distance: usize,
+        smoothing: ScoreSmoothing,
+    ) -> Self {
+        HistogramDetector {
+            video_details,
+            min_scenecut_distance,
+            max_scenecut_distance,
+            smoothing,
+            recent_differences: VecDeque::with_capacity(smoothing.window),
+            previous: None,
+            last_score: None,
+        }
+    }
+
+    /// The score of the most recently analyzed frame.
+    pub fn last_score(&self) -> Option<FrameScore> {
+        self.last_score
+    }
+
+    /// Determines whether frame `frameno`, the second frame of `frame_set`,
+    /// starts a new scene.
+    pub fn analyze_next_frame<T: Pixel>(
+        &mut self,
+        frame_set: &[&Frame<T>],
+        frameno: usize,
+        previous_keyframe: usize,
+    ) -> bool {
+        let previous = match self.previous.take() {
+            Some((previous_frameno, histograms)) if previous_frameno + 1 == frameno => histograms,
+            _ => self.histograms(frame_set[0]),
+        };
+        let current = self.histograms(frame_set[1]);
+        let difference = histogram_difference(&previous, &current);
+        self.previous = Some((frameno, current));
+
+        let smoothed = self.smoothed_difference();
+        self.last_score = Some(FrameScore {
+            frameno,
+            raw: difference,
+            smoothed,
+        });
+        if self.smoothing.window > 0 {
+            if self.recent_differences.len() == self.smoothing.window {
+                self.recent_differences.pop_front();
+            }
+            self.recent_differences.push_back(difference);
+        }
+
+        let distance = frameno - previous_keyframe;
+        if distance < self.min_scenecut_distance {
+            return false;
+        }
+        if distance >= self.max_scenecut_distance {
+            return true;
+        }
+        difference >= MIN_DIFFERENCE && difference >= smoothed * ADAPTIVE_RATIO
+    }
+
+    /// Combines the differences of the preceding frames
+    /// into a baseline for the adaptive threshold.
+    fn smoothed_difference(&self) -> f64 {
+        let recent = &self.recent_differences;
+        if recent.is_empty() {
+            return 0.0;
+        }
+        match self.smoothing.method {
+            SmoothingMethod::Mean => recent.iter().sum::<f64>() / recent.len() as f64,
+            SmoothingMethod::Median => {
+                let mut sorted = recent.iter().copied().collect::<Vec<_>>();
+                sorted.sort_by(f64::total_cmp);
+                let len = sorted.len();
+                (sorted[(len - 1) / 2] + sorted[len / 2]) / 2.0
+            }
+        }
+    }
+
+    fn histograms<T: Pixel>(&self, frame: &Frame<T>) -> Vec<Vec<f64>> {
+        frame_histograms(frame, &self.video_details)
+    }
+}
+
+/// Detects scene changes from the chroma planes alone, to catch cuts
+/// between scenes with similar luma but different colors,
+/// which detectors only looking at luma miss.
+pub(crate) struct ChromaDetector {
+    video_details: VideoDetails,
+    /// The most recently computed chroma histograms, along with their frame number.
+    previous: Option<(usize, Vec<Vec<f64>>)>,
+}
+
+impl ChromaDetector {
+    /// Returns `None` for monochrome video, which has no chroma to compare.
+    pub fn new(video_details: VideoDetails) -> Option<Self> {
+        if video_details.chroma_sampling == ChromaSampling::Cs400 {
+            return None;
+        }
+        Some(ChromaDetector {
+            video_details,
+            previous: None,
+        })
+    }
+
+    /// Determines whether frame `frameno`, the second frame of `frame_set`,
+    /// differs enough in color from the first to start a new scene.
+    pub fn analyze_next_frame<T: Pixel>(
+        &mut self,
+        frame_set: &[&Frame<T>],
+        frameno: usize,
+    ) -> bool {
+        let previous = match self.previous.take() {
+            Some((previous_frameno, histograms)) if previous_frameno + 1 == frameno => histograms,
+            _ => self.histograms(frame_set[0]),
+        };
+        let current = self.histograms(frame_set[1]);
+        let difference = previous
+            .iter()
+            .zip(&current)
+            .map(|(a, b)| plane_difference(a, b))
+            .fold(0.0, f64::max);
+        self.previous = Some((frameno, current));
+        difference >= MIN_CHROMA_DIFFERENCE
+    }
+
+    fn histograms<T: Pixel>(&self, frame: &Frame<T>) -> Vec<Vec<f64>> {
+        let details = &self.video_details;
+        let (chroma_width, chroma_height) = details
+            .chroma_sampling
+            .get_chroma_dimensions(details.width, details.height);
+        frame.planes[1..]
+            .iter()
+            .map(|plane| {
+                histogram(
+                    plane,
+                    chroma_width,
+                    chroma_height,
+                    details.bit_depth,
+                    CHROMA_BINS,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the difference between the histograms of two frames, from `0.0` to `1.0`.
+pub(crate) fn frame_difference<T: Pixel>(
+    a: &Frame<T>,
+    b: &Frame<T>,
+    video_details: &VideoDetails,
+) -> f64 {
+    histogram_difference(
+        &frame_histograms(a, video_details),
+        &frame_histograms(b, video_details),
+    )
+}
+
+/// Computes the normalized histogram of each plane of a frame.
+fn frame_histograms<T: Pixel>(frame: &Frame<T>, details: &VideoDetails) -> Vec<Vec<f64>> {
+    let mut histograms = vec![histogram(
+        &frame.planes[0],
+        details.width,
+        details.height,
+        details.bit_depth,
+        LUMA_BINS,
+    )];
+    if details.chroma_sampling != ChromaSampling::Cs400 {
+        let (chroma_width, chroma_height) = details
+            .chroma_sampling
+            .get_chroma_dimensions(details.width, details.height);
+        for plane in &frame.planes[1..] {
+            histograms.push(histogram(
+                plane,
+                chroma_width,
+                chroma_height,
+                details.bit_depth,
+                CHROMA_BINS,
+            ));
+        }
+    }
+    histograms
+}
+
+fn histogram<T: Pixel>(
+    plane: &Plane<T>,
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    bins: usize,
+) -> Vec<f64> {
+    let shift = bit_depth - bins.trailing_zeros() as usize;
+    let mut counts = vec![0u32; bins];
+    for y in 0..height {
+        for &px in metrics::row(plane, y, width) {
+            // Samples above the bit depth, such as from a mislabeled source,
+            // are counted in the highest bin
+            let bin = (Into::<u32>::into(px) >> shift) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+    }
+    let total = (width * height) as f64;
+    counts.iter().map(|&count| count as f64 / total).collect()
+}
+
+/// Returns the difference between two sets of histograms, from `0.0` to `1.0`.
+/// Luma is weighted as heavily as both chroma planes together.
+fn histogram_difference(a: &[Vec<f64>], b: &[Vec<f64>]) -> f64 {
+    let luma = plane_difference(&a[0], &b[0]);
+    if a.len() == 1 {
+        return luma;
+    }
+    (2.0 * luma + plane_difference(&a[1], &b[1]) + plane_difference(&a[2], &b[2])) / 4.0
+}
+
+/// Returns the difference between two histograms of a plane, from `0.0` to `1.0`.
+fn plane_difference(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f64>() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_above_bit_depth_use_highest_bin() {
+        let mut plane = Plane::<u16>::new(4, 1, 0, 0, 0, 0);
+        plane.data_origin_mut()[..4].copy_from_slice(&[0, 1023, 1024, 4095]);
+        let counts = histogram(&plane, 4, 1, 10, 16);
+        assert_eq!(counts[0], 0.25);
+        assert_eq!(counts[15], 0.75);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::{DecodeError, Decoder, FieldOrder, VideoDetails};
+use crate::SceneFrame;
+use image::{ColorType, DynamicImage, GenericImageView, ImageError};
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel, Rational};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file extensions of images included in a sequence.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "exr", "tif", "tiff", "jpg", "jpeg", "bmp"];
+/// The bit depth high bit depth images are converted to,
+/// since the detector does not support full 16-bit samples.
+const HIGH_BIT_DEPTH: usize = 10;
+
+/// Decodes a numbered sequence of images in a directory,
+/// such as a PNG, EXR or TIFF frame dump, as monochrome video.
+///
+/// Images are ordered by the last number in their file names,
+/// and only their luma is analyzed. Images with more than
+/// 8 bits per channel, including floating point EXR, are read as 10-bit video.
+pub struct ImageSequenceDecoder {
+    files: Vec<PathBuf>,
+    next_file: usize,
+    video_details: VideoDetails,
+    /// The first image, which is decoded ahead of time to read the video details from.
+    first_image: Option<DynamicImage>,
+}
+
+impl ImageSequenceDecoder {
+    /// Lists the images in `dir`, and decodes the first one
+    /// to determine the video details.
+    ///
+    /// `framerate` is the number of frames per second,
+    /// which image sequences do not record.
+    pub fn new<P: AsRef<Path>>(dir: P, framerate: Rational) -> Result<Self, DecodeError> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_image = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            if is_image {
+                files.push(path);
+            }
+        }
+        files.sort_by_cached_key(|path| (frame_number(path), path.clone()));
+
+        let first_image = match files.first() {
+            Some(path) => image::open(path).map_err(map_image_error)?,
+            None => {
+                return Err(DecodeError::InvalidInput(
+                    "No images found in directory".to_string(),
+                ));
+            }
+        };
+        let color = first_image.color();
+        let bit_depth = if color.bytes_per_pixel() > color.channel_count() {
+            HIGH_BIT_DEPTH
+        } else {
+            8
+        };
+        let (width, height) = first_image.dimensions();
+        let video_details = VideoDetails {
+            width: width as usize,
+            height: height as usize,
+            bit_depth,
+            chroma_sampling: ChromaSampling::Cs400,
+            chroma_sample_position: ChromaSamplePosition::Unknown,
+            time_base: Rational::new(framerate.den, framerate.num),
+            field_order: FieldOrder::Progressive,
+        };
+        Ok(ImageSequenceDecoder {
+            files,
+            next_file: 0,
+            video_details,
+            first_image: Some(first_image),
+        })
+    }
+}
+
+impl Decoder for ImageSequenceDecoder {
+    fn get_video_details(&self) -> VideoDetails {
+        self.video_details
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        Some(self.files.len())
+    }
+
+    fn read_video_frame<T: Pixel>(&mut self, cfg: &VideoDetails) -> Result<Frame<T>, DecodeError> {
+        let path = match self.files.get(self.next_file) {
+            Some(path) => path,
+            None => return Err(DecodeError::EndOfStream),
+        };
+        let image = match self.first_image.take() {
+            Some(image) => image,
+            None => image::open(path).map_err(map_image_error)?,
+        };
+        self.next_file += 1;
+
+        let mut f: Frame<T> =
+            Frame::new_with_padding(cfg.width, cfg.height, ChromaSampling::Cs400, LUMA_PADDING);
+        if cfg.bit_depth > 8 {
+            let luma = image.to_luma16();
+            check_dimensions(path, luma.width(), luma.height(), cfg)?;
+            let samples = luma
+                .as_raw()
+                .iter()
+                .map(|&px| px >> (16 - cfg.bit_depth))
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>();
+            f.planes[0].copy_from_raw_u8(&samples, cfg.width * 2, 2);
+        } else {
+            let luma = image.to_luma8();
+            check_dimensions(path, luma.width(), luma.height(), cfg)?;
+            f.planes[0].copy_from_raw_u8(luma.as_raw(), cfg.width, 1);
+        }
+        Ok(f)
+    }
+
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError> {
+        // Each image can be read independently, so skipped images are never decoded
+        if count > 0 {
+            self.first_image = None;
+        }
+        self.next_file = (self.next_file + count).min(self.files.len());
+        Ok(())
+    }
+}
+
+impl SceneFrame {
+    /// Saves the frame as an image, such as a thumbnail for a shot browser,
+    /// in the format given by the extension of `path`, such as PNG or JPEG.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        image::save_buffer(
+            path,
+            &self.rgb,
+            self.width as u32,
+            self.height as u32,
+            ColorType::Rgb8,
+        )
+    }
+}
+
+/// Returns the last number in the file name of `path`, if any.
+fn frame_number(path: &Path) -> Option<u64> {
+    let stem = path.file_stem()?.to_str()?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    stem[start..end].parse().ok()
+}
+
+fn check_dimensions(
+    path: &Path,
+    width: u32,
+    height: u32,
+    cfg: &VideoDetails,
+) -> Result<(), DecodeError> {
+    if (width as usize, height as usize) != (cfg.width, cfg.height) {
+        return Err(DecodeError::InvalidInput(format!(
+            "{} is {}x{}, but the sequence is {}x{}",
+            path.display(),
+            width,
+            height,
+            cfg.width,
+            cfg.height
+        )));
+    }
+    Ok(())
+}
+
+fn map_image_error(err: ImageError) -> DecodeError {
+    match err {
+        ImageError::IoError(err) => DecodeError::Io(err),
+        err => DecodeError::InvalidInput(err.to_string()),
+    }
+}
@@ -0,0 +1,118 @@
+use crate::black::BLACK_FRAME_THRESHOLD;
+use crate::convert::CropRect;
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{Frame, Pixel};
+
+/// The number of frames sampled for black bars by default.
+const LETTERBOX_FRAMES: usize = 24;
+/// The minimum fraction of the frame the picture within the bars must cover,
+/// below which the sampled frames are assumed to be mostly dark,
+/// such as during a fade in, rather than letterboxed.
+const MIN_PICTURE_AREA: f64 = 0.25;
+
+/// How black bars around the picture are detected and excluded from analysis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
+pub struct LetterboxDetection {
+    /// The number of frames at the start of the clip sampled for black bars.
+    pub frames: usize,
+    /// The mean luma of a row or column, from `0.0` to `1.0`,
+    /// below which it is part of a black bar in every sampled frame.
+    pub threshold: f32,
+}
+
+impl Default for LetterboxDetection {
+    fn default() -> Self {
+        LetterboxDetection {
+            frames: LETTERBOX_FRAMES,
+            threshold: BLACK_FRAME_THRESHOLD,
+        }
+    }
+}
+
+/// Finds the black bars around the picture in the first frames of a clip.
+pub(crate) struct LetterboxDetector {
+    video_details: VideoDetails,
+    detection: LetterboxDetection,
+    /// The highest mean luma of each row in the frames sampled so far.
+    row_max: Vec<f32>,
+    /// The highest mean luma of each column in the frames sampled so far.
+    column_max: Vec<f32>,
+    frames_sampled: usize,
+}
+
+impl LetterboxDetector {
+    pub fn new(video_details: VideoDetails, detection: LetterboxDetection) -> Self {
+        LetterboxDetector {
+            video_details,
+            detection,
+            row_max: vec![0.0; video_details.height],
+            column_max: vec![0.0; video_details.width],
+            frames_sampled: 0,
+        }
+    }
+
+    /// Samples the next frame of the clip.
+    pub fn push<T: Pixel>(&mut self, frame: &Frame<T>) {
+        let VideoDetails {
+            width,
+            height,
+            bit_depth,
+            ..
+        } = self.video_details;
+        let max_value = ((1u64 << bit_depth) - 1) as f32;
+        let mut column_sums = vec![0u64; width];
+        for (y, row_max) in self.row_max.iter_mut().enumerate() {
+            let mut row_sum = 0u64;
+            for (&px, column_sum) in metrics::row(&frame.planes[0], y, width)
+                .iter()
+                .zip(column_sums.iter_mut())
+            {
+                let px = u64::from(Into::<u32>::into(px));
+                row_sum += px;
+                *column_sum += px;
+            }
+            *row_max = row_max.max(row_sum as f32 / width as f32 / max_value);
+        }
+        for (column_max, &sum) in self.column_max.iter_mut().zip(&column_sums) {
+            *column_max = column_max.max(sum as f32 / height as f32 / max_value);
+        }
+        self.frames_sampled += 1;
+    }
+
+    /// Whether enough frames have been sampled.
+    pub fn is_done(&self) -> bool {
+        self.frames_sampled >= self.detection.frames
+    }
+
+    /// Returns the area within the black bars of the frames sampled,
+    /// or `None` if there are no bars, or too little of the frame is left.
+    pub fn crop(&self) -> Option<CropRect> {
+        let threshold = self.detection.threshold;
+        let (y, height) = picture_span(&self.row_max, threshold)?;
+        let (x, width) = picture_span(&self.column_max, threshold)?;
+        let (frame_width, frame_height) = (self.video_details.width, self.video_details.height);
+        if width == frame_width && height == frame_height {
+            return None;
+        }
+        if ((width * height) as f64) < MIN_PICTURE_AREA * (frame_width * frame_height) as f64 {
+            return None;
+        }
+        Some(CropRect {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Returns the start and length of the span between the first and the last
+/// of `means` at or above `threshold`, if any.
+fn picture_span(means: &[f32], threshold: f32) -> Option<(usize, usize)> {
+    let start = means.iter().position(|&mean| mean >= threshold)?;
+    let end = means.iter().rposition(|&mean| mean >= threshold)? + 1;
+    Some((start, end - start))
+}
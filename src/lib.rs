@@ -1,19 +1,148 @@
 #![allow(clippy::too_many_arguments)]
 
+mod adbreak;
+mod batch;
+mod black;
+mod buffer;
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod convert;
+mod decoder;
+mod detector;
+mod dialogue;
+mod duplicate;
+mod ecr;
+mod error;
+pub mod eval;
+mod events;
+pub mod export;
+#[cfg(feature = "ffmpeg-cli")]
+mod ffmpeg;
+mod fingerprint;
+mod flash;
+mod highlight;
+mod histogram;
+#[cfg(feature = "images")]
+mod images;
+mod letterbox;
+#[cfg(feature = "ffmpeg-cli")]
+mod live;
+mod mask;
+mod memory;
+mod metrics;
+#[cfg(feature = "mkv")]
+mod mkv;
+mod multi;
+mod overlay;
+mod parallel;
+mod pipeline;
+mod placement;
+mod pool;
+mod postprocess;
+mod pulldown;
+#[cfg(any(feature = "capi", feature = "wasm"))]
+mod push;
+#[cfg(feature = "python")]
+mod python;
+mod range;
+mod raw;
+mod recurring;
+mod scenedetector;
+mod sceneframe;
+#[cfg(feature = "serialize")]
+mod schema;
+mod soak;
+mod split;
+mod stats;
+mod subsample;
+mod tag;
+#[cfg(test)]
+mod test_util;
+mod timeout;
+pub mod timing;
+mod transition;
+mod twopass;
+#[cfg(feature = "wasm")]
+mod wasm;
 mod y4m;
 
+pub use adbreak::AdBreakCandidate;
+pub use batch::{detect_scene_changes_batch, BatchProgressCallback, BatchProgressEvent};
+pub use black::{BlackFrameDetection, BlackFrameRun};
+pub use buffer::{BufferDecoder, FrameSender};
+#[cfg(feature = "checksum")]
+pub use checksum::SceneChecksum;
+pub use convert::CropRect;
+pub use decoder::{
+    BoxedDecoder, DecodeError, Decoder, ErrorPolicy, FieldOrder, FrameGap, FrameMeta, VideoDetails,
+    VideoStream,
+};
+pub use dialogue::DialogueSequence;
+pub use duplicate::DuplicateFrameRun;
+pub use error::DetectionError;
+pub use events::{detect_scene_changes_with_events, DetectionEvent, EventSink};
+#[cfg(feature = "ffmpeg-cli")]
+pub use ffmpeg::{FfmpegPipeDecoder, HwAccel, StreamOptions, ThreadType};
+pub use fingerprint::SceneFingerprint;
+pub use flash::FlashSuppression;
+pub use highlight::HighlightCandidate;
+pub use histogram::{FrameScore, ScoreSmoothing, SmoothingMethod};
+#[cfg(feature = "images")]
+pub use images::ImageSequenceDecoder;
+pub use letterbox::LetterboxDetection;
+#[cfg(feature = "ffmpeg-cli")]
+pub use live::{detect_scene_changes_live, LiveOptions};
+pub use mask::WeightMask;
+pub use memory::MemoryConfig;
+#[cfg(feature = "mkv")]
+pub use mkv::MkvDecoder;
+pub use multi::detect_scene_changes_multi;
+pub use overlay::{OverlayEvent, Region};
+pub use parallel::detect_scene_changes_parallel;
+pub use placement::{place_keyframes, GopConstraints};
+pub use postprocess::PostProcessStep;
+pub use pulldown::PulldownCadence;
 pub use rav1e::scenechange::SceneChangeDetector;
+pub use raw::RawDecoder;
+pub use recurring::{find_common_segments, CommonSegment, SegmentOccurrence};
+pub use scenedetector::{CutDecision, SceneDetector};
+#[cfg(feature = "serialize")]
+pub use schema::{DetectionRun, SCHEMA_VERSION};
+pub use soak::SoakReport;
+pub use split::{find_unachievable_splits, UnachievableSplit};
+pub use stats::{scene_statistics, SceneStatistics};
+pub use subsample::detect_scene_changes_subsampled;
+pub use tag::{tag_scene_changes, SCENE_CHANGE_PARAM};
+pub use timeout::{TimeoutDecoder, TimeoutReader};
+pub use transition::{TransitionEvent, TransitionKind};
+pub use twopass::detect_scene_changes_two_pass;
+pub use y4m::Y4mDecoder;
 
-use ::y4m::Decoder;
+#[cfg(feature = "checksum")]
+use checksum::SceneChecksummer;
+use flash::FlashTracker;
+use overlay::OverlayTracker;
+use parallel::{SceneLength, SceneLengthLimits};
+use pipeline::{DetectionPipeline, FrameDecision};
+use pool::ReusedFramePool;
 use rav1e::config::{CpuFeatureLevel, EncoderConfig};
-use rav1e::prelude::{Pixel, Sequence};
-use std::collections::{BTreeMap, BTreeSet};
-use std::io::Read;
-use std::sync::Arc;
-use std::time::Instant;
+use rav1e::prelude::{Frame, Pixel, Rational, Sequence};
+use sceneframe::MiddleFrameBuffer;
+use soak::SoakMonitor;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use timing::FrameTiming;
+use transition::TransitionTracker;
 
 /// Options determining how to run scene change detection.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(default))]
 pub struct DetectionOptions {
     /// The speed of detection algorithm to use.
     /// Slower algorithms are more accurate/better for use in encoders.
@@ -24,15 +153,247 @@ pub struct DetectionOptions {
     /// for use in an encoder.
     /// If you want a raw list of scene changes, you should disable this.
     pub detect_flashes: bool,
+    /// Enabling this will remove flashes from the scene changes
+    /// once they are detected, with a tunable threshold and flash length.
+    ///
+    /// Unlike `detect_flashes`, whose window is limited by `lookahead_distance`
+    /// and the detector's built-in maximum, this can remove longer flashes,
+    /// such as in strobe-heavy concert footage.
+    /// It is applied before `post_process`.
+    pub flash_suppression: Option<FlashSuppression>,
     /// The minimum distane between two scene changes.
     pub min_scenecut_distance: Option<usize>,
     /// The maximum distance between two scene changes.
     pub max_scenecut_distance: Option<usize>,
+    /// The minimum duration between two scene changes, in seconds.
+    ///
+    /// `detect_scene_changes` measures this with the timestamps of the frames
+    /// if the decoder reports one for every frame, so it is honored for
+    /// variable frame rate input. Otherwise, and in the other detection modes,
+    /// it is converted to a frame count using the nominal frame rate of the input.
+    /// It takes precedence over `min_scenecut_distance` if both are set.
+    pub min_scenecut_duration: Option<f64>,
+    /// The maximum duration between two scene changes, in seconds.
+    ///
+    /// This is measured the same way as `min_scenecut_duration`,
+    /// and takes precedence over `max_scenecut_distance` if both are set.
+    pub max_scenecut_duration: Option<f64>,
+    /// Which scene change is kept when two are closer
+    /// than the minimum distance between them.
+    ///
+    /// Policies other than `KeepFirst` are applied once detection has finished,
+    /// so per-scene results computed during detection describe
+    /// all scene changes found before the minimum distance was applied.
+    pub min_distance_policy: MinDistancePolicy,
     /// The distance to look ahead in the video
     /// for scene flash detection.
     ///
     /// Not used if `detect_flashes` is `false`.
     pub lookahead_distance: usize,
+    /// The maximum memory the frame buffers may use, in bytes.
+    ///
+    /// If set, the lookahead distance is lowered as needed to stay within it,
+    /// based on an estimate of the memory used by the frames held
+    /// during detection. This does not cover memory used internally
+    /// by the detector or the decoder. The chosen configuration is reported
+    /// in the results. Only used by `detect_scene_changes`.
+    pub max_memory: Option<usize>,
+    /// Enabling this will only look ahead by `lookahead_distance`
+    /// in sections with frequent scene changes, and stop looking ahead
+    /// during long scenes, which lowers memory usage and latency.
+    ///
+    /// The lookahead distance is chosen at each scene change,
+    /// so that the detector keeps its history within a scene: a scene
+    /// following a long scene is analyzed without lookahead, and any other
+    /// scene with the full lookahead distance. Flashes are still detected
+    /// within rapid cuts, but the first scene change after a long scene
+    /// is detected without lookahead.
+    pub adaptive_lookahead: bool,
+    /// Enabling this will export a coarse map of
+    /// which regions of the frame changed at each scene change.
+    ///
+    /// This is useful for diagnosing whether a scene change
+    /// was triggered by a full-frame change or by localized motion.
+    pub export_heatmaps: bool,
+    /// The minimum fraction of the frame, from `0.0` to `1.0`,
+    /// that must have changed for a scene change to be kept.
+    ///
+    /// This can be used to suppress scene changes where only a small
+    /// region of the frame changed, such as a picture-in-picture switch
+    /// or a lower-third graphic appearing. Scene changes forced by
+    /// `max_scenecut_distance` are never suppressed.
+    pub min_changed_area: Option<f64>,
+    /// Enabling this will report changes to persistent overlays,
+    /// such as score bugs or channel logos appearing or disappearing,
+    /// as separate events. These are never reported as scene changes.
+    pub detect_overlays: bool,
+    /// Enabling this will annotate runs of scenes alternating
+    /// between two shots, as in the shot-reverse-shot coverage
+    /// of a dialogue.
+    pub detect_dialogue: bool,
+    /// Enabling this will flag scenes with a sudden spike in motion
+    /// followed by a slow-motion, replay-like scene,
+    /// as candidates for sports highlights.
+    pub detect_highlights: bool,
+    /// Enabling this will flag likely commercial breaks,
+    /// based on runs of black frames and spikes in scene change density.
+    pub detect_ad_breaks: bool,
+    /// Enabling this will report runs of black frames, which often
+    /// delimit programs and commercials, whether or not they are
+    /// also detected as scene changes.
+    pub detect_black_frames: Option<BlackFrameDetection>,
+    /// The mean luma difference from the previous frame, from `0.0` to `1.0`,
+    /// at or below which a frame is considered a duplicate of it.
+    ///
+    /// Setting this will report runs of duplicate frames, such as
+    /// freeze frames, or held drawings in animation which can skew
+    /// scene statistics. A small value such as `0.002` tolerates
+    /// encoding noise in otherwise identical frames.
+    pub duplicate_frame_threshold: Option<f32>,
+    /// Enabling this will compute a checksum of the decoded luma
+    /// of each scene, which can be used to verify that chunked encodes
+    /// were built from exactly the frames the scene list describes.
+    #[cfg(feature = "checksum")]
+    pub compute_checksums: bool,
+    /// Enabling this will compute perceptual hashes of the first
+    /// and middle frames of each scene, which can be compared
+    /// to find scenes repeated across videos, such as intros or recaps.
+    pub compute_fingerprints: bool,
+    /// Which frames of each detected scene are given to the
+    /// scene frame callback of `detect_scene_changes`.
+    ///
+    /// The middle frame of a scene is only known once the scene ends,
+    /// so it is given at the next scene change or at the end of the clip.
+    /// The frames of the second half of the current scene are held until then,
+    /// so memory use grows with the length of the scene, which
+    /// `max_scenecut_distance` can bound. This is not counted by `max_memory`.
+    pub scene_frames: SceneFrames,
+    /// Enabling this will also detect scene changes from the chroma planes,
+    /// catching cuts between scenes with similar luma but different colors,
+    /// which are common in animated content.
+    /// Has no effect for monochrome input, such as from `Y4mDecoder::luma_only`.
+    pub detect_chroma_changes: bool,
+    /// Enabling this will report fades and dissolves as spans of frames.
+    /// These do not affect the reported scene changes.
+    pub detect_transitions: bool,
+    /// How the scores of preceding frames are smoothed into the baseline
+    /// of the adaptive threshold, which can be made more robust
+    /// for grainy sources.
+    ///
+    /// Only used by the `Histogram` algorithm.
+    pub score_smoothing: ScoreSmoothing,
+    /// Enabling this will record the raw and smoothed score of each frame,
+    /// which is useful for tuning `score_smoothing`.
+    ///
+    /// Only the `Histogram` algorithm records scores.
+    pub record_scores: bool,
+    /// Enabling this will also report the scene changes found without
+    /// flash detection or scenecut distances, alongside the scene changes
+    /// found with them, so that both can be had from a single run.
+    ///
+    /// This runs the detection algorithm twice per frame.
+    pub report_raw_scene_changes: bool,
+    /// The CPU features the detector may use.
+    pub cpu_features: CpuFeatures,
+    /// Enabling this will make the results depend only on the input,
+    /// so that they are identical across machines.
+    ///
+    /// The detector only uses its portable Rust implementation,
+    /// rather than the SIMD implementation for the current CPU,
+    /// which can round differently. This is considerably slower.
+    /// This overrides `cpu_features`, and `frame_time_budget` is ignored,
+    /// since it depends on timing.
+    pub deterministic: bool,
+    /// The time allowed for analyzing a single frame.
+    ///
+    /// If the analysis of several frames in a row exceeds this,
+    /// the fast algorithm is used for the rest of the clip
+    /// from the next scene change, which helps keep realtime pipelines
+    /// on schedule. Switching at a scene change keeps the detector's
+    /// history within the current scene.
+    /// Has no effect if `analysis_speed` is already `Fast`.
+    pub frame_time_budget: Option<Duration>,
+    /// The number of frames at the start of the input which are
+    /// only used to warm up the detector, and are not reported on.
+    ///
+    /// This is useful for analyzing a range which starts mid-content,
+    /// such as a chunk of a longer video: starting the input a few frames
+    /// before the range lets the analysis behave as if it had the full history.
+    /// Frame numbers in the results are relative to the first frame
+    /// after the warmup frames, which always starts a new scene.
+    pub warmup_frames: usize,
+    /// Downscales the luma plane by this factor in each dimension
+    /// before analysis, using a box filter. Chroma is not analyzed.
+    ///
+    /// For high resolution input, analyzing a quarter-resolution
+    /// luma plane is several times faster and nearly as accurate.
+    /// The detector's scores are averaged over the frame,
+    /// so its thresholds apply to the downscaled frames unchanged.
+    ///
+    /// A factor of 1 analyzes the frames at full resolution, as `None` does.
+    /// A factor of 0 is invalid, and makes detection fail
+    /// with `DetectionError::InvalidOptions`.
+    pub downscale: Option<usize>,
+    /// The area of the luma plane to analyze, such as the picture
+    /// within letterbox bars, clamped to lie within the frame.
+    ///
+    /// Black bars dilute the difference between frames, hiding cuts,
+    /// and channel logos or timestamps can be excluded the same way.
+    /// Chroma is not analyzed if this is set. For interlaced input split
+    /// into fields, this is given in the lines of the whole frame.
+    pub crop: Option<CropRect>,
+    /// Setting this will find black bars around the picture in the first
+    /// frames of the clip, and crop them as with `crop`, unless it is set.
+    ///
+    /// The sampled frames are held until the bars are known,
+    /// which delays the first results by as many frames.
+    /// `detect_scene_changes_parallel` does not detect black bars,
+    /// so that all chunks are analyzed the same way.
+    pub detect_letterbox: Option<LetterboxDetection>,
+    /// Weights for the areas of the frame given to the detector,
+    /// such as to ignore animated tickers or burned-in subtitles,
+    /// which must have a weight for each block.
+    ///
+    /// Lower weights move the pixels of a block towards mid-gray,
+    /// so that changes within it contribute less to the costs.
+    /// Chroma is not analyzed if this is set.
+    pub weight_mask: Option<WeightMask>,
+    /// How interlaced input is analyzed, to avoid combing between fields
+    /// being detected as scene changes in scenes with motion.
+    ///
+    /// This only applies if the decoder reports the input as interlaced.
+    /// Chroma is not analyzed if it applies.
+    pub field_handling: FieldHandling,
+    /// Enabling this will leave frames repeating the frame before them,
+    /// such as those added by 3:2 pulldown, out of the detector's costs,
+    /// and report the pulldown cadence if one is found.
+    ///
+    /// Telecined content otherwise has near-zero costs between repeats,
+    /// which makes the scores of the other frames noisy.
+    /// Repeated frames are never detected as scene changes, and have no scores.
+    /// If the fields of the input are repeated rather than whole frames,
+    /// this works best together with `FieldHandling::FirstField`.
+    pub detect_pulldown: bool,
+    /// Enabling this will track memory usage, open file descriptors
+    /// and frames which are never released during detection.
+    ///
+    /// This is intended for validating long-running deployments.
+    pub soak_mode: bool,
+    /// The range of frames of the input to analyze.
+    ///
+    /// Frames before the range are skipped, or seeked past if the decoder
+    /// supports it, and reading stops at the end of the range.
+    /// Frame numbers in the results are still relative to the start
+    /// of the input. Any `warmup_frames` are taken from before the range,
+    /// limited to the frames available there.
+    pub frame_range: Option<Range<usize>>,
+    /// Steps applied in order to the detected scene changes,
+    /// such as merging short scenes, to clean up the scene list.
+    ///
+    /// This is applied once detection has finished, so per-scene results
+    /// computed during detection, such as checksums and heatmaps,
+    /// describe the scene changes from before post-processing.
+    pub post_process: Vec<PostProcessStep>,
 }
 
 impl Default for DetectionOptions {
@@ -40,37 +401,452 @@ impl Default for DetectionOptions {
         DetectionOptions {
             analysis_speed: SceneDetectionSpeed::Standard,
             detect_flashes: true,
+            flash_suppression: None,
             lookahead_distance: 5,
+            max_memory: None,
+            adaptive_lookahead: false,
             min_scenecut_distance: None,
             max_scenecut_distance: None,
+            min_scenecut_duration: None,
+            max_scenecut_duration: None,
+            min_distance_policy: MinDistancePolicy::KeepFirst,
+            export_heatmaps: false,
+            min_changed_area: None,
+            detect_overlays: false,
+            detect_dialogue: false,
+            detect_highlights: false,
+            detect_ad_breaks: false,
+            detect_black_frames: None,
+            duplicate_frame_threshold: None,
+            #[cfg(feature = "checksum")]
+            compute_checksums: false,
+            compute_fingerprints: false,
+            scene_frames: SceneFrames::First,
+            detect_chroma_changes: false,
+            detect_transitions: false,
+            score_smoothing: ScoreSmoothing::default(),
+            record_scores: false,
+            report_raw_scene_changes: false,
+            cpu_features: CpuFeatures::Auto,
+            deterministic: false,
+            frame_time_budget: None,
+            warmup_frames: 0,
+            downscale: None,
+            crop: None,
+            detect_letterbox: None,
+            weight_mask: None,
+            field_handling: FieldHandling::Weave,
+            detect_pulldown: false,
+            soak_mode: false,
+            frame_range: None,
+            post_process: Vec::new(),
         }
     }
 }
 
 /// Results from a scene change detection pass.
-#[derive(Debug, Clone)]
-#[cfg_attr(feature = "serialize", derive(serde::Serialize))]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub struct DetectionResults {
     /// The 0-indexed frame numbers where scene changes were detected.
     pub scene_changes: Vec<usize>,
     /// The total number of frames read.
     pub frame_count: usize,
+    /// The 0-indexed frame number of the first frame read,
+    /// which is the start of `frame_range` if it was set.
+    #[cfg_attr(feature = "serialize", serde(default, skip_serializing_if = "is_zero"))]
+    pub first_frame: usize,
     /// Average speed (FPS)
     pub speed: f64,
+    /// The 0-indexed frame numbers where scene changes were detected
+    /// without flash detection or scenecut distances,
+    /// if `report_raw_scene_changes` was enabled.
+    ///
+    /// Other options, such as `min_changed_area`, do not apply to these,
+    /// and they are not post-processed.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub raw_scene_changes: Vec<usize>,
+    /// Each detected scene change along with its confidence,
+    /// in the same order as the scene changes were detected.
+    ///
+    /// These describe the scene changes from before post-processing.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub scene_cuts: Vec<SceneCut>,
+    /// Difference heatmaps for each detected scene change
+    /// after the first frame, if `export_heatmaps` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub heatmaps: Vec<DifferenceHeatmap>,
+    /// Overlay changes, if `detect_overlays` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub overlay_events: Vec<OverlayEvent>,
+    /// Runs of scenes alternating between two shots,
+    /// if `detect_dialogue` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub dialogue_sequences: Vec<DialogueSequence>,
+    /// Candidate sports highlights, ranked from most to least likely,
+    /// if `detect_highlights` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub highlight_candidates: Vec<HighlightCandidate>,
+    /// Likely commercial breaks, if `detect_ad_breaks` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub ad_breaks: Vec<AdBreakCandidate>,
+    /// Runs of black frames, if `detect_black_frames` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub black_frames: Vec<BlackFrameRun>,
+    /// Runs of duplicate frames, if `duplicate_frame_threshold` was set.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub duplicate_frames: Vec<DuplicateFrameRun>,
+    /// Checksums of each scene, if `compute_checksums` was enabled.
+    #[cfg(feature = "checksum")]
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub scene_checksums: Vec<SceneChecksum>,
+    /// Perceptual hashes of each scene, if `compute_fingerprints` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub scene_fingerprints: Vec<SceneFingerprint>,
+    /// Fades and dissolves, if `detect_transitions` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub transitions: Vec<TransitionEvent>,
+    /// The score of each analyzed frame, if `record_scores` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub frame_scores: Vec<FrameScore>,
+    /// The first frame analyzed with the fast algorithm
+    /// because `frame_time_budget` was exceeded, if any.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub degraded_at: Option<usize>,
+    /// Whether detection was cancelled before the end of the clip,
+    /// in which case the results only cover the frames analyzed until then.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "is_false")
+    )]
+    pub cancelled: bool,
+    /// Resource usage during detection, if `soak_mode` was enabled.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub soak_report: Option<SoakReport>,
+    /// The configuration chosen to stay within `max_memory`, if it was set.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub memory_config: Option<MemoryConfig>,
+    /// The 3:2 pulldown cadence of the clip,
+    /// if `detect_pulldown` was enabled and one was found.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub pulldown_cadence: Option<PulldownCadence>,
+    /// The area within the black bars found by `detect_letterbox`,
+    /// if it was set and bars were found.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub letterbox_crop: Option<CropRect>,
+    /// Frames the decoder found missing from the video, such as frames
+    /// which failed to decode, after which frame numbers no longer match
+    /// the frames of the container. Only decoders which can detect gaps
+    /// report them, and only `detect_scene_changes` records them.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub frame_gaps: Vec<FrameGap>,
+    /// The frames the source marks as keyframes, such as its IDR frames,
+    /// for comparison with the scene changes. Only decoders which give
+    /// per-frame properties report them, and only `detect_scene_changes`
+    /// records them.
+    #[cfg_attr(
+        feature = "serialize",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub decoder_keyframes: Vec<usize>,
+}
+
+#[cfg(feature = "serialize")]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[cfg(feature = "serialize")]
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+impl DetectionResults {
+    /// Renumbers all frames in the results, which are relative
+    /// to the first frame read, relative to the start of the input.
+    pub(crate) fn offset_frames(&mut self, first_frame: usize) {
+        self.first_frame += first_frame;
+        for frameno in self
+            .scene_changes
+            .iter_mut()
+            .chain(self.raw_scene_changes.iter_mut())
+        {
+            *frameno += first_frame;
+        }
+        for cut in &mut self.scene_cuts {
+            cut.frameno += first_frame;
+        }
+        for heatmap in &mut self.heatmaps {
+            heatmap.frameno += first_frame;
+        }
+        for event in &mut self.overlay_events {
+            event.frameno += first_frame;
+        }
+        for sequence in &mut self.dialogue_sequences {
+            for frameno in &mut sequence.scene_changes {
+                *frameno += first_frame;
+            }
+        }
+        for candidate in &mut self.highlight_candidates {
+            candidate.start_frame += first_frame;
+            candidate.replay_frame += first_frame;
+            candidate.end_frame += first_frame;
+        }
+        for ad_break in &mut self.ad_breaks {
+            ad_break.start_frame += first_frame;
+            ad_break.end_frame += first_frame;
+        }
+        for run in &mut self.black_frames {
+            run.start_frame += first_frame;
+            run.end_frame += first_frame;
+        }
+        for run in &mut self.duplicate_frames {
+            run.start_frame += first_frame;
+            run.end_frame += first_frame;
+        }
+        #[cfg(feature = "checksum")]
+        for checksum in &mut self.scene_checksums {
+            checksum.start_frame += first_frame;
+        }
+        for fingerprint in &mut self.scene_fingerprints {
+            fingerprint.start_frame += first_frame;
+        }
+        for transition in &mut self.transitions {
+            transition.start += first_frame;
+            transition.end += first_frame;
+        }
+        for score in &mut self.frame_scores {
+            score.frameno += first_frame;
+        }
+        if let Some(ref mut frameno) = self.degraded_at {
+            *frameno += first_frame;
+        }
+        if let Some(ref mut cadence) = self.pulldown_cadence {
+            cadence.first_repeat += first_frame;
+        }
+        for gap in &mut self.frame_gaps {
+            gap.frameno += first_frame;
+        }
+        for frameno in &mut self.decoder_keyframes {
+            *frameno += first_frame;
+        }
+    }
+
+    /// Returns the scenes described by `scene_changes`,
+    /// including the final scene which ends at the last frame.
+    pub fn scenes(&self) -> Vec<Scene> {
+        self.scene_changes
+            .iter()
+            .enumerate()
+            .map(|(i, &start_frame)| {
+                let end_frame = self
+                    .scene_changes
+                    .get(i + 1)
+                    .copied()
+                    .unwrap_or(self.first_frame + self.frame_count);
+                let len = end_frame - start_frame;
+                Scene {
+                    start_frame,
+                    end_frame,
+                    len,
+                    representative_frame: start_frame + len / 2,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A detected scene change, along with how confident the detection is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneCut {
+    /// The 0-indexed frame number of the first frame of the new scene.
+    pub frameno: usize,
+    /// How much the frame differs from the frame before it, from `0.0` to `1.0`,
+    /// measured by comparing their luma and chroma histograms.
+    ///
+    /// This is independent of the detection algorithm, so that it can be
+    /// compared across runs. Cuts with a low confidence are more likely
+    /// to be false positives, or to have been forced by `max_scenecut_distance`.
+    /// The first frame always has a confidence of `1.0`.
+    pub confidence: f32,
+    /// Whether the scene change was decided once the end of input was reached,
+    /// with fewer than `lookahead_distance` frames after it.
+    #[cfg_attr(feature = "serialize", serde(default))]
+    pub flush_decided: bool,
+}
+
+/// A single scene, spanning from one scene change to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    /// The 0-indexed frame number of the first frame of the scene.
+    pub start_frame: usize,
+    /// The 0-indexed frame number after the last frame of the scene.
+    pub end_frame: usize,
+    /// The number of frames in the scene.
+    pub len: usize,
+    /// The 0-indexed frame number of the middle frame of the scene,
+    /// which is the canonical frame to represent it, such as in thumbnails.
+    ///
+    /// For scenes with an even number of frames,
+    /// this is the later of the two middle frames.
+    pub representative_frame: usize,
+}
+
+impl Scene {
+    /// Returns the presentation time of `representative_frame`.
+    pub fn representative_timestamp(&self, timing: &FrameTiming) -> Option<Duration> {
+        timing.frame_to_timestamp(self.representative_frame)
+    }
+}
+
+/// The number of rows and columns in a `DifferenceHeatmap`.
+pub const HEATMAP_GRID_SIZE: usize = 16;
+
+/// A coarse map of how much each region of the frame changed
+/// between a scene change and the frame before it.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct DifferenceHeatmap {
+    /// The 0-indexed frame number of the scene change.
+    pub frameno: usize,
+    /// The mean absolute luma difference of each cell
+    /// in a `HEATMAP_GRID_SIZE` by `HEATMAP_GRID_SIZE` grid,
+    /// normalized to the range `0.0..=1.0`, in row-major order.
+    pub cells: Vec<f32>,
+}
+
+/// The progress of a detection pass.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressEvent {
+    /// The number of frames decoded so far.
+    pub frames_decoded: usize,
+    /// The number of frames analyzed so far.
+    pub frames_analyzed: usize,
+    /// The number of scene changes detected so far.
+    pub scene_changes: usize,
+    /// The average number of frames analyzed per second so far.
+    pub fps: f64,
+    /// The estimated time remaining until all frames are analyzed.
+    /// Only available if the decoder knows the total number of frames.
+    pub eta: Option<Duration>,
 }
 
 /// An optional callback that will fire after each frame is analyzed.
-/// Arguments passed in will be, in order,
-/// the number of frames analyzed, and the number of keyframes detected.
 ///
 /// This is generally useful for displaying progress, etc.
-pub type ProgressCallback = Box<dyn Fn(usize, usize)>;
+pub type ProgressCallback = Box<dyn Fn(&ProgressEvent)>;
+
+/// A frame of a detected scene, converted to 8-bit RGB.
+#[derive(Debug, Clone)]
+pub struct SceneFrame {
+    /// The 0-indexed frame number of the frame.
+    pub frameno: usize,
+    /// Where the frame lies within its scene.
+    pub position: SceneFramePosition,
+    /// The width of the frame in pixels.
+    pub width: usize,
+    /// The height of the frame in pixels.
+    pub height: usize,
+    /// Packed RGB samples in row-major order, 3 bytes per pixel.
+    pub rgb: Vec<u8>,
+}
 
-pub fn new_detector<R: Read, T: Pixel>(
-    dec: &mut Decoder<R>,
+impl SceneFrame {
+    fn new<T: Pixel>(
+        frameno: usize,
+        position: SceneFramePosition,
+        frame: &Frame<T>,
+        video_details: &VideoDetails,
+    ) -> Self {
+        SceneFrame {
+            frameno,
+            position,
+            width: video_details.width,
+            height: video_details.height,
+            rgb: convert::frame_to_rgb(frame, video_details),
+        }
+    }
+}
+
+/// An optional callback that will fire with the frames of each detected scene
+/// selected by `scene_frames`, in the order of the frames.
+/// The first frame is given as soon as the scene change is detected.
+///
+/// This is generally useful for generating per-scene thumbnails
+/// or embeddings in the same pass as detection.
+pub type SceneFrameCallback = Box<dyn FnMut(SceneFrame)>;
+
+pub fn new_detector<D: Decoder, T: Pixel>(
+    dec: &mut D,
     opts: DetectionOptions,
 ) -> SceneChangeDetector<T> {
-    let video_details = y4m::get_video_details(dec);
+    let video_details = dec.get_video_details();
+    build_detector(&video_details, &opts)
+}
+
+pub(crate) fn build_detector<T: Pixel>(
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> SceneChangeDetector<T> {
     let mut config =
         EncoderConfig::with_speed_preset(if opts.analysis_speed == SceneDetectionSpeed::Fast {
             10
@@ -78,14 +854,11 @@ pub fn new_detector<R: Read, T: Pixel>(
             8
         });
 
-    config.min_key_frame_interval = opts
-        .min_scenecut_distance
+    let (min_scenecut_distance, max_scenecut_distance) = scenecut_distances(video_details, opts);
+    config.min_key_frame_interval = min_scenecut_distance.map(|val| val as u64).unwrap_or(0);
+    config.max_key_frame_interval = max_scenecut_distance
         .map(|val| val as u64)
-        .unwrap_or(0);
-    config.max_key_frame_interval = opts
-        .max_scenecut_distance
-        .map(|val| val as u64)
-        .unwrap_or(u32::max_value() as u64);
+        .unwrap_or(u32::MAX as u64);
     config.width = video_details.width;
     config.height = video_details.height;
     config.bit_depth = video_details.bit_depth;
@@ -96,7 +869,11 @@ pub fn new_detector<R: Read, T: Pixel>(
     let sequence = Arc::new(Sequence::new(&config));
     SceneChangeDetector::new(
         config,
-        CpuFeatureLevel::default(),
+        if opts.deterministic {
+            CpuFeatureLevel::RUST
+        } else {
+            opts.cpu_features.level()
+        },
         if opts.detect_flashes {
             opts.lookahead_distance
         } else {
@@ -106,85 +883,644 @@ pub fn new_detector<R: Read, T: Pixel>(
     )
 }
 
-/// Runs through a y4m video clip,
+/// Resolves the minimum and maximum scenecut distances in frames,
+/// taking durations in seconds into account.
+pub(crate) fn scenecut_distances(
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> (Option<usize>, Option<usize>) {
+    let min_scenecut_distance = opts
+        .min_scenecut_duration
+        .map(|secs| duration_to_frames(secs, video_details.time_base))
+        .or(opts.min_scenecut_distance);
+    let max_scenecut_distance = opts
+        .max_scenecut_duration
+        .map(|secs| duration_to_frames(secs, video_details.time_base))
+        .or(opts.max_scenecut_distance);
+    (min_scenecut_distance, max_scenecut_distance)
+}
+
+/// Checks for options which cannot be applied before any frames are decoded,
+/// returning the reason if there are any.
+pub(crate) fn check_options(opts: &DetectionOptions) -> Result<(), &'static str> {
+    if opts.lookahead_distance == 0 {
+        return Err("lookahead distance must be at least 1");
+    }
+    if opts.downscale == Some(0) {
+        return Err("downscale factor must be at least 1");
+    }
+    if let Some(ref mask) = opts.weight_mask {
+        if !mask.is_valid() {
+            return Err("weight mask must have a weight for each block");
+        }
+    }
+    Ok(())
+}
+
+/// Resolves the minimum and maximum length of a scene, measuring the durations
+/// with the timestamps of the frames if they are known, or converting them
+/// to frame counts using the nominal frame rate of the clip otherwise.
+fn scene_length_limits<'a>(
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+    timestamps: Option<&'a [Duration]>,
+) -> SceneLengthLimits<'a> {
+    match timestamps {
+        Some(timestamps) => SceneLengthLimits {
+            min: opts
+                .min_scenecut_duration
+                .map(SceneLength::Seconds)
+                .or_else(|| opts.min_scenecut_distance.map(SceneLength::Frames)),
+            max: opts
+                .max_scenecut_duration
+                .map(SceneLength::Seconds)
+                .or_else(|| opts.max_scenecut_distance.map(SceneLength::Frames)),
+            timestamps,
+        },
+        None => {
+            let (min_distance, max_distance) = scenecut_distances(video_details, opts);
+            SceneLengthLimits {
+                min: min_distance.map(SceneLength::Frames),
+                max: max_distance.map(SceneLength::Frames),
+                timestamps: &[],
+            }
+        }
+    }
+}
+
+/// Returns the timestamp of each of the first `frame_count` frames followed by
+/// the time the last of them ends, or `None` unless all of them have a timestamp.
+fn frame_timestamps(frame_meta: &[Option<FrameMeta>], frame_count: usize) -> Option<Vec<Duration>> {
+    let frame_meta = frame_meta.get(..frame_count)?;
+    let mut timestamps = frame_meta
+        .iter()
+        .map(|meta| meta.and_then(|meta| meta.pts))
+        .collect::<Option<Vec<_>>>()?;
+    let (&first, &last) = (timestamps.first()?, timestamps.last()?);
+    // Without a duration, the last frame is assumed to last as long as the average frame
+    let last_duration = frame_meta[frame_count - 1]
+        .and_then(|meta| meta.duration)
+        .unwrap_or_else(|| match frame_count {
+            1 => Duration::ZERO,
+            _ => last.saturating_sub(first) / (frame_count - 1) as u32,
+        });
+    timestamps.push(last + last_duration);
+    Some(timestamps)
+}
+
+/// Converts a duration in seconds to the nearest whole number of frames
+/// for a clip with the given time base.
+fn duration_to_frames(secs: f64, time_base: Rational) -> usize {
+    (secs * time_base.den as f64 / time_base.num as f64).round() as usize
+}
+
+/// Runs through a video clip,
 /// detecting where scene changes occur.
 /// This is adjustable based on the `opts` parameters.
 ///
+/// Decoding runs on a separate thread, so that it can overlap
+/// with the analysis of previously decoded frames.
+///
+/// If `cancel_flag` is given, it is checked before each frame is analyzed.
+/// Once it is set, detection stops early and returns the results so far.
+///
+/// If the decoder fails partway through the clip, a `DetectionError`
+/// is returned with the results for the frames analyzed until then.
+/// A decoder which stalls, such as when reading through a `TimeoutReader`
+/// or wrapped in a `TimeoutDecoder` which times out,
+/// results in `DetectionError::DecoderStalled`.
+///
 /// This is the preferred, simplified interface
 /// for analyzing a whole clip for scene changes.
-pub fn detect_scene_changes<R: Read, T: Pixel>(
-    dec: &mut Decoder<R>,
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+pub fn detect_scene_changes<D: Decoder + Send, T: Pixel>(
+    dec: &mut D,
     opts: DetectionOptions,
     progress_callback: Option<ProgressCallback>,
-) -> DetectionResults {
-    assert!(opts.lookahead_distance >= 1);
+    scene_frame_callback: Option<SceneFrameCallback>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+) -> Result<DetectionResults, DetectionError> {
+    detect_scene_changes_with_cuts::<D, T>(
+        dec,
+        opts,
+        progress_callback,
+        scene_frame_callback,
+        None,
+        cancel_flag,
+        &mut ReusedFramePool::new(),
+    )
+}
 
-    let mut detector = new_detector(dec, opts);
-    let video_details = y4m::get_video_details(dec);
-    let mut frame_queue = BTreeMap::new();
-    let mut keyframes = BTreeSet::new();
-    keyframes.insert(0);
+/// Runs `detect_scene_changes`, calling `cut_callback` with each scene change
+/// as soon as it is detected, before any scene changes are removed
+/// or moved once detection has finished.
+///
+/// Frames are decoded into frames from `reused_frames`, which may hold frames
+/// left over from an earlier pass over another input.
+pub(crate) fn detect_scene_changes_with_cuts<D: Decoder + Send, T: Pixel>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    progress_callback: Option<ProgressCallback>,
+    mut scene_frame_callback: Option<SceneFrameCallback>,
+    mut cut_callback: Option<&mut dyn FnMut(SceneCut)>,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    reused_frames: &mut ReusedFramePool<T>,
+) -> Result<DetectionResults, DetectionError> {
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+    let first_frame = opts.frame_range.as_ref().map_or(0, |range| range.start);
+    let (mut dec, opts) = range::restrict_to_range(dec, &opts);
+    let dec = &mut dec;
+    let video_details = dec.get_video_details();
+    let (opts, memory_config) = memory::fit_to_memory(&video_details, &opts);
+    let total_frames = dec
+        .total_frames()
+        .map(|frames| frames.saturating_sub(opts.warmup_frames));
+    // Durations are measured with the timestamps of the frames where possible
+    let measures_durations =
+        opts.min_scenecut_duration.is_some() || opts.max_scenecut_duration.is_some();
+    let applies_distances_after =
+        opts.min_distance_policy != MinDistancePolicy::KeepFirst || measures_durations;
+    let mut pipeline = DetectionPipeline::new(
+        video_details,
+        if !applies_distances_after {
+            opts.clone()
+        } else {
+            // The scenecut distances are applied once all scene changes are known
+            DetectionOptions {
+                min_scenecut_distance: None,
+                max_scenecut_distance: None,
+                min_scenecut_duration: None,
+                max_scenecut_duration: None,
+                ..opts.clone()
+            }
+        },
+    );
 
     let start_time = Instant::now();
-    let mut frameno = 0;
-    loop {
-        let mut next_input_frameno = frame_queue
-            .keys()
-            .last()
-            .copied()
-            .map(|key| key + 1)
-            .unwrap_or(0);
-        while next_input_frameno <= frameno + opts.lookahead_distance {
-            let frame = y4m::read_video_frame::<R, T>(dec, &video_details);
-            if let Ok(frame) = frame {
-                frame_queue.insert(next_input_frameno, Arc::new(frame));
-                next_input_frameno += 1;
-            } else {
-                // End of input
-                break;
+    let frames_decoded = AtomicUsize::new(0);
+    let mut soak_monitor = if opts.soak_mode {
+        Some(SoakMonitor::new())
+    } else {
+        None
+    };
+    let is_cancelled = || {
+        cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    };
+    let mut scene_cuts = Vec::new();
+    let mut heatmaps = Vec::new();
+    let mut overlay_tracker = OverlayTracker::new(video_details);
+    let mut overlay_events = Vec::new();
+    let mut dialogue_fingerprints = Vec::new();
+    let mut frame_hashes = Vec::new();
+    let mut motion = Vec::new();
+    let mut is_duplicate = Vec::new();
+    let mut mean_luma = Vec::new();
+    let mut transition_tracker = TransitionTracker::new(video_details);
+    let mut transitions = Vec::new();
+    let mut cut_strengths = Vec::new();
+    let mut flash_tracker = opts
+        .flash_suppression
+        .map(|suppression| FlashTracker::new(suppression, video_details));
+    #[cfg(feature = "checksum")]
+    let mut checksummer = SceneChecksummer::new(
+        video_details.width,
+        video_details.height,
+        video_details.bit_depth,
+    );
+    let mut middle_frames = (scene_frame_callback.is_some()
+        && opts.scene_frames != SceneFrames::First)
+        .then(MiddleFrameBuffer::new);
+    // Frames are decoded into the frames the detector is done with, if any
+    let (frame_pool, frame_recycler) = reused_frames.for_details(video_details);
+    let mut handle_decision = |decision: FrameDecision<T>,
+                               keyframe_count: usize,
+                               flush_decided: bool| {
+        if decision.is_keyframe {
+            let cut = SceneCut {
+                frameno: decision.frameno,
+                confidence: decision.previous.as_ref().map_or(1.0, |previous| {
+                    histogram::frame_difference(previous, &decision.frame, &video_details) as f32
+                }),
+                flush_decided,
+            };
+            if let Some(ref mut cut_fn) = cut_callback {
+                cut_fn(SceneCut {
+                    frameno: first_frame + cut.frameno,
+                    ..cut
+                });
             }
+            scene_cuts.push(cut);
         }
-
-        // The frame_queue should start at whatever the previous frame was
-        let frame_set = frame_queue
-            .values()
-            .cloned()
-            .take(opts.lookahead_distance + 2)
-            .collect::<Vec<_>>();
-        if frame_set.len() < 2 {
-            // End of video
-            break;
-        }
-        if frameno == 0
-            || detector.analyze_next_frame(
-                &frame_set,
-                frameno as u64,
-                *keyframes.iter().last().unwrap(),
-            )
-        {
-            keyframes.insert(frameno as u64);
-        };
-
-        if frameno > 0 {
-            frame_queue.remove(&(frameno - 1));
+        if let (true, Some(tracker)) = (decision.is_keyframe, &mut flash_tracker) {
+            tracker.push(
+                decision.frameno,
+                decision.previous.as_ref(),
+                &decision.frame,
+            );
+        }
+        let needs_grid = opts.detect_overlays || (opts.export_heatmaps && decision.is_keyframe);
+        if let (true, Some(previous)) = (needs_grid, &decision.previous) {
+            let grid = metrics::difference_grid(
+                &previous.planes[0],
+                &decision.frame.planes[0],
+                video_details.width,
+                video_details.height,
+                video_details.bit_depth,
+                HEATMAP_GRID_SIZE,
+            );
+            if opts.export_heatmaps && decision.is_keyframe {
+                heatmaps.push(DifferenceHeatmap {
+                    frameno: decision.frameno,
+                    cells: grid.clone(),
+                });
+            }
+            if opts.detect_overlays {
+                overlay_events.extend(overlay_tracker.push(
+                    decision.frameno,
+                    grid,
+                    decision.is_keyframe,
+                ));
+            }
+        }
+        if opts.detect_highlights || opts.duplicate_frame_threshold.is_some() {
+            let difference = decision.previous.as_ref().map(|previous| {
+                metrics::mean_difference(
+                    &previous.planes[0],
+                    &decision.frame.planes[0],
+                    video_details.width,
+                    video_details.height,
+                    video_details.bit_depth,
+                )
+            });
+            if opts.detect_highlights {
+                motion.push(difference.unwrap_or(0.0));
+            }
+            if let Some(threshold) = opts.duplicate_frame_threshold {
+                is_duplicate.push(difference.is_some_and(|difference| difference <= threshold));
+            }
+        }
+        #[cfg(feature = "checksum")]
+        if opts.compute_checksums {
+            checksummer.push(
+                decision.frameno,
+                &decision.frame.planes[0],
+                decision.is_keyframe,
+            );
+        }
+        if opts.compute_fingerprints {
+            frame_hashes.push(fingerprint::dhash(
+                &decision.frame.planes[0],
+                video_details.width,
+                video_details.height,
+            ));
+        }
+        if opts.detect_ad_breaks || opts.detect_black_frames.is_some() {
+            mean_luma.push(metrics::mean_value(
+                &decision.frame.planes[0],
+                video_details.width,
+                video_details.height,
+                video_details.bit_depth,
+            ));
+        }
+        let needs_strength = !opts.post_process.is_empty()
+            || opts.min_distance_policy == MinDistancePolicy::KeepStronger;
+        if needs_strength && decision.is_keyframe {
+            cut_strengths.extend(
+                postprocess::cut_strength(
+                    decision.previous.as_deref(),
+                    &decision.frame,
+                    &video_details,
+                )
+                .map(|strength| (decision.frameno, strength)),
+            );
+        }
+        if opts.detect_transitions {
+            transitions.extend(transition_tracker.push(
+                decision.frameno,
+                decision.previous.as_ref(),
+                &decision.frame,
+            ));
+        }
+        if opts.detect_dialogue && decision.is_keyframe {
+            dialogue_fingerprints.push((
+                decision.frameno,
+                fingerprint::dhash(
+                    &decision.frame.planes[0],
+                    video_details.width,
+                    video_details.height,
+                ),
+            ));
+        }
+        if let Some(ref mut scene_frame_fn) = scene_frame_callback {
+            // The middle frame of the previous scene comes before the first frame of the next
+            if let Some((frameno, frame)) = middle_frames.as_mut().and_then(|buffer| {
+                buffer.push(decision.frameno, &decision.frame, decision.is_keyframe)
+            }) {
+                scene_frame_fn(SceneFrame::new(
+                    frameno,
+                    SceneFramePosition::Middle,
+                    &frame,
+                    &video_details,
+                ));
+            }
+            if decision.is_keyframe && opts.scene_frames != SceneFrames::Middle {
+                scene_frame_fn(SceneFrame::new(
+                    decision.frameno,
+                    SceneFramePosition::First,
+                    &decision.frame,
+                    &video_details,
+                ));
+            }
+        }
+        if let Some(ref mut monitor) = soak_monitor {
+            monitor.sample(decision.frameno, &decision.frame);
         }
-
-        frameno += 1;
         if let Some(ref progress_fn) = progress_callback {
-            progress_fn(frameno, keyframes.len());
+            let frames_analyzed = decision.frameno + 1;
+            let fps = frames_analyzed as f64 / start_time.elapsed().as_secs_f64();
+            progress_fn(&ProgressEvent {
+                frames_decoded: frames_decoded
+                    .load(Ordering::Relaxed)
+                    .saturating_sub(opts.warmup_frames),
+                frames_analyzed,
+                scene_changes: keyframe_count,
+                fps,
+                eta: total_frames.map(|total| {
+                    Duration::from_secs_f64(total.saturating_sub(frames_analyzed) as f64 / fps)
+                }),
+            });
+        }
+        if let Some(previous) = decision.previous {
+            frame_recycler.recycle(previous);
+        }
+    };
+    let mut decode_error = None;
+    let mut decoder_keyframes = Vec::new();
+    let mut frame_meta = Vec::new();
+    thread::scope(|scope| {
+        let (frame_tx, frame_rx) = mpsc::sync_channel(opts.lookahead_distance + 1);
+        let frames_decoded = &frames_decoded;
+        // Reborrowed so that the frame gaps can be taken once decoding ends
+        let dec = &mut *dec;
+        let decoder_keyframes = &mut decoder_keyframes;
+        let frame_meta = &mut frame_meta;
+        scope.spawn(move || loop {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("decode").entered();
+            #[cfg(feature = "tracing")]
+            let decode_start = Instant::now();
+            let mut frame = frame_pool.get();
+            let result = dec
+                .read_video_frame_into(&video_details, &mut frame)
+                .map(|()| frame);
+            #[cfg(feature = "tracing")]
+            tracing::trace!(elapsed = ?decode_start.elapsed(), ok = result.is_ok(), "decoded frame");
+            if result.is_ok() {
+                let frameno = frames_decoded.fetch_add(1, Ordering::Relaxed);
+                let meta = dec.frame_meta();
+                if meta.is_some_and(|meta| meta.is_decoder_keyframe) {
+                    decoder_keyframes.push(frameno);
+                }
+                if measures_durations {
+                    frame_meta.push(meta);
+                }
+            }
+            let done = result.is_err();
+            if frame_tx.send(result).is_err() || done {
+                break;
+            }
+        });
+
+        for result in frame_rx {
+            if is_cancelled() {
+                // Dropping the receiver stops the decoding thread
+                break;
+            }
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(DecodeError::EndOfStream) => break,
+                Err(err) => {
+                    decode_error = Some(err);
+                    break;
+                }
+            };
+            for decision in pipeline.push(Arc::new(frame)) {
+                handle_decision(decision, pipeline.keyframes().len(), false);
+            }
+        }
+    });
+    let cancelled = is_cancelled();
+    if !cancelled && decode_error.is_none() {
+        for decision in pipeline.finish() {
+            handle_decision(decision, pipeline.keyframes().len(), true);
+        }
+    }
+    // The last scene ends with the last analyzed frame
+    if let (Some(scene_frame_fn), Some(buffer)) = (&mut scene_frame_callback, &mut middle_frames) {
+        if let Some((frameno, frame)) = buffer.finish() {
+            scene_frame_fn(SceneFrame::new(
+                frameno,
+                SceneFramePosition::Middle,
+                &frame,
+                &video_details,
+            ));
         }
     }
-    DetectionResults {
-        scene_changes: keyframes.into_iter().map(|val| val as usize).collect(),
-        frame_count: frameno,
-        speed: frameno as f64 / start_time.elapsed().as_secs_f64(),
+
+    let frame_count = pipeline.frames_analyzed();
+    // Gaps in the warmup frames or after the last analyzed frame are dropped
+    let frame_gaps = dec
+        .take_frame_gaps()
+        .into_iter()
+        .filter(|gap| (opts.warmup_frames..opts.warmup_frames + frame_count).contains(&gap.frameno))
+        .map(|gap| FrameGap {
+            frameno: gap.frameno - opts.warmup_frames,
+            ..gap
+        })
+        .collect();
+    let decoder_keyframes = decoder_keyframes
+        .into_iter()
+        .filter(|frameno| (opts.warmup_frames..opts.warmup_frames + frame_count).contains(frameno))
+        .map(|frameno| frameno - opts.warmup_frames)
+        .collect();
+    let mut scene_changes = match flash_tracker {
+        Some(ref tracker) => tracker.remove_flashes(pipeline.keyframes()),
+        None => pipeline.keyframes().to_vec(),
+    };
+    if applies_distances_after && frame_count > 0 {
+        let timestamps = frame_timestamps(
+            frame_meta.get(opts.warmup_frames..).unwrap_or_default(),
+            frame_count,
+        );
+        scene_changes = parallel::apply_scene_length_limits(
+            &scene_changes,
+            &cut_strengths,
+            frame_count,
+            &scene_length_limits(&video_details, &opts, timestamps.as_deref()),
+            opts.min_distance_policy,
+        );
+    }
+    let mut results = DetectionResults {
+        scene_changes: postprocess::apply_post_processing(
+            &opts.post_process,
+            &scene_changes,
+            &cut_strengths,
+            &decoder_keyframes,
+            frame_count,
+        ),
+        frame_count,
+        speed: frame_count as f64 / start_time.elapsed().as_secs_f64(),
+        raw_scene_changes: pipeline.raw_keyframes(),
+        scene_cuts,
+        heatmaps,
+        overlay_events,
+        dialogue_sequences: dialogue::find_dialogue_sequences(&dialogue_fingerprints),
+        highlight_candidates: highlight::find_highlight_candidates(&motion, pipeline.keyframes()),
+        ad_breaks: if opts.detect_ad_breaks {
+            adbreak::find_ad_breaks(&mean_luma, pipeline.keyframes(), &video_details)
+        } else {
+            Vec::new()
+        },
+        black_frames: opts.detect_black_frames.map_or_else(Vec::new, |detection| {
+            black::black_runs(&mean_luma, detection.threshold, detection.min_frames)
+        }),
+        duplicate_frames: duplicate::duplicate_runs(&is_duplicate),
+        #[cfg(feature = "checksum")]
+        scene_checksums: checksummer.finish(),
+        transitions,
+        frame_scores: pipeline.frame_scores(),
+        degraded_at: pipeline.degraded_at(),
+        cancelled,
+        memory_config,
+        pulldown_cadence: pulldown::find_cadence(pipeline.repeated_frames()),
+        letterbox_crop: pipeline.letterbox_crop(),
+        frame_gaps,
+        decoder_keyframes,
+        ..Default::default()
+    };
+    // The scenes are only known once post-processing is done
+    results.scene_fingerprints =
+        fingerprint::scene_fingerprints(&frame_hashes, &results.scene_changes, frame_count);
+    results.offset_frames(first_frame);
+    // Release the remaining frames before checking for leaks
+    drop(pipeline);
+    results.soak_report = soak_monitor.map(SoakMonitor::finish);
+    match decode_error {
+        Some(err) => Err(DetectionError::from_decode_error(err, results)),
+        None => Ok(results),
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialOrd, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
 pub enum SceneDetectionSpeed {
     /// Fastest scene detection using pixel-wise comparison
     Fast,
     /// Scene detection using motion vectors
     Standard,
+    /// Scene detection comparing luma and chroma histograms
+    /// against an adaptive threshold, independent of rav1e's cost model.
+    ///
+    /// This behaves similarly to PySceneDetect's content detectors.
+    /// Flash detection is not performed.
+    Histogram,
+    /// Scene detection comparing the edges of consecutive frames,
+    /// which also detects gradual transitions such as dissolves.
+    /// Flash detection is not performed.
+    EdgeChangeRatio,
+}
+
+/// How the fields of interlaced frames are given to the detector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldHandling {
+    /// Analyzes whole frames with both fields woven together,
+    /// the same as progressive frames.
+    Weave,
+    /// Analyzes the first field of each frame,
+    /// with each of its lines repeated to the full height of the frame.
+    Bob,
+    /// Analyzes the first field of each frame on its own, at half height,
+    /// which is faster than `Bob`.
+    FirstField,
+}
+
+/// Which frames of each scene are given to a `SceneFrameCallback`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum SceneFrames {
+    /// The first frame of each scene.
+    First,
+    /// The middle frame of each scene, which is the later
+    /// of the two middle frames for scenes with an even number of frames.
+    Middle,
+    /// Both the first and the middle frame of each scene.
+    FirstAndMiddle,
+}
+
+/// Where a `SceneFrame` lies within its scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneFramePosition {
+    /// The first frame of the scene.
+    First,
+    /// The middle frame of the scene.
+    Middle,
+}
+
+/// Which of two scene changes closer than the minimum scenecut distance is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum MinDistancePolicy {
+    /// Keeps the earlier scene change, and drops any later ones
+    /// until the minimum distance has passed.
+    KeepFirst,
+    /// Keeps the later scene change, so that a sequence of rapid cuts
+    /// is represented by its last cut.
+    KeepLast,
+    /// Keeps the scene change with the larger mean luma difference
+    /// from the preceding frame, keeping the earlier one on a tie.
+    KeepStronger,
+}
+
+/// The CPU features the detector may use, for benchmarking
+/// its SIMD implementations or working around issues with them.
+///
+/// Features which the CPU does not support are never used,
+/// so selecting them is the same as `Auto`.
+/// Selecting x86 features on other architectures is also the same as `Auto`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum CpuFeatures {
+    /// Uses the best features supported by the CPU,
+    /// unless limited by the `RAV1E_CPU_TARGET` environment variable.
+    Auto,
+    /// Only uses the portable Rust implementation.
+    Rust,
+    /// Uses at most SSE4.1 on x86.
+    Sse4,
+    /// Uses at most AVX2 on x86.
+    Avx2,
+}
+
+impl CpuFeatures {
+    fn level(self) -> CpuFeatureLevel {
+        let detected = CpuFeatureLevel::default();
+        let requested = match self {
+            CpuFeatures::Auto => return detected,
+            CpuFeatures::Rust => return CpuFeatureLevel::RUST,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            CpuFeatures::Sse4 => CpuFeatureLevel::SSE4_1,
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            CpuFeatures::Avx2 => CpuFeatureLevel::AVX2,
+            #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+            CpuFeatures::Sse4 | CpuFeatures::Avx2 => return detected,
+        };
+        if requested > detected {
+            detected
+        } else {
+            requested
+        }
+    }
 }
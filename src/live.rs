@@ -0,0 +1,139 @@
+use crate::decoder::{DecodeError, Decoder};
+use crate::ffmpeg::{FfmpegPipeDecoder, StreamOptions};
+use crate::scenedetector::{CutDecision, SceneDetector};
+use crate::{check_options, DetectionError, DetectionOptions, DetectionResults};
+use rav1e::prelude::Pixel;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How a live stream is read by `detect_scene_changes_live`.
+#[derive(Debug, Clone)]
+pub struct LiveOptions {
+    /// The options for reading the stream.
+    pub stream: StreamOptions,
+    /// The time to wait before reconnecting after the stream
+    /// stalled or failed.
+    pub reconnect_delay: Duration,
+    /// The maximum number of consecutive reconnections without
+    /// decoding a frame before giving up, or `None` to never give up.
+    pub max_reconnects: Option<usize>,
+}
+
+impl Default for LiveOptions {
+    fn default() -> Self {
+        LiveOptions {
+            stream: StreamOptions {
+                stall_timeout: Some(Duration::from_secs(10)),
+                ..Default::default()
+            },
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnects: Some(10),
+        }
+    }
+}
+
+/// Detects scene changes in a live stream, such as HLS or RTSP,
+/// calling `on_cut` for each scene change as soon as it is detected.
+///
+/// Scene changes are reported with a delay of `lookahead_distance` frames,
+/// except for those decided with less lookahead when the stream stalls,
+/// fails or ends, which have `flush_decided` set. If the stream stalls or fails, it is reconnected after
+/// `reconnect_delay`, and frame numbers continue from the frames read
+/// before. The first frame after a reconnection is not reported
+/// as a scene change. As with `SceneDetector`, options which are
+/// applied once detection has finished are not supported.
+///
+/// Returns the number of frames read once the stream ends or `cancel_flag`
+/// is set. Otherwise returns `DetectionError::InvalidOptions` before
+/// connecting if the options cannot be applied, or the last error once
+/// `max_reconnects` is exceeded, whose partial results only count
+/// the frames read.
+pub fn detect_scene_changes_live<T: Pixel, F: FnMut(CutDecision)>(
+    url: &str,
+    opts: DetectionOptions,
+    live_opts: &LiveOptions,
+    mut on_cut: F,
+    cancel_flag: Option<Arc<AtomicBool>>,
+) -> Result<usize, DetectionError> {
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+    let is_cancelled = || {
+        cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    };
+    let mut frame_offset = 0;
+    let mut reconnects = 0;
+    loop {
+        let mut dec = match FfmpegPipeDecoder::from_url_with_options(url, &live_opts.stream) {
+            Ok(dec) => dec,
+            Err(err) => {
+                reconnect(live_opts, &mut reconnects, err, frame_offset)?;
+                continue;
+            }
+        };
+        let video_details = dec.get_video_details();
+        let mut detector = SceneDetector::<T>::live(video_details, opts.clone())?;
+        let mut frames_read = 0;
+        let mut report = |cut: CutDecision| {
+            if frame_offset == 0 || cut.frameno > 0 {
+                on_cut(CutDecision {
+                    frameno: frame_offset + cut.frameno,
+                    ..cut
+                });
+            }
+        };
+        let result = loop {
+            if is_cancelled() {
+                break Ok(());
+            }
+            match dec.read_video_frame::<T>(&video_details) {
+                Ok(frame) => {
+                    frames_read += 1;
+                    if let Some(cut) = detector.push_frame(frame) {
+                        report(cut);
+                    }
+                }
+                Err(DecodeError::EndOfStream) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+        detector.flush().into_iter().for_each(report);
+        frame_offset += frames_read;
+        if frames_read > 0 {
+            reconnects = 0;
+        }
+        match result {
+            Ok(()) => return Ok(frame_offset),
+            Err(err) => reconnect(live_opts, &mut reconnects, err, frame_offset)?,
+        }
+    }
+}
+
+/// Waits to reconnect after `err`, or returns it if there have been
+/// too many consecutive reconnections.
+fn reconnect(
+    live_opts: &LiveOptions,
+    reconnects: &mut usize,
+    err: DecodeError,
+    frames_read: usize,
+) -> Result<(), DetectionError> {
+    if live_opts
+        .max_reconnects
+        .is_some_and(|max| *reconnects >= max)
+    {
+        return Err(DetectionError::from_decode_error(
+            err,
+            DetectionResults {
+                frame_count: frames_read,
+                ..Default::default()
+            },
+        ));
+    }
+    *reconnects += 1;
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = %err, reconnects = *reconnects, "reconnecting to stream");
+    thread::sleep(live_opts.reconnect_delay);
+    Ok(())
+}
@@ -1,12 +1,17 @@
 use av_scenechange::*;
 use clap::{App, Arg};
+use rav1e::prelude::Rational;
 use std::fs::File;
-use std::io::{self, BufReader, Read, Write};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process;
+use std::time::Duration;
 
 fn main() {
     #[cfg(feature = "tracing")]
     use rust_hawktracer::*;
     init_logger();
+    #[cfg(feature = "tracing")]
+    init_tracing();
 
     #[cfg(feature = "tracing")]
     let instance = HawktracerInstance::new();
@@ -16,7 +21,7 @@ fn main() {
         buffer_size: 4096,
     });
 
-    let matches = App::new("av-scenechange")
+    let app = App::new("av-scenechange")
         .arg(
             Arg::with_name("INPUT")
                 .help("Sets the input file to use")
@@ -25,7 +30,7 @@ fn main() {
         )
         .arg(
             Arg::with_name("SPEED_MODE")
-                .help("Speed level for scene-change detection, 0: best quality, 1: fastest mode")
+                .help("Speed level for scene-change detection, 0: best quality, 1: fastest mode, 2: histogram-based, 3: edge change ratio")
                 .long("speed")
                 .short("s")
                 .takes_value(true)
@@ -36,6 +41,55 @@ fn main() {
                 .help("Do not detect short scene flashes and exclude them as scene cuts")
                 .long("no-flash-detection"),
         )
+        .arg(
+            Arg::with_name("FLASH_LENGTH")
+                .help("Remove flashes of up to this many frames from the scene changes")
+                .long("flash-length")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("FLASH_THRESHOLD")
+                .help("Remove flashes whose surrounding frames differ by less than this, from 0 to 1, from the scene changes")
+                .long("flash-threshold")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("FIELD_HANDLING")
+                .help("How to analyze interlaced input: whole frames, the first field line-doubled, or the first field at half height")
+                .long("field-handling")
+                .takes_value(true)
+                .possible_values(&["weave", "bob", "first-field"])
+                .default_value("weave"),
+        )
+        .arg(
+            Arg::with_name("CROP")
+                .help("Only analyze this area of the frame, such as the picture within letterbox bars, given as WIDTHxHEIGHT+X+Y")
+                .long("crop")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("DETECT_LETTERBOX")
+                .help("Find black bars around the picture in the first frames, and only analyze the area within them")
+                .long("detect-letterbox")
+                .conflicts_with("CROP"),
+        )
+        .arg(
+            Arg::with_name("WEIGHT_MASK")
+                .help("Weight the areas of the frame by a binary PGM image stretched over it, where black areas are ignored, such as tickers or subtitles")
+                .long("weight-mask")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("DETECT_PULLDOWN")
+                .help("Leave frames repeated by 3:2 pulldown out of detection, and report the pulldown cadence")
+                .long("detect-pulldown"),
+        )
+        .arg(
+            Arg::with_name("MAX_MEMORY")
+                .help("Lower the lookahead as needed to keep the frame buffers within this many MiB")
+                .long("max-memory")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("MIN_KEYINT")
                 .help("Sets a minimum interval between two consecutive scenecuts")
@@ -48,6 +102,73 @@ fn main() {
                 .long("max-scenecut")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("MIN_KEYINT_POLICY")
+                .help("Which of two scenecuts closer than the minimum interval is kept")
+                .long("min-scenecut-policy")
+                .takes_value(true)
+                .possible_values(&["first", "last", "stronger"])
+                .default_value("first"),
+        )
+        .arg(
+            Arg::with_name("LUMA_ONLY")
+                .help("Discard chroma while decoding, which speeds up analysis of color input")
+                .long("luma-only"),
+        )
+        .arg(
+            Arg::with_name("DETECT_CHROMA_CHANGES")
+                .help("Also detect scene changes between scenes with similar luma but different colors")
+                .long("detect-chroma-changes"),
+        )
+        .arg(
+            Arg::with_name("DETECT_BLACK_FRAMES")
+                .help("Also report runs of black frames, such as those around commercial breaks")
+                .long("detect-black-frames"),
+        )
+        .arg(
+            Arg::with_name("DUPLICATE_THRESHOLD")
+                .help("Also report runs of duplicate frames, whose mean luma difference from the previous frame is at most this, from 0 to 1")
+                .long("duplicate-threshold")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("STALL_TIMEOUT")
+                .help("Fail if no input is received for this many seconds")
+                .long("stall-timeout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("POST_PROCESS")
+                .help("JSON file with a list of post-processing steps to apply to the scene changes")
+                .long("post-process")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("TAG")
+                .help("Copy the input to stdout as y4m, tagging the first frame of each scene, instead of printing the results")
+                .long("tag"),
+        )
+        .arg(
+            Arg::with_name("RAW_FORMAT")
+                .help("Read the input as headerless video in this pixel format, such as yuv420p, yuv420p10le or p010le, instead of y4m")
+                .long("raw")
+                .takes_value(true)
+                .requires("RAW_SIZE")
+                .conflicts_with_all(&["LUMA_ONLY", "TAG"]),
+        )
+        .arg(
+            Arg::with_name("RAW_SIZE")
+                .help("The width and height of headerless input, such as 1920x1080")
+                .long("raw-size")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("RAW_FPS")
+                .help("The frame rate of headerless input, such as 24 or 24000/1001")
+                .long("raw-fps")
+                .takes_value(true)
+                .default_value("25"),
+        )
         .arg(
             Arg::with_name("OUTPUT")
                 .help("File to write results in")
@@ -55,15 +176,107 @@ fn main() {
                 .short("o")
                 .takes_value(true),
         )
-        .get_matches();
-    let input = match matches.value_of("INPUT").unwrap() {
-        "-" => Box::new(io::stdin()) as Box<dyn Read>,
-        f => Box::new(File::open(&f).unwrap()) as Box<dyn Read>,
+        .arg(
+            Arg::with_name("REFERENCE")
+                .help("Reference cut list to evaluate the detected scene changes against, printing precision and recall to stderr")
+                .long("reference")
+                .takes_value(true)
+                .conflicts_with("TAG"),
+        )
+        .arg(
+            Arg::with_name("REFERENCE_FORMAT")
+                .help("Format of the reference cut list, either frame numbers or shots given by their first and last frame")
+                .long("reference-format")
+                .takes_value(true)
+                .possible_values(&["frames", "shots"])
+                .default_value("frames"),
+        )
+        .arg(
+            Arg::with_name("TOLERANCE")
+                .help("Number of frames a scene change may be away from a reference cut to match it")
+                .long("tolerance")
+                .takes_value(true)
+                .default_value("2"),
+        );
+    // Thumbnails can only be saved with an image encoder
+    #[cfg(feature = "images")]
+    let app = app.arg(
+        Arg::with_name("THUMBNAILS")
+            .help("Directory to save the first frame of each scene to, as PNG files named by frame number")
+            .long("thumbnails")
+            .takes_value(true)
+            .conflicts_with("TAG"),
+    );
+    let matches = app.get_matches();
+    let mut input = match matches.value_of("INPUT").unwrap() {
+        "-" => Box::new(io::stdin()) as Box<dyn Read + Send>,
+        f => Box::new(File::open(f).unwrap()) as Box<dyn Read + Send>,
     };
+    if let Some(timeout) = matches.value_of("STALL_TIMEOUT") {
+        let timeout = timeout
+            .parse()
+            .expect("Stall timeout must be a positive number");
+        input = Box::new(TimeoutReader::new(input, Duration::from_secs_f64(timeout)));
+    }
     let mut reader = BufReader::new(input);
 
+    let flash_suppression =
+        if matches.is_present("FLASH_LENGTH") || matches.is_present("FLASH_THRESHOLD") {
+            let defaults = FlashSuppression::default();
+            Some(FlashSuppression {
+                threshold: matches
+                    .value_of("FLASH_THRESHOLD")
+                    .map_or(defaults.threshold, |val| {
+                        val.parse()
+                            .expect("Flash threshold must be a number from 0 to 1")
+                    }),
+                max_length: matches
+                    .value_of("FLASH_LENGTH")
+                    .map_or(defaults.max_length, |val| {
+                        val.parse()
+                            .expect("Flash length must be a positive integer")
+                    }),
+            })
+        } else {
+            None
+        };
     let mut opts = DetectionOptions {
         detect_flashes: !matches.is_present("NO_FLASH_DETECT"),
+        flash_suppression,
+        detect_chroma_changes: matches.is_present("DETECT_CHROMA_CHANGES"),
+        detect_black_frames: if matches.is_present("DETECT_BLACK_FRAMES") {
+            Some(BlackFrameDetection::default())
+        } else {
+            None
+        },
+        duplicate_frame_threshold: matches.value_of("DUPLICATE_THRESHOLD").map(|val| {
+            val.parse()
+                .expect("Duplicate threshold must be a number from 0 to 1")
+        }),
+        field_handling: match matches.value_of("FIELD_HANDLING").unwrap() {
+            "bob" => FieldHandling::Bob,
+            "first-field" => FieldHandling::FirstField,
+            _ => FieldHandling::Weave,
+        },
+        crop: matches
+            .value_of("CROP")
+            .map(|val| parse_crop(val).expect("Crop must be given as WIDTHxHEIGHT+X+Y")),
+        detect_letterbox: if matches.is_present("DETECT_LETTERBOX") {
+            Some(LetterboxDetection::default())
+        } else {
+            None
+        },
+        weight_mask: matches.value_of("WEIGHT_MASK").map(|path| {
+            let data = std::fs::read(path).expect("Could not read weight mask");
+            parse_pgm_mask(&data).expect("Weight mask must be a binary PGM image")
+        }),
+        detect_pulldown: matches.is_present("DETECT_PULLDOWN"),
+        max_memory: matches.value_of("MAX_MEMORY").map(|val| {
+            val.parse::<usize>()
+                .expect("Max memory must be a positive integer")
+                * 1024
+                * 1024
+        }),
         min_scenecut_distance: matches.value_of("MIN_KEYINT").map(|val| {
             val.parse()
                 .expect("Min-scenecut must be a positive integer")
@@ -72,34 +285,217 @@ fn main() {
             val.parse()
                 .expect("Max-scenecut must be a positive integer")
         }),
+        min_distance_policy: match matches.value_of("MIN_KEYINT_POLICY").unwrap() {
+            "last" => MinDistancePolicy::KeepLast,
+            "stronger" => MinDistancePolicy::KeepStronger,
+            _ => MinDistancePolicy::KeepFirst,
+        },
         ..Default::default()
     };
 
+    if let Some(post_process) = matches.value_of("POST_PROCESS") {
+        let spec = File::open(post_process).expect("Could not open post-processing file");
+        opts.post_process =
+            serde_json::from_reader(BufReader::new(spec)).expect("Invalid post-processing steps");
+    }
+
     if let Some(speed_mode) = matches.value_of("SPEED_MODE") {
         opts.analysis_speed = match speed_mode {
             "0" => SceneDetectionSpeed::Standard,
             "1" => SceneDetectionSpeed::Fast,
-            _ => panic!("Speed mode must be in range [0; 1]"),
+            "2" => SceneDetectionSpeed::Histogram,
+            "3" => SceneDetectionSpeed::EdgeChangeRatio,
+            _ => panic!("Speed mode must be in range [0; 3]"),
         };
     }
 
-    let mut dec = y4m::Decoder::new(&mut reader).unwrap();
-    let bit_depth = dec.get_bit_depth();
-    let results = if bit_depth == 8 {
-        detect_scene_changes::<_, u8>(&mut dec, opts, None)
+    let tag = matches.is_present("TAG");
+    #[cfg(feature = "images")]
+    let scene_frame_callback = matches.value_of("THUMBNAILS").map(thumbnail_writer);
+    #[cfg(not(feature = "images"))]
+    let scene_frame_callback = None;
+    let results = if let Some(pix_fmt) = matches.value_of("RAW_FORMAT") {
+        let (width, height) = parse_raw_size(matches.value_of("RAW_SIZE").unwrap())
+            .expect("Raw size must be given as WIDTHxHEIGHT");
+        let framerate = parse_framerate(matches.value_of("RAW_FPS").unwrap())
+            .expect("Raw frame rate must be a positive number or fraction");
+        let mut dec =
+            RawDecoder::with_pix_fmt(&mut reader, width, height, pix_fmt, framerate).unwrap();
+        detect(&mut dec, opts.clone(), scene_frame_callback)
     } else {
-        detect_scene_changes::<_, u16>(&mut dec, opts, None)
+        let mut dec = if matches.is_present("LUMA_ONLY") {
+            Y4mDecoder::luma_only(&mut reader).unwrap()
+        } else {
+            Y4mDecoder::new(&mut reader).unwrap()
+        };
+        if tag {
+            let stdout = io::stdout();
+            let output = BufWriter::new(stdout.lock());
+            if dec.get_video_details().bit_depth == 8 {
+                tag_scene_changes::<_, _, u8>(&mut dec, output, opts.clone())
+            } else {
+                tag_scene_changes::<_, _, u16>(&mut dec, output, opts.clone())
+            }
+        } else {
+            detect(&mut dec, opts.clone(), scene_frame_callback)
+        }
+    };
+    // Output the results for the frames analyzed before any error,
+    // so that they are not lost
+    let (results, failed) = match results {
+        Ok(results) => (results, false),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            (err.into_partial_results(), true)
+        }
     };
-    print!("{}", serde_json::to_string(&results).unwrap());
+    if let Some(reference) = matches.value_of("REFERENCE") {
+        let reference = std::fs::read_to_string(reference).expect("Could not read reference");
+        let reference = if matches.value_of("REFERENCE_FORMAT") == Some("shots") {
+            eval::parse_shot_list(&reference)
+        } else {
+            eval::parse_frame_list(&reference)
+        }
+        .unwrap_or_else(|err| panic!("{}", err));
+        let tolerance = matches
+            .value_of("TOLERANCE")
+            .unwrap()
+            .parse()
+            .expect("Tolerance must be a positive integer");
+        let evaluation = eval::evaluate(&results, &reference, tolerance);
+        eprintln!(
+            "Precision: {:.4}, recall: {:.4}, F1: {:.4} ({} true positives, {} false positives, {} false negatives)",
+            evaluation.precision,
+            evaluation.recall,
+            evaluation.f1,
+            evaluation.true_positives,
+            evaluation.false_positives,
+            evaluation.false_negatives
+        );
+    }
+    let run = DetectionRun::new(opts, results);
+    if !tag {
+        print!("{}", run.to_json().unwrap());
+    }
 
     if matches.is_present("OUTPUT") {
         let output_file = matches.value_of("OUTPUT").unwrap();
-        let mut file = File::create(&output_file).expect("Could not create file");
+        let mut file = File::create(output_file).expect("Could not create file");
 
-        let output =
-            serde_json::to_string_pretty(&results).expect("Could not convert results into json");
+        let output = run
+            .to_json_pretty()
+            .expect("Could not convert results into json");
         file.write_all(&output.into_bytes()).unwrap();
     }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn detect<D: Decoder + Send>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    scene_frame_callback: Option<SceneFrameCallback>,
+) -> Result<DetectionResults, DetectionError> {
+    if dec.get_video_details().bit_depth == 8 {
+        detect_scene_changes::<_, u8>(dec, opts, None, scene_frame_callback, None)
+    } else {
+        detect_scene_changes::<_, u16>(dec, opts, None, scene_frame_callback, None)
+    }
+}
+
+#[cfg(feature = "images")]
+fn thumbnail_writer(dir: &str) -> SceneFrameCallback {
+    let dir = std::path::PathBuf::from(dir);
+    std::fs::create_dir_all(&dir).expect("Could not create thumbnail directory");
+    Box::new(move |frame: SceneFrame| {
+        frame
+            .save(dir.join(format!("{:06}.png", frame.frameno)))
+            .expect("Could not save thumbnail");
+    })
+}
+
+fn parse_raw_size(size: &str) -> Option<(usize, usize)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+fn parse_crop(crop: &str) -> Option<CropRect> {
+    let (size, offset) = crop.split_once('+')?;
+    let (width, height) = parse_raw_size(size)?;
+    let (x, y) = offset.split_once('+')?;
+    Some(CropRect {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width,
+        height,
+    })
+}
+
+/// Parses a binary PGM image into a weight mask with a block for each pixel,
+/// where the maximum gray value is a weight of `1.0`.
+fn parse_pgm_mask(data: &[u8]) -> Option<WeightMask> {
+    // The header is the magic number and three numbers separated by whitespace,
+    // followed by a single whitespace character before the pixels
+    let mut fields = Vec::with_capacity(4);
+    let mut pos = 0;
+    while fields.len() < 4 {
+        while data.get(pos)?.is_ascii_whitespace() {
+            pos += 1;
+        }
+        if data[pos] == b'#' {
+            // Comments, such as those written by image editors, run to the end of the line
+            while *data.get(pos)? != b'\n' {
+                pos += 1;
+            }
+            continue;
+        }
+        let start = pos;
+        while !data.get(pos)?.is_ascii_whitespace() {
+            pos += 1;
+        }
+        fields.push(std::str::from_utf8(&data[start..pos]).ok()?);
+    }
+    if fields[0] != "P5" {
+        return None;
+    }
+    let columns: usize = fields[1].parse().ok()?;
+    let rows: usize = fields[2].parse().ok()?;
+    let max_value: u16 = fields[3].parse().ok()?;
+    if max_value == 0 || max_value > 255 {
+        return None;
+    }
+    let pixels = data.get(pos + 1..pos + 1 + columns * rows)?;
+    Some(WeightMask {
+        columns,
+        rows,
+        weights: pixels
+            .iter()
+            .map(|&px| f32::from(px) / f32::from(max_value))
+            .collect(),
+    })
+}
+
+fn parse_framerate(framerate: &str) -> Option<Rational> {
+    let (num, den) = match framerate.split_once('/') {
+        Some((num, den)) => (num.parse().ok()?, den.parse().ok()?),
+        None => (framerate.parse().ok()?, 1),
+    };
+    if num == 0 || den == 0 {
+        return None;
+    }
+    Some(Rational::new(num, den))
+}
+
+/// Prints the spans and events of detection to stderr, filtered by
+/// the `AVSC_TRACE` environment variable, such as `AVSC_TRACE=debug`.
+#[cfg(feature = "tracing")]
+fn init_tracing() {
+    // Fails if the logger has already been set up for the devel feature
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_env("AVSC_TRACE"))
+        .with_writer(io::stderr)
+        .try_init();
 }
 
 #[cfg(not(feature = "devel"))]
@@ -0,0 +1,65 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{ChromaSampling, Frame, Pixel};
+
+/// Weights for the areas of the frame given to the detector,
+/// such as to ignore a scoreboard or burned-in subtitles.
+///
+/// The weights are given for a grid of blocks stretched over the frame,
+/// which may be as fine as the frame itself for per-pixel weights,
+/// although a coarse grid is usually enough and much smaller.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeightMask {
+    /// The number of columns of blocks.
+    pub columns: usize,
+    /// The number of rows of blocks.
+    pub rows: usize,
+    /// The weight of each block in row-major order, from `0.0`,
+    /// which ignores the block, to `1.0`, which leaves it unchanged.
+    pub weights: Vec<f32>,
+}
+
+impl WeightMask {
+    /// Whether there is a weight for each block.
+    pub(crate) fn is_valid(&self) -> bool {
+        self.columns > 0 && self.rows > 0 && self.weights.len() == self.columns * self.rows
+    }
+}
+
+/// Copies the luma plane of a frame into a monochrome frame, moving each pixel
+/// towards mid-gray in proportion to the weight of its block, so that
+/// differences in blocks with lower weights contribute less to the costs.
+pub(crate) fn apply_weights<T: Pixel>(
+    frame: &Frame<T>,
+    details: &VideoDetails,
+    mask: &WeightMask,
+) -> Frame<T> {
+    let mut out: Frame<T> = Frame::new_with_padding(
+        details.width,
+        details.height,
+        ChromaSampling::Cs400,
+        LUMA_PADDING,
+    );
+    let mid = (1u32 << (details.bit_depth - 1)) as f32;
+    let columns = (0..details.width)
+        .map(|x| x * mask.columns / details.width)
+        .collect::<Vec<_>>();
+    let stride = out.planes[0].cfg.stride;
+    let data = out.planes[0].data_origin_mut();
+    for y in 0..details.height {
+        let weights = &mask.weights[y * mask.rows / details.height * mask.columns..];
+        let row = metrics::row(&frame.planes[0], y, details.width);
+        for ((out_px, &px), &column) in data[y * stride..y * stride + details.width]
+            .iter_mut()
+            .zip(row)
+            .zip(&columns)
+        {
+            let px = Into::<u32>::into(px) as f32;
+            *out_px =
+                T::cast_from((mid + weights[column].clamp(0.0, 1.0) * (px - mid)).round() as u32);
+        }
+    }
+    out
+}
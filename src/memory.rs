@@ -0,0 +1,109 @@
+use crate::convert::{self, LUMA_PADDING};
+use crate::decoder::VideoDetails;
+use crate::DetectionOptions;
+use rav1e::prelude::ChromaSampling;
+
+/// The configuration chosen to keep the frame buffers within `max_memory`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryConfig {
+    /// The lookahead distance used for detection.
+    pub lookahead_distance: usize,
+    /// The estimated peak memory used by frame buffers, in bytes.
+    pub estimated_memory: usize,
+    /// Whether the estimate is within `max_memory`.
+    ///
+    /// If even a lookahead distance of 1 does not fit,
+    /// detection still runs with it, and this is `false`.
+    pub within_limit: bool,
+}
+
+/// Lowers the lookahead distance in `opts` until the frame buffers
+/// are estimated to fit within `opts.max_memory`, if set.
+///
+/// Returns the options to run detection with, and the chosen configuration.
+pub(crate) fn fit_to_memory(
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> (DetectionOptions, Option<MemoryConfig>) {
+    let max_memory = match opts.max_memory {
+        Some(max_memory) => max_memory,
+        None => return (opts.clone(), None),
+    };
+    let mut lookahead_distance = opts.lookahead_distance;
+    while lookahead_distance > 1
+        && estimate_memory(video_details, opts, lookahead_distance) > max_memory
+    {
+        lookahead_distance -= 1;
+    }
+    let estimated_memory = estimate_memory(video_details, opts, lookahead_distance);
+    let config = MemoryConfig {
+        lookahead_distance,
+        estimated_memory,
+        within_limit: estimated_memory <= max_memory,
+    };
+    let opts = DetectionOptions {
+        lookahead_distance,
+        ..opts.clone()
+    };
+    (opts, Some(config))
+}
+
+/// Estimates the peak memory used by the frames `detect_scene_changes` holds
+/// with the given lookahead distance, in bytes.
+///
+/// This counts the frames queued for lookahead, the frames queued between
+/// decoding and analysis, and the copies of the queued frames given to the
+/// detector if they are weighted, downscaled, cropped or split into fields,
+/// and the frames sampled for black bars, but not memory allocated
+/// internally by the detector or the decoder.
+///
+/// The copies are counted at their full size if black bars may be cropped.
+fn estimate_memory(
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+    lookahead_distance: usize,
+) -> usize {
+    // The lookahead frames and the frame before them, the same number again
+    // queued by the decoding thread, and the frames being decoded and handled
+    let queued_frames = lookahead_distance + 2;
+    let held_frames = 2 * queued_frames + 1;
+    let mut memory = held_frames * frame_size(video_details);
+    let field_details = convert::field_details(video_details, opts.field_handling);
+    if opts.downscale.is_some_and(|factor| factor > 1)
+        || opts.crop.is_some()
+        || opts.detect_letterbox.is_some()
+        || opts.weight_mask.is_some()
+        || field_details.is_some()
+    {
+        let source_details = field_details.unwrap_or(*video_details);
+        let cropped_details = opts.crop.map_or(source_details, |crop| {
+            convert::crop_details(
+                &source_details,
+                &crop.for_source(video_details, &source_details),
+            )
+        });
+        let analysis_details = convert::analysis_details(&cropped_details, opts.downscale);
+        memory += queued_frames * frame_size(&analysis_details);
+    }
+    if let Some(detection) = opts.detect_letterbox {
+        // The frames sampled for black bars are held until they are analyzed
+        memory += detection.frames * frame_size(video_details);
+    }
+    memory
+}
+
+/// Returns the size of a padded frame, in bytes.
+fn frame_size(video_details: &VideoDetails) -> usize {
+    let chroma_sampling = video_details.chroma_sampling;
+    let bytes = if video_details.bit_depth > 8 { 2 } else { 1 };
+    let width = video_details.width + 2 * LUMA_PADDING;
+    let height = video_details.height + 2 * LUMA_PADDING;
+    let chroma_size = if chroma_sampling == ChromaSampling::Cs400 {
+        0
+    } else {
+        let (chroma_width, chroma_height) = chroma_sampling.get_chroma_dimensions(width, height);
+        2 * chroma_width * chroma_height
+    };
+    (width * height + chroma_size) * bytes
+}
@@ -0,0 +1,116 @@
+use rav1e::prelude::{Pixel, Plane};
+
+/// The normalized difference above which a cell of a difference grid
+/// is considered to have changed.
+pub(crate) const CHANGED_CELL_THRESHOLD: f32 = 0.05;
+
+/// Returns the visible part of row `y` of a plane.
+pub(crate) fn row<T: Pixel>(plane: &Plane<T>, y: usize, width: usize) -> &[T] {
+    let start = y * plane.cfg.stride;
+    &plane.data_origin()[start..start + width]
+}
+
+/// Splits the plane into a `grid_size` by `grid_size` grid of cells,
+/// and computes the mean absolute difference between the two planes
+/// for each cell, normalized to the range `0.0..=1.0`.
+///
+/// Cells are returned in row-major order.
+pub(crate) fn difference_grid<T: Pixel>(
+    prev: &Plane<T>,
+    cur: &Plane<T>,
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+    grid_size: usize,
+) -> Vec<f32> {
+    let mut sums = vec![0u64; grid_size * grid_size];
+    let mut counts = vec![0u64; grid_size * grid_size];
+    for y in 0..height {
+        let cell_row = y * grid_size / height * grid_size;
+        let rows = row(prev, y, width).iter().zip(row(cur, y, width));
+        for (x, (&a, &b)) in rows.enumerate() {
+            let cell = cell_row + x * grid_size / width;
+            sums[cell] += u64::from((Into::<i32>::into(a) - Into::<i32>::into(b)).unsigned_abs());
+            counts[cell] += 1;
+        }
+    }
+
+    let max_value = ((1u64 << bit_depth) - 1) as f32;
+    sums.iter()
+        .zip(counts.iter())
+        .map(|(&sum, &count)| {
+            if count == 0 {
+                0.0
+            } else {
+                sum as f32 / count as f32 / max_value
+            }
+        })
+        .collect()
+}
+
+/// Computes the mean absolute difference between two planes,
+/// normalized to the range `0.0..=1.0`.
+pub(crate) fn mean_difference<T: Pixel>(
+    prev: &Plane<T>,
+    cur: &Plane<T>,
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+) -> f32 {
+    difference_grid(prev, cur, width, height, bit_depth, 1)[0]
+}
+
+/// Computes the mean value of a plane, normalized to the range `0.0..=1.0`.
+pub(crate) fn mean_value<T: Pixel>(
+    plane: &Plane<T>,
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+) -> f32 {
+    let sum = (0..height)
+        .flat_map(|y| row(plane, y, width).iter())
+        .map(|&px| u64::from(Into::<u32>::into(px)))
+        .sum::<u64>();
+    let max_value = ((1u64 << bit_depth) - 1) as f32;
+    sum as f32 / (width * height) as f32 / max_value
+}
+
+/// Measures how closely `middle` lies on a linear blend between
+/// `before` and `after`, as in a fade or dissolve.
+///
+/// Returns the mean absolute difference between `before` and `after`,
+/// and the mean absolute deviation of `middle` from their average,
+/// both normalized to the range `0.0..=1.0`.
+pub(crate) fn blend_deviation<T: Pixel>(
+    before: &Plane<T>,
+    middle: &Plane<T>,
+    after: &Plane<T>,
+    width: usize,
+    height: usize,
+    bit_depth: usize,
+) -> (f32, f32) {
+    let mut change = 0u64;
+    let mut deviation = 0u64;
+    for y in 0..height {
+        let rows = row(before, y, width)
+            .iter()
+            .zip(row(middle, y, width))
+            .zip(row(after, y, width));
+        for ((&a, &b), &c) in rows {
+            let (a, b, c) = (
+                Into::<i32>::into(a),
+                Into::<i32>::into(b),
+                Into::<i32>::into(c),
+            );
+            change += u64::from((c - a).unsigned_abs());
+            deviation += u64::from((a + c - 2 * b).unsigned_abs());
+        }
+    }
+
+    let max_value = ((1u64 << bit_depth) - 1) as f32;
+    let pixels = (width * height) as f32;
+    (
+        change as f32 / pixels / max_value,
+        deviation as f32 / 2.0 / pixels / max_value,
+    )
+}
@@ -0,0 +1,329 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::{
+    DecodeError, Decoder, ErrorPolicy, FieldOrder, FrameGap, FrameMeta, VideoDetails, VideoStream,
+};
+use dav1d::{PixelLayout, PlanarImageComponent};
+use matroska_demuxer::{MatroskaFile, TrackType};
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel, Rational};
+use std::collections::VecDeque;
+use std::io::{Read, Seek};
+use std::mem;
+use std::time::Duration;
+
+/// The Matroska codec ID of AV1 video.
+const AV1_CODEC_ID: &str = "V_AV1";
+
+/// Decodes AV1 video from Matroska or WebM files, using dav1d,
+/// without requiring ffmpeg.
+///
+/// The first video track is decoded, unless another is selected
+/// with `with_video_stream`, and must be AV1.
+/// If the track has no default duration,
+/// the video is assumed to be 30 frames per second.
+///
+/// Frames skipped because of the error policy are reported as frame gaps.
+/// If the track has a default duration, frames missing from the timestamps
+/// of the container are also reported, including the skipped frames.
+pub struct MkvDecoder<R: Read + Seek> {
+    demuxer: MatroskaFile<R>,
+    decoder: dav1d::Decoder,
+    track: u64,
+    video_details: VideoDetails,
+    /// A picture decoded ahead of time, to read the video details from.
+    pending_picture: Option<dav1d::Picture>,
+    /// Whether dav1d has not yet accepted all of the last packet sent to it.
+    pending_data: bool,
+    end_of_stream: bool,
+    packet: matroska_demuxer::Frame,
+    error_policy: ErrorPolicy,
+    /// The number of packets and frames skipped because of `error_policy`.
+    errors_skipped: usize,
+    /// The number of packets and frames skipped since the last picture.
+    skipped_since_picture: usize,
+    /// The duration of a Matroska timestamp tick, in nanoseconds.
+    timestamp_scale: u64,
+    /// The default duration of the video track, in nanoseconds, if set.
+    frame_duration: Option<u64>,
+    /// The timestamp of the last picture, in nanoseconds.
+    last_timestamp: Option<i64>,
+    frames_decoded: usize,
+    frame_gaps: Vec<FrameGap>,
+    /// The timestamp of each packet sent to dav1d whose picture
+    /// has not been returned yet, and whether it is a keyframe.
+    sent_packets: VecDeque<(i64, bool)>,
+    frame_meta: Option<FrameMeta>,
+}
+
+impl<R: Read + Seek> MkvDecoder<R> {
+    /// Reads the Matroska headers from `reader`,
+    /// and decodes the first frame to determine the video details.
+    pub fn new(reader: R) -> Result<Self, DecodeError> {
+        Self::with_video_stream(reader, &VideoStream::Default)
+    }
+
+    /// Reads the Matroska headers from `reader`, and decodes the first frame
+    /// of the selected video track to determine the video details.
+    ///
+    /// A track selected by language or title is the first track which matches.
+    pub fn with_video_stream(reader: R, stream: &VideoStream) -> Result<Self, DecodeError> {
+        let demuxer = MatroskaFile::open(reader).map_err(map_demux_error)?;
+        let mut video_tracks = demuxer
+            .tracks()
+            .iter()
+            .filter(|track| track.track_type() == TrackType::Video);
+        let track = match stream {
+            VideoStream::Default => video_tracks.next(),
+            VideoStream::Index(index) => video_tracks.nth(*index),
+            VideoStream::Language(language) => {
+                video_tracks.find(|track| track.language() == Some(language.as_str()))
+            }
+            VideoStream::Title(title) => {
+                video_tracks.find(|track| track.name() == Some(title.as_str()))
+            }
+        }
+        .ok_or_else(|| DecodeError::InvalidInput("No matching video track found".to_string()))?;
+        if track.codec_id() != AV1_CODEC_ID {
+            return Err(DecodeError::InvalidInput(format!(
+                "Unsupported codec {}, only AV1 is supported",
+                track.codec_id()
+            )));
+        }
+        let frame_duration = track.default_duration().map(|duration| duration.get());
+        let time_base = match track.default_duration() {
+            Some(duration) => Rational::new(duration.get(), 1_000_000_000),
+            None => Rational::new(1, 30),
+        };
+        let track = track.track_number().get();
+        let timestamp_scale = demuxer.info().timestamp_scale().get();
+        let decoder = dav1d::Decoder::new().map_err(map_dav1d_error)?;
+
+        let mut dec = MkvDecoder {
+            demuxer,
+            decoder,
+            track,
+            video_details: VideoDetails {
+                time_base,
+                ..Default::default()
+            },
+            pending_picture: None,
+            pending_data: false,
+            end_of_stream: false,
+            packet: matroska_demuxer::Frame::default(),
+            error_policy: ErrorPolicy::Fail,
+            errors_skipped: 0,
+            skipped_since_picture: 0,
+            timestamp_scale,
+            frame_duration,
+            last_timestamp: None,
+            frames_decoded: 0,
+            frame_gaps: Vec::new(),
+            sent_packets: VecDeque::new(),
+            frame_meta: None,
+        };
+        let picture = dec.next_picture()?;
+        dec.video_details = VideoDetails {
+            width: picture.width() as usize,
+            height: picture.height() as usize,
+            bit_depth: picture.bit_depth(),
+            chroma_sampling: map_pixel_layout(picture.pixel_layout()),
+            chroma_sample_position: ChromaSamplePosition::Unknown,
+            time_base,
+            // AV1 has no interlaced coding tools
+            field_order: FieldOrder::Progressive,
+        };
+        dec.pending_picture = Some(picture);
+        Ok(dec)
+    }
+
+    /// Sets what happens when a packet or frame fails to decode.
+    ///
+    /// Errors are only skipped after the first frame, which is decoded
+    /// by `new` to determine the video details. Errors reading the
+    /// Matroska container itself always stop decoding.
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// The number of packets and frames which were skipped
+    /// because they failed to decode, as allowed by the error policy.
+    pub fn errors_skipped(&self) -> usize {
+        self.errors_skipped
+    }
+
+    /// Skips a packet or frame which failed to decode if `skip` is set,
+    /// or returns the error.
+    fn skip_error(&mut self, err: dav1d::Error, skip: bool) -> Result<(), DecodeError> {
+        if !skip {
+            return Err(map_dav1d_error(err));
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = ?err, "skipping undecodable data");
+        self.errors_skipped += 1;
+        self.skipped_since_picture += 1;
+        Ok(())
+    }
+
+    /// Records the frames missing before a picture about to be returned,
+    /// from its timestamp if the frame duration is known,
+    /// or else from the packets and frames skipped before it.
+    fn record_gap(&mut self, picture: &dav1d::Picture) {
+        let skipped = mem::take(&mut self.skipped_since_picture);
+        let timestamp = picture.timestamp();
+        let missing = match (self.frame_duration, self.last_timestamp, timestamp) {
+            (Some(duration), Some(last), Some(timestamp)) => {
+                let frames = ((timestamp - last) as f64 / duration as f64).round();
+                // Negative distances saturate to zero
+                (frames as usize).saturating_sub(1)
+            }
+            _ => skipped,
+        };
+        if missing > 0 {
+            self.frame_gaps.push(FrameGap {
+                frameno: self.frames_decoded,
+                missing,
+            });
+        }
+        if timestamp.is_some() {
+            self.last_timestamp = timestamp;
+        }
+        self.frames_decoded += 1;
+    }
+
+    /// Returns the properties of the packet a picture was decoded from,
+    /// dropping those of any packets before it which failed to decode.
+    fn picture_meta(&mut self, picture: &dav1d::Picture) -> FrameMeta {
+        let timestamp = picture.timestamp();
+        let mut is_keyframe = false;
+        while let Some(&(sent, keyframe)) = self.sent_packets.front() {
+            if Some(sent) > timestamp {
+                break;
+            }
+            self.sent_packets.pop_front();
+            if Some(sent) == timestamp {
+                is_keyframe = keyframe;
+                break;
+            }
+        }
+        FrameMeta {
+            pts: timestamp.map(|timestamp| Duration::from_nanos(timestamp.max(0) as u64)),
+            duration: self.frame_duration.map(Duration::from_nanos),
+            is_decoder_keyframe: is_keyframe,
+        }
+    }
+
+    fn next_picture(&mut self) -> Result<dav1d::Picture, DecodeError> {
+        if let Some(picture) = self.pending_picture.take() {
+            return Ok(picture);
+        }
+        loop {
+            if self.pending_data {
+                match self.decoder.send_pending_data() {
+                    Ok(()) => self.pending_data = false,
+                    Err(dav1d::Error::Again) => {}
+                    Err(err) => {
+                        // dav1d drops the packet after any other error
+                        self.skip_error(err, self.error_policy != ErrorPolicy::Fail)?;
+                        self.pending_data = false;
+                    }
+                }
+            }
+            match self.decoder.get_picture() {
+                Ok(picture) => return Ok(picture),
+                Err(dav1d::Error::Again) => {}
+                Err(err) => self.skip_error(err, self.error_policy == ErrorPolicy::SkipFrame)?,
+            }
+            if self.pending_data {
+                continue;
+            }
+            if self.end_of_stream {
+                return Err(DecodeError::EndOfStream);
+            }
+            self.send_next_packet()?;
+        }
+    }
+
+    /// Sends the next packet of the video track to dav1d,
+    /// or marks the end of the stream if there are none left.
+    fn send_next_packet(&mut self) -> Result<(), DecodeError> {
+        loop {
+            let has_packet = self
+                .demuxer
+                .next_frame(&mut self.packet)
+                .map_err(map_demux_error)?;
+            if !has_packet {
+                self.end_of_stream = true;
+                return Ok(());
+            }
+            if self.packet.track == self.track {
+                break;
+            }
+        }
+        let data = mem::take(&mut self.packet.data);
+        let timestamp = (self.packet.timestamp * self.timestamp_scale) as i64;
+        self.sent_packets
+            .push_back((timestamp, self.packet.is_keyframe == Some(true)));
+        match self.decoder.send_data(data, None, Some(timestamp), None) {
+            Ok(()) => Ok(()),
+            Err(dav1d::Error::Again) => {
+                self.pending_data = true;
+                Ok(())
+            }
+            Err(err) => {
+                self.sent_packets.pop_back();
+                self.skip_error(err, self.error_policy != ErrorPolicy::Fail)
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Decoder for MkvDecoder<R> {
+    fn get_video_details(&self) -> VideoDetails {
+        self.video_details
+    }
+
+    fn read_video_frame<T: Pixel>(&mut self, cfg: &VideoDetails) -> Result<Frame<T>, DecodeError> {
+        let picture = self.next_picture()?;
+        self.record_gap(&picture);
+        self.frame_meta = Some(self.picture_meta(&picture));
+        let bytes = if cfg.bit_depth > 8 { 2 } else { 1 };
+        let mut f: Frame<T> =
+            Frame::new_with_padding(cfg.width, cfg.height, cfg.chroma_sampling, LUMA_PADDING);
+
+        let y = PlanarImageComponent::Y;
+        f.planes[0].copy_from_raw_u8(&picture.plane(y), picture.stride(y) as usize, bytes);
+        // Monochrome input has no chroma planes to copy
+        if cfg.chroma_sampling != ChromaSampling::Cs400 {
+            let u = PlanarImageComponent::U;
+            let v = PlanarImageComponent::V;
+            f.planes[1].copy_from_raw_u8(&picture.plane(u), picture.stride(u) as usize, bytes);
+            f.planes[2].copy_from_raw_u8(&picture.plane(v), picture.stride(v) as usize, bytes);
+        }
+        Ok(f)
+    }
+
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        mem::take(&mut self.frame_gaps)
+    }
+
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        self.frame_meta
+    }
+}
+
+fn map_pixel_layout(layout: PixelLayout) -> ChromaSampling {
+    match layout {
+        PixelLayout::I400 => ChromaSampling::Cs400,
+        PixelLayout::I420 => ChromaSampling::Cs420,
+        PixelLayout::I422 => ChromaSampling::Cs422,
+        PixelLayout::I444 => ChromaSampling::Cs444,
+    }
+}
+
+fn map_demux_error(err: matroska_demuxer::DemuxError) -> DecodeError {
+    DecodeError::InvalidInput(err.to_string())
+}
+
+fn map_dav1d_error(err: dav1d::Error) -> DecodeError {
+    DecodeError::InvalidInput(format!("{:?}", err))
+}
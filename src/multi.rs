@@ -0,0 +1,196 @@
+use crate::flash::FlashTracker;
+use crate::parallel::apply_scenecut_distances;
+use crate::pipeline::{DetectionPipeline, FrameDecision};
+use crate::postprocess;
+use crate::range;
+use crate::{
+    check_options, scenecut_distances, DecodeError, Decoder, DetectionError, DetectionOptions,
+    DetectionResults, MinDistancePolicy, SceneDetectionSpeed, VideoDetails,
+};
+use rav1e::prelude::Pixel;
+use std::io;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Runs through a video clip once, detecting scene changes with each of
+/// the given algorithms, and returns the results for each in the same order.
+///
+/// This is intended for comparing algorithms, which otherwise requires
+/// decoding the clip once for each. The frames are shared between them,
+/// and are analyzed on the calling thread after each frame is decoded.
+/// As with `detect_scene_changes_parallel`, the scene changes, the raw
+/// scene changes and the frame scores are reported, but additional analysis
+/// enabled in `opts`, such as heatmaps, is not performed. Scenecut durations
+/// are converted to frame counts using the nominal frame rate of the clip,
+/// and `speed` counts the time spent decoding and analyzing with all algorithms.
+///
+/// If the decoder fails partway through the clip, every result is an error
+/// with the results for the frames analyzed until then.
+pub fn detect_scene_changes_multi<D: Decoder, T: Pixel>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    speeds: &[SceneDetectionSpeed],
+) -> Vec<Result<DetectionResults, DetectionError>> {
+    if let Err(reason) = check_options(&opts) {
+        return speeds
+            .iter()
+            .map(|_| Err(DetectionError::from_invalid_options(reason)))
+            .collect();
+    }
+    let first_frame = opts.frame_range.as_ref().map_or(0, |range| range.start);
+    let (mut dec, opts) = range::restrict_to_range(dec, &opts);
+    let dec = &mut dec;
+    let video_details = dec.get_video_details();
+    let applies_distances_after = opts.min_distance_policy != MinDistancePolicy::KeepFirst
+        || opts.min_scenecut_duration.is_some()
+        || opts.max_scenecut_duration.is_some();
+    let needs_strength = !opts.post_process.is_empty()
+        || opts.min_distance_policy == MinDistancePolicy::KeepStronger;
+    let pipeline_opts = if !applies_distances_after {
+        opts.clone()
+    } else {
+        // The scenecut distances are applied once all scene changes are known
+        DetectionOptions {
+            min_scenecut_distance: None,
+            max_scenecut_distance: None,
+            min_scenecut_duration: None,
+            max_scenecut_duration: None,
+            ..opts.clone()
+        }
+    };
+    let start_time = Instant::now();
+    let mut detections = speeds
+        .iter()
+        .map(|&speed| Detection {
+            pipeline: DetectionPipeline::<T>::new(
+                video_details,
+                DetectionOptions {
+                    analysis_speed: speed,
+                    ..pipeline_opts.clone()
+                },
+            ),
+            flash_tracker: opts
+                .flash_suppression
+                .map(|suppression| FlashTracker::new(suppression, video_details)),
+            cut_strengths: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let mut decode_error = None;
+    loop {
+        let frame = match dec.read_video_frame::<T>(&video_details) {
+            Ok(frame) => Arc::new(frame),
+            Err(DecodeError::EndOfStream) => break,
+            Err(err) => {
+                decode_error = Some(err);
+                break;
+            }
+        };
+        for detection in &mut detections {
+            let decisions = detection.pipeline.push(Arc::clone(&frame));
+            detection.handle_decisions(decisions, needs_strength, &video_details);
+        }
+    }
+    if decode_error.is_none() {
+        for detection in &mut detections {
+            let decisions = detection.pipeline.finish();
+            detection.handle_decisions(decisions, needs_strength, &video_details);
+        }
+    }
+
+    let elapsed = start_time.elapsed().as_secs_f64();
+    let (min_scenecut_distance, max_scenecut_distance) = scenecut_distances(&video_details, &opts);
+    detections
+        .iter()
+        .map(|detection| {
+            let pipeline = &detection.pipeline;
+            let frame_count = pipeline.frames_analyzed();
+            let mut scene_changes = match detection.flash_tracker {
+                Some(ref tracker) => tracker.remove_flashes(pipeline.keyframes()),
+                None => pipeline.keyframes().to_vec(),
+            };
+            if applies_distances_after && frame_count > 0 {
+                scene_changes = apply_scenecut_distances(
+                    &scene_changes,
+                    &detection.cut_strengths,
+                    frame_count,
+                    min_scenecut_distance,
+                    max_scenecut_distance,
+                    opts.min_distance_policy,
+                );
+            }
+            let mut results = DetectionResults {
+                scene_changes: postprocess::apply_post_processing(
+                    &opts.post_process,
+                    &scene_changes,
+                    &detection.cut_strengths,
+                    &[],
+                    frame_count,
+                ),
+                frame_count,
+                speed: frame_count as f64 / elapsed,
+                raw_scene_changes: pipeline.raw_keyframes(),
+                frame_scores: pipeline.frame_scores(),
+                degraded_at: pipeline.degraded_at(),
+                ..Default::default()
+            };
+            results.offset_frames(first_frame);
+            match decode_error {
+                Some(ref err) => Err(DetectionError::from_decode_error(
+                    copy_decode_error(err),
+                    results,
+                )),
+                None => Ok(results),
+            }
+        })
+        .collect()
+}
+
+/// Detection with one of the algorithms, along with what is needed
+/// to apply the options the pipeline does not handle itself.
+struct Detection<T: Pixel> {
+    pipeline: DetectionPipeline<T>,
+    flash_tracker: Option<FlashTracker<T>>,
+    cut_strengths: Vec<(usize, f32)>,
+}
+
+impl<T: Pixel> Detection<T> {
+    fn handle_decisions(
+        &mut self,
+        decisions: Vec<FrameDecision<T>>,
+        needs_strength: bool,
+        video_details: &VideoDetails,
+    ) {
+        for decision in decisions
+            .into_iter()
+            .filter(|decision| decision.is_keyframe)
+        {
+            if let Some(ref mut tracker) = self.flash_tracker {
+                tracker.push(
+                    decision.frameno,
+                    decision.previous.as_ref(),
+                    &decision.frame,
+                );
+            }
+            if needs_strength {
+                self.cut_strengths.extend(
+                    postprocess::cut_strength(
+                        decision.previous.as_deref(),
+                        &decision.frame,
+                        video_details,
+                    )
+                    .map(|strength| (decision.frameno, strength)),
+                );
+            }
+        }
+    }
+}
+
+/// Copies a decode error for the results of each algorithm,
+/// keeping the kind of I/O errors, which cannot be cloned.
+fn copy_decode_error(err: &DecodeError) -> DecodeError {
+    match err {
+        DecodeError::EndOfStream => DecodeError::EndOfStream,
+        DecodeError::InvalidInput(msg) => DecodeError::InvalidInput(msg.clone()),
+        DecodeError::Io(err) => DecodeError::Io(io::Error::new(err.kind(), err.to_string())),
+    }
+}
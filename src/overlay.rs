@@ -0,0 +1,114 @@
+use crate::decoder::VideoDetails;
+use crate::metrics::CHANGED_CELL_THRESHOLD;
+use crate::HEATMAP_GRID_SIZE;
+use std::collections::VecDeque;
+
+/// The normalized difference below which a cell of a difference grid
+/// is considered to be static.
+const STATIC_CELL_THRESHOLD: f32 = 0.01;
+/// The largest fraction of the frame that may change
+/// for the change to be considered an overlay rather than a scene change.
+const MAX_OVERLAY_AREA: f64 = 0.25;
+
+/// A region of the frame, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A persistent overlay, such as a score bug or channel logo,
+/// appearing, disappearing or changing its contents.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct OverlayEvent {
+    /// The 0-indexed frame number where the overlay changed.
+    pub frameno: usize,
+    /// The bounding box of the region which changed.
+    pub region: Region,
+}
+
+/// Detects overlay changes from the difference grids of consecutive frames.
+///
+/// An overlay change is a small region which is static before a frame,
+/// changes at that frame, and is static again afterwards.
+pub(crate) struct OverlayTracker {
+    video_details: VideoDetails,
+    /// The difference grids of the most recent frames,
+    /// along with their frame numbers and whether they were scene changes.
+    grids: VecDeque<(usize, Vec<f32>, bool)>,
+}
+
+impl OverlayTracker {
+    pub fn new(video_details: VideoDetails) -> Self {
+        OverlayTracker {
+            video_details,
+            grids: VecDeque::with_capacity(3),
+        }
+    }
+
+    /// Adds the difference grid between a frame and the one before it.
+    /// Returns an overlay change that occurred at the previous frame, if any.
+    pub fn push(
+        &mut self,
+        frameno: usize,
+        grid: Vec<f32>,
+        is_keyframe: bool,
+    ) -> Option<OverlayEvent> {
+        if self.grids.len() == 3 {
+            self.grids.pop_front();
+        }
+        self.grids.push_back((frameno, grid, is_keyframe));
+        if self.grids.len() < 3 {
+            return None;
+        }
+
+        let (before, changed, after) = (&self.grids[0], &self.grids[1], &self.grids[2]);
+        if changed.2 || before.0 + 1 != changed.0 || changed.0 + 1 != after.0 {
+            return None;
+        }
+        let changed_cells = (0..changed.1.len())
+            .filter(|&i| {
+                changed.1[i] >= CHANGED_CELL_THRESHOLD
+                    && before.1[i] < STATIC_CELL_THRESHOLD
+                    && after.1[i] < STATIC_CELL_THRESHOLD
+            })
+            .collect::<Vec<_>>();
+        let total_changed = changed
+            .1
+            .iter()
+            .filter(|&&cell| cell >= CHANGED_CELL_THRESHOLD)
+            .count();
+        if changed_cells.is_empty()
+            || changed_cells.len() != total_changed
+            || total_changed as f64 / changed.1.len() as f64 > MAX_OVERLAY_AREA
+        {
+            return None;
+        }
+
+        Some(OverlayEvent {
+            frameno: changed.0,
+            region: self.bounding_box(&changed_cells),
+        })
+    }
+
+    fn bounding_box(&self, cells: &[usize]) -> Region {
+        let cols = cells.iter().map(|&i| i % HEATMAP_GRID_SIZE);
+        let rows = cells.iter().map(|&i| i / HEATMAP_GRID_SIZE);
+        let (min_col, max_col) = (cols.clone().min().unwrap(), cols.max().unwrap());
+        let (min_row, max_row) = (rows.clone().min().unwrap(), rows.max().unwrap());
+
+        let (width, height) = (self.video_details.width, self.video_details.height);
+        let x = min_col * width / HEATMAP_GRID_SIZE;
+        let y = min_row * height / HEATMAP_GRID_SIZE;
+        Region {
+            x,
+            y,
+            width: (max_col + 1) * width / HEATMAP_GRID_SIZE - x,
+            height: (max_row + 1) * height / HEATMAP_GRID_SIZE - y,
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use crate::pipeline::{DetectionPipeline, FrameDecision};
+use crate::postprocess;
+use crate::range;
+use crate::{
+    check_options, scenecut_distances, DecodeError, Decoder, DetectionError, DetectionOptions,
+    DetectionResults, MinDistancePolicy, VideoDetails,
+};
+use rav1e::prelude::{Frame, Pixel};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The number of frames before the start of each chunk
+/// that are analyzed only to warm up the detector's history,
+/// in addition to the lookahead distance.
+const CHUNK_WARMUP_FRAMES: usize = 5;
+
+/// The scene changes detected in a chunk, along with their strengths,
+/// tagged with the index of the chunk.
+type ChunkResult = (usize, Vec<(usize, Option<f32>)>);
+
+struct ChunkJob<T: Pixel> {
+    index: usize,
+    /// The frame number of the first frame in `frames`.
+    first_frameno: usize,
+    /// The frames whose scene changes this chunk is responsible for.
+    range: Range<usize>,
+    frames: Vec<Arc<Frame<T>>>,
+}
+
+/// Runs through a video clip like `detect_scene_changes`,
+/// but splits it into chunks of `chunk_size` frames
+/// which are analyzed in parallel on `threads` worker threads.
+///
+/// Each chunk is analyzed together with a few frames before and after it,
+/// so that flash detection behaves the same across chunk boundaries.
+/// The minimum and maximum scenecut distances are applied
+/// after the results of all chunks have been merged.
+/// Only the list of scene changes is reported; additional
+/// analysis enabled in `opts`, such as heatmaps, is not performed.
+///
+/// If the decoder fails partway through the clip, the results for the frames
+/// which could be analyzed until then are returned as part of the error,
+/// the same as with `detect_scene_changes`.
+///
+/// Decoding still happens on the calling thread. Up to `2 * threads` chunks
+/// may be held in memory at once, so `chunk_size` should be chosen
+/// with the frame size of the input in mind.
+pub fn detect_scene_changes_parallel<D: Decoder, T: Pixel>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    threads: usize,
+    chunk_size: usize,
+) -> Result<DetectionResults, DetectionError> {
+    assert!(threads >= 1);
+    assert!(chunk_size >= 1);
+    assert!(opts.lookahead_distance >= 1);
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+
+    let first_frame = opts.frame_range.as_ref().map_or(0, |range| range.start);
+    let (mut dec, opts) = range::restrict_to_range(dec, &opts);
+    let dec = &mut dec;
+    let video_details = dec.get_video_details();
+    let (min_scenecut_distance, max_scenecut_distance) = scenecut_distances(&video_details, &opts);
+    let chunk_opts = DetectionOptions {
+        flash_suppression: None,
+        detect_letterbox: None,
+        export_heatmaps: false,
+        detect_overlays: false,
+        detect_dialogue: false,
+        detect_highlights: false,
+        detect_ad_breaks: false,
+        detect_black_frames: None,
+        duplicate_frame_threshold: None,
+        #[cfg(feature = "checksum")]
+        compute_checksums: false,
+        compute_fingerprints: false,
+        detect_transitions: false,
+        record_scores: false,
+        report_raw_scene_changes: false,
+        post_process: Vec::new(),
+        frame_time_budget: None,
+        warmup_frames: 0,
+        min_scenecut_distance: None,
+        max_scenecut_distance: None,
+        min_scenecut_duration: None,
+        max_scenecut_duration: None,
+        ..opts.clone()
+    };
+    let warmup = CHUNK_WARMUP_FRAMES + opts.lookahead_distance;
+
+    let start_time = Instant::now();
+    let (job_tx, job_rx) = mpsc::sync_channel::<ChunkJob<T>>(threads);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let (result_tx, result_rx) = mpsc::channel();
+    let workers = (0..threads)
+        .map(|_| {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let chunk_opts = chunk_opts.clone();
+            thread::spawn(move || run_worker(&job_rx, &video_details, &chunk_opts, &result_tx))
+        })
+        .collect::<Vec<_>>();
+    drop(result_tx);
+
+    // Holds decoded frames starting from `buffer_start`
+    let mut buffer = VecDeque::new();
+    let mut buffer_start = 0;
+    let mut frame_count = 0;
+    let mut chunk_index = 0;
+    let send_chunk = |index, buffer: &VecDeque<Arc<Frame<T>>>, buffer_start, range| {
+        job_tx
+            .send(ChunkJob {
+                index,
+                first_frameno: buffer_start,
+                range,
+                frames: buffer.iter().cloned().collect(),
+            })
+            .expect("Scene detection worker exited unexpectedly");
+    };
+
+    let mut decode_error = None;
+    loop {
+        let frame = match dec.read_video_frame::<T>(&video_details) {
+            Ok(frame) => frame,
+            Err(DecodeError::EndOfStream) => break,
+            Err(err) => {
+                decode_error = Some(err);
+                break;
+            }
+        };
+        buffer.push_back(Arc::new(frame));
+        frame_count += 1;
+
+        let chunk_start = chunk_index * chunk_size;
+        let chunk_end = chunk_start + chunk_size;
+        if frame_count == chunk_end + opts.lookahead_distance {
+            send_chunk(chunk_index, &buffer, buffer_start, chunk_start..chunk_end);
+            chunk_index += 1;
+            // Keep only the frames needed to warm up the next chunk
+            let next_start = chunk_end.saturating_sub(warmup);
+            while buffer_start < next_start {
+                buffer.pop_front();
+                buffer_start += 1;
+            }
+        }
+    }
+    if decode_error.is_some() {
+        // Without the following frames, the last frames cannot be analyzed
+        // the same way as if decoding had succeeded, so leave them out
+        frame_count = frame_count.saturating_sub(opts.lookahead_distance);
+    }
+    let chunk_start = chunk_index * chunk_size;
+    if chunk_start < frame_count {
+        send_chunk(chunk_index, &buffer, buffer_start, chunk_start..frame_count);
+    }
+    drop(job_tx);
+
+    let mut chunk_results = result_rx.iter().collect::<Vec<ChunkResult>>();
+    for worker in workers {
+        worker
+            .join()
+            .expect("Scene detection worker panicked unexpectedly");
+    }
+    chunk_results.sort_unstable_by_key(|(index, _)| *index);
+    // Renumber the results relative to the first frame after the warmup frames
+    let cuts = chunk_results
+        .into_iter()
+        .flat_map(|(_, cuts)| cuts)
+        .filter(|&(frameno, _)| frameno >= opts.warmup_frames)
+        .map(|(frameno, strength)| (frameno - opts.warmup_frames, strength))
+        .collect::<Vec<_>>();
+    let raw_scene_changes = cuts.iter().map(|&(frameno, _)| frameno).collect::<Vec<_>>();
+    let cut_strengths = cuts
+        .iter()
+        .filter_map(|&(frameno, strength)| strength.map(|strength| (frameno, strength)))
+        .collect::<Vec<_>>();
+    let frame_count = frame_count.saturating_sub(opts.warmup_frames);
+    let scene_changes = apply_scenecut_distances(
+        &raw_scene_changes,
+        &cut_strengths,
+        frame_count,
+        min_scenecut_distance,
+        max_scenecut_distance,
+        opts.min_distance_policy,
+    );
+
+    let mut results = DetectionResults {
+        scene_changes: postprocess::apply_post_processing(
+            &opts.post_process,
+            &scene_changes,
+            &cut_strengths,
+            &[],
+            frame_count,
+        ),
+        frame_count,
+        speed: frame_count as f64 / start_time.elapsed().as_secs_f64(),
+        ..Default::default()
+    };
+    results.offset_frames(first_frame);
+    match decode_error {
+        Some(err) => Err(DetectionError::from_decode_error(err, results)),
+        None => Ok(results),
+    }
+}
+
+fn run_worker<T: Pixel>(
+    job_rx: &Mutex<Receiver<ChunkJob<T>>>,
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+    result_tx: &mpsc::Sender<ChunkResult>,
+) {
+    loop {
+        // Release the lock before running the detector,
+        // so other workers can pick up jobs in the meantime
+        let ChunkJob {
+            index,
+            first_frameno,
+            range,
+            frames,
+        } = match job_rx.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+
+        let mut pipeline = DetectionPipeline::new(*video_details, opts.clone());
+        // Each scene change is reported along with its strength, for post-processing
+        let mut cuts = Vec::new();
+        let mut add_cut = |decision: FrameDecision<T>| {
+            let frameno = decision.frameno + first_frameno;
+            if decision.is_keyframe && range.contains(&frameno) {
+                let strength = postprocess::cut_strength(
+                    decision.previous.as_deref(),
+                    &decision.frame,
+                    video_details,
+                );
+                cuts.push((frameno, strength));
+            }
+        };
+        for frame in frames {
+            for decision in pipeline.push(frame) {
+                add_cut(decision);
+            }
+        }
+        pipeline.finish().into_iter().for_each(add_cut);
+
+        if result_tx.send((index, cuts)).is_err() {
+            return;
+        }
+    }
+}
+
+/// A minimum or maximum length of a scene.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum SceneLength {
+    Frames(usize),
+    /// A duration in seconds, measured with the timestamps of the frames.
+    Seconds(f64),
+}
+
+/// The minimum and maximum length of a scene.
+pub(crate) struct SceneLengthLimits<'a> {
+    pub min: Option<SceneLength>,
+    pub max: Option<SceneLength>,
+    /// The timestamp of each frame followed by the time the clip ends,
+    /// which must be given if either limit is in seconds.
+    pub timestamps: &'a [Duration],
+}
+
+impl SceneLengthLimits<'_> {
+    fn frames(min_distance: Option<usize>, max_distance: Option<usize>) -> Self {
+        SceneLengthLimits {
+            min: min_distance.map(SceneLength::Frames),
+            max: max_distance.map(SceneLength::Frames),
+            timestamps: &[],
+        }
+    }
+
+    fn seconds_between(&self, start: usize, end: usize) -> f64 {
+        self.timestamps[end]
+            .saturating_sub(self.timestamps[start])
+            .as_secs_f64()
+    }
+
+    /// Whether a scene from `start` up to `end` is shorter than the minimum.
+    fn is_too_short(&self, start: usize, end: usize) -> bool {
+        match self.min {
+            None => false,
+            Some(SceneLength::Frames(frames)) => end - start < frames,
+            Some(SceneLength::Seconds(secs)) => self.seconds_between(start, end) < secs,
+        }
+    }
+
+    /// Whether a scene from `start` up to `end` is longer than the maximum.
+    fn is_too_long(&self, start: usize, end: usize) -> bool {
+        match self.max {
+            None => false,
+            Some(SceneLength::Frames(frames)) => end - start > frames,
+            Some(SceneLength::Seconds(secs)) => self.seconds_between(start, end) > secs,
+        }
+    }
+
+    /// Returns where a scene starting at `start` must end
+    /// to be as long as possible without exceeding the maximum.
+    fn forced_cut(&self, start: usize) -> usize {
+        match self.max {
+            None => usize::MAX,
+            Some(SceneLength::Frames(frames)) => start + frames.max(1),
+            Some(SceneLength::Seconds(_)) => {
+                let within = (start + 1..self.timestamps.len())
+                    .take_while(|&end| !self.is_too_long(start, end))
+                    .count();
+                start + within.max(1)
+            }
+        }
+    }
+}
+
+/// Applies the minimum and maximum scenecut distances to a sorted list of
+/// scene changes, the same way the detector itself would with the `KeepFirst` policy.
+///
+/// `strengths` are the strengths of the scene changes, for the `KeepStronger` policy.
+pub(crate) fn apply_scenecut_distances(
+    scene_changes: &[usize],
+    strengths: &[(usize, f32)],
+    frame_count: usize,
+    min_distance: Option<usize>,
+    max_distance: Option<usize>,
+    policy: MinDistancePolicy,
+) -> Vec<usize> {
+    apply_scene_length_limits(
+        scene_changes,
+        strengths,
+        frame_count,
+        &SceneLengthLimits::frames(min_distance, max_distance),
+        policy,
+    )
+}
+
+/// Applies the minimum and maximum length of a scene to a sorted list of
+/// scene changes, like `apply_scenecut_distances`,
+/// with either limit given in frames or in seconds.
+pub(crate) fn apply_scene_length_limits(
+    scene_changes: &[usize],
+    strengths: &[(usize, f32)],
+    frame_count: usize,
+    limits: &SceneLengthLimits,
+    policy: MinDistancePolicy,
+) -> Vec<usize> {
+    let strength = |frameno| {
+        strengths
+            .iter()
+            .find(|&&(cut, _)| cut == frameno)
+            .map(|&(_, strength)| strength)
+    };
+    let mut result = vec![0];
+    let mut previous = 0;
+    for &frameno in scene_changes.iter().chain(std::iter::once(&frame_count)) {
+        while limits.is_too_long(previous, frameno) {
+            let cut = limits.forced_cut(previous);
+            if cut >= frameno {
+                break;
+            }
+            previous = cut;
+            result.push(cut);
+        }
+        if frameno >= frame_count || frameno == 0 {
+            continue;
+        }
+        if !limits.is_too_short(previous, frameno) {
+            previous = frameno;
+            result.push(frameno);
+            continue;
+        }
+        // The first frame always starts a scene, and replacing the previous
+        // scene change must not leave a scene longer than the maximum
+        let replaces_previous = result.len() > 1
+            && !limits.is_too_long(result[result.len() - 2], frameno)
+            && match policy {
+                MinDistancePolicy::KeepFirst => false,
+                MinDistancePolicy::KeepLast => true,
+                MinDistancePolicy::KeepStronger => strength(frameno) > strength(previous),
+            };
+        if replaces_previous {
+            previous = frameno;
+            *result.last_mut().unwrap() = frameno;
+        }
+    }
+    result
+}
@@ -0,0 +1,645 @@
+use crate::convert::{self, CropRect};
+use crate::decoder::VideoDetails;
+use crate::detector::Detector;
+use crate::histogram::{ChromaDetector, FrameScore};
+use crate::letterbox::LetterboxDetector;
+use crate::mask;
+use crate::metrics::{self, CHANGED_CELL_THRESHOLD};
+use crate::pulldown::REPEAT_THRESHOLD;
+use crate::{scenecut_distances, DetectionOptions, SceneDetectionSpeed, HEATMAP_GRID_SIZE};
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The number of consecutive frames which must exceed the frame time budget
+/// before analysis is degraded to the fast algorithm.
+const OVER_BUDGET_FRAMES_BEFORE_DEGRADING: usize = 5;
+/// The length of a scene after which adaptive lookahead
+/// stops looking ahead until the next scene change.
+const ADAPTIVE_LOOKAHEAD_QUIET_FRAMES: usize = 48;
+
+/// The outcome of analyzing a single input frame.
+pub(crate) struct FrameDecision<T: Pixel> {
+    /// The 0-indexed frame number of the analyzed frame.
+    pub frameno: usize,
+    /// Whether a scene change was detected at this frame.
+    pub is_keyframe: bool,
+    /// The analyzed frame itself.
+    pub frame: Arc<Frame<T>>,
+    /// The frame preceding the analyzed frame, if any.
+    pub previous: Option<Arc<Frame<T>>>,
+}
+
+/// Feeds frames to a scene change detector one at a time,
+/// keeping only as many frames around as the lookahead requires.
+pub(crate) struct DetectionPipeline<T: Pixel> {
+    detector: Detector<T>,
+    /// Detects changes in color the main detector misses,
+    /// if `detect_chroma_changes` is enabled.
+    chroma_detector: Option<ChromaDetector>,
+    /// Detects scene changes without flash detection or scenecut distances,
+    /// if `report_raw_scene_changes` is enabled.
+    raw_detector: Option<Detector<T>>,
+    video_details: VideoDetails,
+    /// Samples the first frames for black bars, if `detect_letterbox` is set
+    /// and no crop is given, until enough frames are sampled.
+    letterbox_detector: Option<LetterboxDetector>,
+    /// The frames sampled by `letterbox_detector`, which are analyzed
+    /// once the black bars are known.
+    letterbox_frames: Vec<Arc<Frame<T>>>,
+    /// The area within the black bars found by `letterbox_detector`, if any.
+    letterbox_crop: Option<CropRect>,
+    /// The details of the fields given to the detector instead of whole frames,
+    /// if the video is interlaced and `field_handling` applies to it.
+    field_details: Option<VideoDetails>,
+    /// The area of the frames or fields given to the detector,
+    /// if `crop` is set or black bars were found.
+    crop: Option<CropRect>,
+    /// The details of the frames given to the detector, which differ
+    /// from `video_details` if weighting, downscaling, cropping
+    /// or field handling applies.
+    analysis_details: VideoDetails,
+    opts: DetectionOptions,
+    /// The current lookahead distance,
+    /// which changes over time if `adaptive_lookahead` is enabled.
+    lookahead_distance: usize,
+    min_scenecut_distance: usize,
+    max_scenecut_distance: usize,
+    min_changed_area: Option<f64>,
+    /// Starts at the frame preceding `frameno`,
+    /// and ends at the most recently received frame.
+    frame_queue: VecDeque<Arc<Frame<T>>>,
+    /// The frames of `frame_queue`, as given to the detector.
+    analysis_queue: VecDeque<Arc<Frame<T>>>,
+    /// Whether each frame of `frame_queue` repeats the frame before it,
+    /// if `detect_pulldown` is enabled.
+    repeat_queue: VecDeque<bool>,
+    /// Whether each frame received repeats the frame before it,
+    /// including the warmup frames, if `detect_pulldown` is enabled.
+    repeated_frames: Vec<bool>,
+    /// The next frame to be analyzed.
+    frameno: usize,
+    frames_received: usize,
+    /// All scene changes, including those within the warmup frames.
+    all_keyframes: Vec<usize>,
+    /// The number of frames at the start of the input which are
+    /// analyzed only to warm up the detector.
+    warmup_frames: usize,
+    /// The scene changes after the warmup frames,
+    /// numbered from the first frame after them.
+    keyframes: Vec<usize>,
+    /// All scene changes found by `raw_detector`, including those within the warmup frames.
+    all_raw_keyframes: Vec<usize>,
+    /// The scores of all analyzed frames, including the warmup frames,
+    /// if `record_scores` is enabled.
+    frame_scores: Vec<FrameScore>,
+    /// The number of consecutive frames whose analysis exceeded the frame time budget.
+    over_budget_frames: usize,
+    /// Whether the frame time budget was exceeded too many times in a row,
+    /// so that analysis is degraded at the next scene change.
+    degrade_pending: bool,
+    degraded_at: Option<usize>,
+    /// Whether only the state needed for further analysis is kept,
+    /// rather than the history of the whole clip.
+    live: bool,
+}
+
+impl<T: Pixel> DetectionPipeline<T> {
+    /// Creates a pipeline with options which have passed `check_options`.
+    pub fn new(video_details: VideoDetails, opts: DetectionOptions) -> Self {
+        let (min_scenecut_distance, max_scenecut_distance) =
+            scenecut_distances(&video_details, &opts);
+        let field_details = convert::field_details(&video_details, opts.field_handling);
+        let crop = opts.crop.map(|crop| {
+            crop.for_source(
+                &video_details,
+                field_details.as_ref().unwrap_or(&video_details),
+            )
+        });
+        let analysis_details =
+            analysis_details(&video_details, field_details.as_ref(), crop.as_ref(), &opts);
+        DetectionPipeline {
+            detector: Detector::new(&analysis_details, &opts),
+            chroma_detector: if opts.detect_chroma_changes {
+                ChromaDetector::new(video_details)
+            } else {
+                None
+            },
+            raw_detector: raw_detector(&analysis_details, &opts),
+            letterbox_detector: match (opts.crop, opts.detect_letterbox) {
+                (None, Some(detection)) => Some(LetterboxDetector::new(video_details, detection)),
+                _ => None,
+            },
+            letterbox_frames: Vec::new(),
+            letterbox_crop: None,
+            video_details,
+            field_details,
+            crop,
+            analysis_details,
+            lookahead_distance: opts.lookahead_distance,
+            min_scenecut_distance: min_scenecut_distance.unwrap_or(0),
+            max_scenecut_distance: max_scenecut_distance.unwrap_or(usize::MAX),
+            min_changed_area: opts.min_changed_area,
+            frame_queue: VecDeque::with_capacity(opts.lookahead_distance + 2),
+            analysis_queue: VecDeque::with_capacity(opts.lookahead_distance + 2),
+            repeat_queue: VecDeque::with_capacity(opts.lookahead_distance + 2),
+            repeated_frames: Vec::new(),
+            frameno: 0,
+            frames_received: 0,
+            all_keyframes: Vec::new(),
+            warmup_frames: opts.warmup_frames,
+            keyframes: Vec::new(),
+            all_raw_keyframes: Vec::new(),
+            frame_scores: Vec::new(),
+            over_budget_frames: 0,
+            degrade_pending: false,
+            degraded_at: None,
+            live: false,
+            opts,
+        }
+    }
+
+    /// Creates a pipeline for an unbounded stream of frames, which only keeps
+    /// the state needed for further analysis, so that its memory use stays
+    /// bounded. The scene changes, scores and repeated frames found
+    /// are not kept, so only the decisions returned describe them.
+    pub fn live(video_details: VideoDetails, opts: DetectionOptions) -> Self {
+        DetectionPipeline {
+            live: true,
+            ..Self::new(video_details, opts)
+        }
+    }
+
+    /// Adds the next input frame, analyzing the oldest pending frames
+    /// once enough lookahead frames are available.
+    pub fn push(&mut self, frame: Arc<Frame<T>>) -> Vec<FrameDecision<T>> {
+        if let Some(ref mut letterbox_detector) = self.letterbox_detector {
+            letterbox_detector.push(&frame);
+            self.letterbox_frames.push(frame);
+            if !letterbox_detector.is_done() {
+                return Vec::new();
+            }
+            return self.apply_letterbox();
+        }
+
+        let weighted = match self.opts.weight_mask {
+            Some(ref mask) => Arc::new(mask::apply_weights(&frame, &self.video_details, mask)),
+            None => Arc::clone(&frame),
+        };
+        let (source, source_details) = match self.field_details {
+            Some(ref field_details) => (
+                Arc::new(convert::extract_field(
+                    &weighted,
+                    &self.video_details,
+                    field_details,
+                )),
+                field_details,
+            ),
+            None => (weighted, &self.video_details),
+        };
+        let cropped_details;
+        let (source, source_details) = match self.crop {
+            Some(ref crop) => {
+                cropped_details = convert::crop_details(source_details, crop);
+                (
+                    Arc::new(convert::crop_luma(&source, crop)),
+                    &cropped_details,
+                )
+            }
+            None => (source, source_details),
+        };
+        let analysis_frame = match self.opts.downscale {
+            Some(factor) if factor > 1 => {
+                Arc::new(convert::downscale_luma(&source, source_details, factor))
+            }
+            _ => source,
+        };
+        if self.opts.detect_pulldown {
+            let is_repeat = self.analysis_queue.back().is_some_and(|last| {
+                metrics::mean_difference(
+                    &last.planes[0],
+                    &analysis_frame.planes[0],
+                    self.analysis_details.width,
+                    self.analysis_details.height,
+                    self.analysis_details.bit_depth,
+                ) <= REPEAT_THRESHOLD
+            });
+            self.repeat_queue.push_back(is_repeat);
+            self.repeated_frames.push(is_repeat);
+        }
+        self.analysis_queue.push_back(analysis_frame);
+        self.frame_queue.push_back(frame);
+        self.frames_received += 1;
+        // More than one frame is analyzed if the lookahead distance shrank
+        let mut decisions = Vec::new();
+        while self.frames_received > self.frameno + self.lookahead_distance {
+            match self.analyze_next() {
+                Some(decision) => decisions.extend(self.skip_warmup(decision)),
+                None => break,
+            }
+        }
+        decisions
+    }
+
+    /// Analyzes all remaining frames with the lookahead available,
+    /// such as once the end of input is reached.
+    ///
+    /// Further frames can still be pushed afterwards.
+    pub fn finish(&mut self) -> Vec<FrameDecision<T>> {
+        let mut decisions = self.apply_letterbox();
+        while let Some(decision) = self.analyze_next() {
+            decisions.extend(self.skip_warmup(decision));
+        }
+        decisions
+    }
+
+    /// The number of frames analyzed so far, excluding warmup frames.
+    pub fn frames_analyzed(&self) -> usize {
+        self.frameno.saturating_sub(self.warmup_frames)
+    }
+
+    /// The frames where scene changes have been detected so far.
+    pub fn keyframes(&self) -> &[usize] {
+        &self.keyframes
+    }
+
+    /// The frames where `raw_detector` has detected scene changes so far,
+    /// numbered the same as `keyframes`.
+    pub fn raw_keyframes(&self) -> Vec<usize> {
+        if self.raw_detector.is_none() || self.frames_analyzed() == 0 {
+            return Vec::new();
+        }
+        std::iter::once(0)
+            .chain(
+                self.all_raw_keyframes
+                    .iter()
+                    .filter(|&&frameno| frameno > self.warmup_frames)
+                    .map(|&frameno| frameno - self.warmup_frames),
+            )
+            .collect()
+    }
+
+    /// The scores of the frames analyzed so far, excluding warmup frames.
+    pub fn frame_scores(&self) -> Vec<FrameScore> {
+        self.frame_scores
+            .iter()
+            .filter(|score| score.frameno >= self.warmup_frames)
+            .map(|score| FrameScore {
+                frameno: score.frameno - self.warmup_frames,
+                ..*score
+            })
+            .collect()
+    }
+
+    /// Whether each frame analyzed so far repeats the frame before it,
+    /// excluding warmup frames, if `detect_pulldown` is enabled.
+    pub fn repeated_frames(&self) -> &[bool] {
+        let start = self.warmup_frames.min(self.repeated_frames.len());
+        let end = (start + self.frames_analyzed()).min(self.repeated_frames.len());
+        &self.repeated_frames[start..end]
+    }
+
+    /// The area within the black bars found by `detect_letterbox`, if any.
+    pub fn letterbox_crop(&self) -> Option<CropRect> {
+        self.letterbox_crop
+    }
+
+    /// The first frame analyzed with the fast algorithm
+    /// after the frame time budget was repeatedly exceeded, if any.
+    pub fn degraded_at(&self) -> Option<usize> {
+        self.degraded_at
+            .map(|frameno| frameno.saturating_sub(self.warmup_frames))
+    }
+
+    /// Drops decisions for warmup frames, and renumbers the rest
+    /// relative to the first frame after the warmup frames,
+    /// which always starts a new scene.
+    fn skip_warmup(&mut self, mut decision: FrameDecision<T>) -> Option<FrameDecision<T>> {
+        if decision.frameno < self.warmup_frames {
+            return None;
+        }
+        decision.frameno -= self.warmup_frames;
+        if decision.frameno == 0 {
+            decision.is_keyframe = true;
+            decision.previous = None;
+        }
+        if decision.is_keyframe {
+            self.keyframes.push(decision.frameno);
+        }
+        Some(decision)
+    }
+
+    fn analyze_next(&mut self) -> Option<FrameDecision<T>> {
+        if self.live {
+            self.discard_history();
+        }
+        if self.analysis_queue.len() < 2 {
+            // End of video
+            return None;
+        }
+        // Repeated frames are left out of the lookahead, so that their
+        // near-zero costs do not skew the detector. A repeat preceding
+        // the analyzed frame is kept, as it is identical to the frame it repeats.
+        let frame_set = self
+            .analysis_queue
+            .iter()
+            .take(1)
+            .chain(
+                self.analysis_queue
+                    .iter()
+                    .enumerate()
+                    .skip(1)
+                    .filter(|&(i, _)| !self.is_repeat(i))
+                    .map(|(_, frame)| frame),
+            )
+            .take(self.lookahead_distance + 2)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let frameno = self.frameno;
+        let is_repeat = frameno > 0 && self.is_repeat(1);
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("analyze", frameno).entered();
+        // Only timed when needed, since there is no clock on some targets, such as wasm
+        let start_time =
+            (self.opts.frame_time_budget.is_some() || cfg!(feature = "tracing")).then(Instant::now);
+        let is_keyframe = frameno == 0
+            || (!is_repeat && {
+                let is_detected =
+                    self.detector
+                        .analyze(&frame_set, frameno, *self.all_keyframes.last().unwrap());
+                if self.opts.record_scores {
+                    self.frame_scores.extend(self.detector.last_score());
+                }
+                let is_chroma_change = self.is_chroma_change(frameno);
+                let is_partial_change =
+                    (is_detected || is_chroma_change) && self.is_partial_change(frameno);
+                #[cfg(feature = "tracing")]
+                tracing::debug!(
+                    is_detected,
+                    is_chroma_change,
+                    is_partial_change,
+                    score = ?self.detector.last_score().map(|score| score.raw),
+                    "analyzed frame"
+                );
+                (is_detected || is_chroma_change) && !is_partial_change
+            });
+        #[cfg(feature = "tracing")]
+        if is_keyframe {
+            tracing::info!(elapsed = ?start_time.map(|time| time.elapsed()), "scene change");
+        } else {
+            tracing::trace!(elapsed = ?start_time.map(|time| time.elapsed()), "no scene change");
+        }
+        if frameno > 0 && !is_repeat {
+            self.check_frame_time_budget(start_time);
+            self.analyze_raw(&frame_set, frameno);
+        }
+        if is_keyframe {
+            self.all_keyframes.push(frameno);
+            if frameno > 0 {
+                self.reconfigure_at_scene_change(frameno);
+            }
+        }
+
+        let (frame, previous) = if frameno == 0 {
+            (self.frame_queue[0].clone(), None)
+        } else {
+            self.analysis_queue.pop_front();
+            self.repeat_queue.pop_front();
+            (self.frame_queue[1].clone(), self.frame_queue.pop_front())
+        };
+        self.frameno += 1;
+
+        Some(FrameDecision {
+            frameno,
+            is_keyframe,
+            frame,
+            previous,
+        })
+    }
+
+    /// Discards the scene changes, scores and repeated frames found so far,
+    /// except for the last scene changes, which further analysis needs.
+    fn discard_history(&mut self) {
+        for keyframes in [
+            &mut self.all_keyframes,
+            &mut self.keyframes,
+            &mut self.all_raw_keyframes,
+        ] {
+            keyframes.drain(..keyframes.len().saturating_sub(1));
+        }
+        self.frame_scores.clear();
+        self.repeated_frames.clear();
+    }
+
+    /// Whether the frame at `index` in `frame_queue` repeats the frame before it.
+    fn is_repeat(&self, index: usize) -> bool {
+        self.repeat_queue.get(index).copied().unwrap_or(false)
+    }
+
+    /// Switches to the fast algorithm for subsequent frames if analysis
+    /// has exceeded the frame time budget too many times in a row.
+    fn check_frame_time_budget(&mut self, start_time: Option<Instant>) {
+        let (budget, start_time) = match (self.opts.frame_time_budget, start_time) {
+            (Some(budget), Some(start_time)) if !self.opts.deterministic => (budget, start_time),
+            _ => return,
+        };
+        if self.opts.analysis_speed != SceneDetectionSpeed::Standard
+            || self.degrade_pending
+            || self.degraded_at.is_some()
+        {
+            return;
+        }
+
+        if start_time.elapsed() > budget {
+            self.over_budget_frames += 1;
+        } else {
+            self.over_budget_frames = 0;
+        }
+        if self.over_budget_frames >= OVER_BUDGET_FRAMES_BEFORE_DEGRADING {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("frame time budget exceeded, degrading to the fast algorithm");
+            self.degrade_pending = true;
+        }
+    }
+
+    /// Applies changes to the detector at a scene change other than
+    /// the first frame. Rebuilding the detector discards its history,
+    /// which only matters within the scene it was built from,
+    /// so the detector is only rebuilt here.
+    ///
+    /// Analysis is degraded here if the frame time budget was exceeded.
+    /// With adaptive lookahead, the next scene is analyzed with the full
+    /// lookahead distance after a short scene, since flashes are likely
+    /// within rapid cuts, and without lookahead after a long one,
+    /// so that fewer frames are held and decisions are made sooner.
+    fn reconfigure_at_scene_change(&mut self, frameno: usize) {
+        let mut needs_rebuild = false;
+        if self.degrade_pending {
+            self.degrade_pending = false;
+            self.degraded_at = Some(frameno + 1);
+            needs_rebuild = true;
+        }
+        if self.opts.adaptive_lookahead {
+            let previous_keyframe = self.all_keyframes[self.all_keyframes.len() - 2];
+            let lookahead_distance =
+                if frameno - previous_keyframe >= ADAPTIVE_LOOKAHEAD_QUIET_FRAMES {
+                    1
+                } else {
+                    self.opts.lookahead_distance
+                };
+            // Only rav1e's algorithms look ahead,
+            // and their detector has to be rebuilt to change how far
+            if lookahead_distance != self.lookahead_distance {
+                self.lookahead_distance = lookahead_distance;
+                needs_rebuild |= matches!(self.detector, Detector::Rav1e(_));
+            }
+        }
+        if needs_rebuild {
+            self.rebuild_detector();
+        }
+    }
+
+    /// Rebuilds the detector for the current lookahead distance,
+    /// and for the fast algorithm if analysis has been degraded.
+    fn rebuild_detector(&mut self) {
+        let opts = DetectionOptions {
+            analysis_speed: if self.degraded_at.is_some() {
+                SceneDetectionSpeed::Fast
+            } else {
+                self.opts.analysis_speed
+            },
+            lookahead_distance: self.lookahead_distance,
+            ..self.opts.clone()
+        };
+        self.detector = Detector::new(&self.analysis_details, &opts);
+    }
+
+    /// Crops the frames given to the detector to the area within the black bars
+    /// found in the frames sampled by the letterbox detector, if any,
+    /// and adds the sampled frames.
+    fn apply_letterbox(&mut self) -> Vec<FrameDecision<T>> {
+        let letterbox_detector = match self.letterbox_detector.take() {
+            Some(detector) => detector,
+            None => return Vec::new(),
+        };
+        self.letterbox_crop = letterbox_detector.crop();
+        if let Some(crop) = self.letterbox_crop {
+            let crop = crop.for_source(
+                &self.video_details,
+                self.field_details.as_ref().unwrap_or(&self.video_details),
+            );
+            self.crop = Some(crop);
+            self.analysis_details = analysis_details(
+                &self.video_details,
+                self.field_details.as_ref(),
+                Some(&crop),
+                &self.opts,
+            );
+            self.rebuild_detector();
+            self.raw_detector = raw_detector(&self.analysis_details, &self.opts);
+        }
+        std::mem::take(&mut self.letterbox_frames)
+            .into_iter()
+            .flat_map(|frame| self.push(frame))
+            .collect()
+    }
+
+    /// Runs the raw detector, if enabled, on the same frames as the detector.
+    fn analyze_raw(&mut self, frame_set: &[Arc<Frame<T>>], frameno: usize) {
+        let raw_detector = match self.raw_detector {
+            Some(ref mut detector) => detector,
+            None => return,
+        };
+        let previous_keyframe = self.all_raw_keyframes.last().copied().unwrap_or(0);
+        if raw_detector.analyze(frame_set, frameno, previous_keyframe) {
+            self.all_raw_keyframes.push(frameno);
+        }
+    }
+
+    /// Checks whether the chroma detector, if enabled,
+    /// finds a scene change the main detector may have missed.
+    fn is_chroma_change(&mut self, frameno: usize) -> bool {
+        let chroma_detector = match self.chroma_detector {
+            Some(ref mut detector) => detector,
+            None => return false,
+        };
+        let is_change = chroma_detector
+            .analyze_next_frame(&[&self.frame_queue[0], &self.frame_queue[1]], frameno);
+        is_change && frameno - self.all_keyframes.last().unwrap() >= self.min_scenecut_distance
+    }
+
+    /// Checks whether a detected scene change should be suppressed
+    /// because too small an area of the frame changed.
+    /// Scene changes forced by the maximum scenecut distance are never suppressed.
+    fn is_partial_change(&self, frameno: usize) -> bool {
+        let min_changed_area = match self.min_changed_area {
+            Some(area) => area,
+            None => return false,
+        };
+        if frameno - self.all_keyframes.last().unwrap() >= self.max_scenecut_distance {
+            return false;
+        }
+
+        let cells = metrics::difference_grid(
+            &self.frame_queue[0].planes[0],
+            &self.frame_queue[1].planes[0],
+            self.video_details.width,
+            self.video_details.height,
+            self.video_details.bit_depth,
+            HEATMAP_GRID_SIZE,
+        );
+        let changed_cells = cells
+            .iter()
+            .filter(|&&cell| cell >= CHANGED_CELL_THRESHOLD)
+            .count();
+        (changed_cells as f64 / cells.len() as f64) < min_changed_area
+    }
+}
+
+/// Returns the details of the frames given to the detector,
+/// after weighting, field handling, cropping and downscaling.
+fn analysis_details(
+    video_details: &VideoDetails,
+    field_details: Option<&VideoDetails>,
+    crop: Option<&CropRect>,
+    opts: &DetectionOptions,
+) -> VideoDetails {
+    let source_details = match field_details {
+        Some(field_details) => *field_details,
+        // Weighted frames are monochrome
+        None if opts.weight_mask.is_some() => VideoDetails {
+            chroma_sampling: ChromaSampling::Cs400,
+            chroma_sample_position: ChromaSamplePosition::Unknown,
+            ..*video_details
+        },
+        None => *video_details,
+    };
+    convert::analysis_details(
+        &crop.map_or(source_details, |crop| {
+            convert::crop_details(&source_details, crop)
+        }),
+        opts.downscale,
+    )
+}
+
+/// Creates a detector without flash detection or scenecut distances,
+/// if `report_raw_scene_changes` is enabled.
+fn raw_detector<T: Pixel>(
+    analysis_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> Option<Detector<T>> {
+    if !opts.report_raw_scene_changes {
+        return None;
+    }
+    let raw_opts = DetectionOptions {
+        detect_flashes: false,
+        min_scenecut_distance: None,
+        max_scenecut_distance: None,
+        min_scenecut_duration: None,
+        max_scenecut_duration: None,
+        ..opts.clone()
+    };
+    Some(Detector::new(analysis_details, &raw_opts))
+}
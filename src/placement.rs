@@ -0,0 +1,199 @@
+use crate::DetectionResults;
+
+/// The constraints of an encoder on where keyframes may be placed,
+/// for turning detected scene changes into a final keyframe list.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct GopConstraints {
+    /// The minimum number of frames between two keyframes.
+    /// Scene changes closer than this to the previous keyframe are dropped.
+    pub min_gop: usize,
+    /// The maximum number of frames between two keyframes.
+    pub max_gop: usize,
+    /// The interval at which keyframes are placed within scenes
+    /// longer than allowed between keyframes.
+    ///
+    /// If this is not set, such scenes are split evenly
+    /// into the fewest parts at most `max_gop` frames long.
+    pub fixed_interval: Option<usize>,
+    /// The number of frames a keyframe placed at `fixed_interval`
+    /// may be delayed by to land on a scene change instead,
+    /// as long as `max_gop` is not exceeded.
+    ///
+    /// This avoids placing a keyframe shortly before a scene change,
+    /// where the scene change would then be dropped for `min_gop`.
+    /// Only used if `fixed_interval` is set.
+    pub scene_bias: usize,
+}
+
+/// Places keyframes for an encoder at the scene changes of `results`,
+/// dropping and adding keyframes as needed to meet `constraints`.
+///
+/// Returns the sorted 0-indexed frame numbers of the keyframes,
+/// which always start with the first frame analyzed.
+pub fn place_keyframes(results: &DetectionResults, constraints: &GopConstraints) -> Vec<usize> {
+    assert!(constraints.max_gop >= 1 && constraints.min_gop <= constraints.max_gop);
+    if let Some(interval) = constraints.fixed_interval {
+        assert!((1..=constraints.max_gop).contains(&interval));
+    }
+
+    if results.frame_count == 0 {
+        return Vec::new();
+    }
+    let start = results.first_frame;
+    let end = start + results.frame_count;
+    let mut keyframes = vec![start];
+    let mut last = start;
+    let mut cuts = results
+        .scene_changes
+        .iter()
+        .copied()
+        .filter(|&frameno| frameno > start && frameno < end)
+        .peekable();
+    loop {
+        // Scene changes too close to the previous keyframe are dropped
+        let next_cut = loop {
+            match cuts.peek() {
+                Some(&cut) if cut - last < constraints.min_gop => {
+                    cuts.next();
+                }
+                next => break next.copied(),
+            }
+        };
+        let latest_cut = match constraints.fixed_interval {
+            Some(interval) => {
+                (last + interval + constraints.scene_bias).min(last + constraints.max_gop)
+            }
+            None => last + constraints.max_gop,
+        };
+        let next = match next_cut {
+            Some(cut) if cut <= latest_cut => {
+                cuts.next();
+                cut
+            }
+            _ => match constraints.fixed_interval {
+                Some(interval) => last + interval,
+                None => {
+                    let stretch = next_cut.unwrap_or(end) - last;
+                    if stretch <= constraints.max_gop {
+                        break;
+                    }
+                    let parts = stretch.div_ceil(constraints.max_gop);
+                    last + stretch.div_ceil(parts)
+                }
+            },
+        };
+        if next >= end {
+            break;
+        }
+        keyframes.push(next);
+        last = next;
+    }
+    keyframes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::results;
+
+    fn gop(min_gop: usize, max_gop: usize) -> GopConstraints {
+        GopConstraints {
+            min_gop,
+            max_gop,
+            fixed_interval: None,
+            scene_bias: 0,
+        }
+    }
+
+    fn fixed(interval: usize, max_gop: usize, scene_bias: usize) -> GopConstraints {
+        GopConstraints {
+            fixed_interval: Some(interval),
+            scene_bias,
+            ..gop(0, max_gop)
+        }
+    }
+
+    #[test]
+    fn long_scenes_are_split_evenly() {
+        // Three parts of at most 40 frames, rounded up to 34 frames each
+        assert_eq!(
+            place_keyframes(&results(&[0], 100), &gop(0, 40)),
+            [0, 34, 67]
+        );
+        assert_eq!(place_keyframes(&results(&[0], 80), &gop(0, 40)), [0, 40]);
+        // A scene exactly `max_gop` long is not split
+        assert_eq!(
+            place_keyframes(&results(&[0, 40], 100), &gop(0, 40)),
+            [0, 40, 70]
+        );
+        // Keyframes are spread evenly up to the next scene change
+        assert_eq!(
+            place_keyframes(&results(&[0, 90], 100), &gop(0, 40)),
+            [0, 30, 60, 90]
+        );
+    }
+
+    #[test]
+    fn min_gop_drops_close_scene_changes() {
+        assert_eq!(
+            place_keyframes(&results(&[0, 5, 10, 15, 50], 100), &gop(10, 250)),
+            [0, 10, 50]
+        );
+        // The distance is measured from the previous keyframe,
+        // not from the dropped scene change
+        assert_eq!(
+            place_keyframes(&results(&[0, 8, 14], 100), &gop(10, 250)),
+            [0, 14]
+        );
+        let constraints = GopConstraints {
+            min_gop: 10,
+            ..fixed(30, 30, 0)
+        };
+        assert_eq!(
+            place_keyframes(&results(&[0, 33], 100), &constraints),
+            [0, 30, 60, 90]
+        );
+    }
+
+    #[test]
+    fn fixed_interval_restarts_at_scene_changes() {
+        assert_eq!(
+            place_keyframes(&results(&[0], 100), &fixed(30, 30, 0)),
+            [0, 30, 60, 90]
+        );
+        assert_eq!(
+            place_keyframes(&results(&[0, 45], 100), &fixed(30, 30, 0)),
+            [0, 30, 45, 75]
+        );
+    }
+
+    #[test]
+    fn scene_bias() {
+        // The keyframe due at 30 is delayed to the scene change at 33
+        assert_eq!(
+            place_keyframes(&results(&[0, 33], 100), &fixed(30, 40, 5)),
+            [0, 33, 63, 93]
+        );
+        assert_eq!(
+            place_keyframes(&results(&[0, 36], 100), &fixed(30, 40, 5)),
+            [0, 30, 36, 66, 96]
+        );
+        // But not past `max_gop`
+        assert_eq!(
+            place_keyframes(&results(&[0, 34], 100), &fixed(30, 32, 5)),
+            [0, 30, 34, 64, 94]
+        );
+    }
+
+    #[test]
+    fn keyframes_start_at_first_analyzed_frame() {
+        // Scene changes outside the analyzed frames are ignored
+        let clip = DetectionResults {
+            first_frame: 100,
+            ..results(&[50, 100, 120, 150], 50)
+        };
+        assert_eq!(place_keyframes(&clip, &gop(10, 250)), [100, 120]);
+        assert!(place_keyframes(&results(&[], 0), &gop(10, 250)).is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::VideoDetails;
+use rav1e::prelude::{Frame, Pixel};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+/// Hands out frames to decode into, reusing the frames
+/// the detector is done with instead of allocating new ones.
+pub(crate) struct FramePool<T: Pixel> {
+    video_details: VideoDetails,
+    released: Receiver<Frame<T>>,
+}
+
+/// Returns frames the detector is done with to a `FramePool`,
+/// which may be on another thread.
+pub(crate) struct FrameRecycler<T: Pixel> {
+    released: Sender<Frame<T>>,
+}
+
+/// Creates a pool of frames with the given details, along with its recycler.
+pub(crate) fn frame_pool<T: Pixel>(
+    video_details: VideoDetails,
+) -> (FramePool<T>, FrameRecycler<T>) {
+    let (sender, receiver) = mpsc::channel();
+    let pool = FramePool {
+        video_details,
+        released: receiver,
+    };
+    (pool, FrameRecycler { released: sender })
+}
+
+/// A frame pool kept across detection passes, such as those of one worker
+/// of a batch, which is replaced whenever the size of the frames changes.
+pub(crate) struct ReusedFramePool<T: Pixel> {
+    pool: Option<(FramePool<T>, FrameRecycler<T>)>,
+}
+
+impl<T: Pixel> ReusedFramePool<T> {
+    pub fn new() -> Self {
+        ReusedFramePool { pool: None }
+    }
+
+    /// Returns the pool for frames with the given details, along with its recycler,
+    /// creating a new pool if the frames in the current one are of a different size.
+    pub fn for_details(
+        &mut self,
+        video_details: VideoDetails,
+    ) -> (&mut FramePool<T>, &FrameRecycler<T>) {
+        let fits = self.pool.as_ref().is_some_and(|(pool, _)| {
+            pool.video_details.width == video_details.width
+                && pool.video_details.height == video_details.height
+                && pool.video_details.chroma_sampling == video_details.chroma_sampling
+        });
+        if !fits {
+            self.pool = Some(frame_pool(video_details));
+        }
+        let (pool, recycler) = self.pool.as_mut().unwrap();
+        (pool, recycler)
+    }
+}
+
+impl<T: Pixel> FramePool<T> {
+    /// Returns a released frame if there is one, or a new frame otherwise.
+    ///
+    /// The contents of a released frame are those of the last frame decoded into it.
+    pub fn get(&self) -> Frame<T> {
+        self.released.try_recv().unwrap_or_else(|_| {
+            Frame::new_with_padding(
+                self.video_details.width,
+                self.video_details.height,
+                self.video_details.chroma_sampling,
+                LUMA_PADDING,
+            )
+        })
+    }
+}
+
+impl<T: Pixel> FrameRecycler<T> {
+    /// Returns `frame` to the pool, if nothing else holds on to it anymore.
+    pub fn recycle(&self, frame: Arc<Frame<T>>) {
+        if let Ok(frame) = Arc::try_unwrap(frame) {
+            // The pool is gone once decoding has finished, unless it is reused
+            let _ = self.released.send(frame);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{Frame, Pixel};
+
+/// A step of post-processing applied to the detected scene changes.
+///
+/// When serialized, each step is an object with a `step` field
+/// naming the step, such as `{"step": "merge-below", "frames": 12}`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serialize", serde(tag = "step", rename_all = "kebab-case"))]
+pub enum PostProcessStep {
+    /// Merges each scene shorter than `frames` into the scene before it,
+    /// or into the scene after it if it is the first scene.
+    MergeBelow { frames: usize },
+    /// Splits each scene longer than `frames` into the fewest scenes
+    /// of equal length which are at most `frames` long.
+    SplitAbove { frames: usize },
+    /// Moves each scene change to the nearest multiple of `interval`
+    /// if it is at most `tolerance` frames away, such as to line up
+    /// with the keyframes of a fixed-GOP encode.
+    SnapToKeyframe { interval: usize, tolerance: usize },
+    /// Moves each scene change to the nearest frame the source marks
+    /// as a keyframe if it is at most `tolerance` frames away, such as
+    /// to split the source at its keyframes without re-encoding.
+    ///
+    /// This requires a decoder which reports source keyframes, and is only
+    /// applied by `detect_scene_changes`. Otherwise no scene changes move.
+    SnapToSourceKeyframe { tolerance: usize },
+    /// Removes scene changes where the mean luma difference
+    /// from the preceding frame, from `0.0` to `1.0`,
+    /// is below `min_difference`.
+    /// Scene changes added by `SplitAbove` are never removed.
+    DropWeak { min_difference: f32 },
+}
+
+/// A scene change being post-processed, along with its luma difference
+/// from the preceding frame, if it was detected rather than added.
+#[derive(Clone, Copy)]
+struct Cut {
+    frameno: usize,
+    strength: Option<f32>,
+}
+
+/// Computes the strength of a scene change for `DropWeak`,
+/// as the mean luma difference from the preceding frame.
+pub(crate) fn cut_strength<T: Pixel>(
+    previous: Option<&Frame<T>>,
+    frame: &Frame<T>,
+    video_details: &VideoDetails,
+) -> Option<f32> {
+    previous.map(|previous| {
+        metrics::mean_difference(
+            &previous.planes[0],
+            &frame.planes[0],
+            video_details.width,
+            video_details.height,
+            video_details.bit_depth,
+        )
+    })
+}
+
+/// Applies the post-processing steps in order to a sorted list of scene changes
+/// in a clip of `frame_count` frames, given the strengths of the scene changes
+/// and the sorted keyframes of the source.
+pub(crate) fn apply_post_processing(
+    steps: &[PostProcessStep],
+    scene_changes: &[usize],
+    strengths: &[(usize, f32)],
+    source_keyframes: &[usize],
+    frame_count: usize,
+) -> Vec<usize> {
+    let mut cuts = scene_changes
+        .iter()
+        .map(|&frameno| Cut {
+            frameno,
+            strength: strengths
+                .iter()
+                .find(|&&(cut, _)| cut == frameno)
+                .map(|&(_, strength)| strength),
+        })
+        .collect::<Vec<_>>();
+    for step in steps {
+        cuts = match *step {
+            PostProcessStep::MergeBelow { frames } => merge_below(&cuts, frames, frame_count),
+            PostProcessStep::SplitAbove { frames } => split_above(&cuts, frames, frame_count),
+            PostProcessStep::SnapToKeyframe {
+                interval,
+                tolerance,
+            } => snap_to_keyframe(&cuts, interval, tolerance, frame_count),
+            PostProcessStep::SnapToSourceKeyframe { tolerance } => {
+                snap_to_source_keyframe(&cuts, source_keyframes, tolerance)
+            }
+            PostProcessStep::DropWeak { min_difference } => cuts
+                .into_iter()
+                .filter(|cut| cut.frameno == 0 || !cut.strength.is_some_and(|s| s < min_difference))
+                .collect(),
+        };
+    }
+    cuts.into_iter().map(|cut| cut.frameno).collect()
+}
+
+/// Returns the frame number after the last frame of the scene starting at `cuts[i]`.
+fn scene_end(cuts: &[Cut], i: usize, frame_count: usize) -> usize {
+    cuts.get(i + 1).map_or(frame_count, |cut| cut.frameno)
+}
+
+fn merge_below(cuts: &[Cut], frames: usize, frame_count: usize) -> Vec<Cut> {
+    let mut result = cuts
+        .iter()
+        .enumerate()
+        .filter(|&(i, cut)| {
+            cut.frameno == 0 || scene_end(cuts, i, frame_count) - cut.frameno >= frames
+        })
+        .map(|(_, &cut)| cut)
+        .collect::<Vec<_>>();
+    if result.len() > 1 && result[1].frameno < frames {
+        result.remove(1);
+    }
+    result
+}
+
+fn split_above(cuts: &[Cut], frames: usize, frame_count: usize) -> Vec<Cut> {
+    let mut result = Vec::with_capacity(cuts.len());
+    for (i, &cut) in cuts.iter().enumerate() {
+        result.push(cut);
+        let len = scene_end(cuts, i, frame_count) - cut.frameno;
+        let parts = len.div_ceil(frames.max(1));
+        result.extend((1..parts).map(|part| Cut {
+            frameno: cut.frameno + len * part / parts,
+            strength: None,
+        }));
+    }
+    result
+}
+
+fn snap_to_keyframe(
+    cuts: &[Cut],
+    interval: usize,
+    tolerance: usize,
+    frame_count: usize,
+) -> Vec<Cut> {
+    let mut result = cuts
+        .iter()
+        .map(|&cut| {
+            let interval = interval.max(1);
+            let nearest = (cut.frameno + interval / 2) / interval * interval;
+            if cut.frameno != 0
+                && nearest < frame_count
+                && nearest.abs_diff(cut.frameno) <= tolerance
+            {
+                Cut {
+                    frameno: nearest,
+                    ..cut
+                }
+            } else {
+                cut
+            }
+        })
+        .collect::<Vec<_>>();
+    result.sort_by_key(|cut| cut.frameno);
+    result.dedup_by_key(|cut| cut.frameno);
+    result
+}
+
+fn snap_to_source_keyframe(cuts: &[Cut], keyframes: &[usize], tolerance: usize) -> Vec<Cut> {
+    let mut result = cuts
+        .iter()
+        .map(|&cut| {
+            // The keyframes on either side of the scene change
+            let after = keyframes.partition_point(|&keyframe| keyframe < cut.frameno);
+            let nearest = keyframes[after.saturating_sub(1)..]
+                .iter()
+                .take(2)
+                .min_by_key(|&&keyframe| keyframe.abs_diff(cut.frameno));
+            match nearest {
+                Some(&nearest)
+                    if cut.frameno != 0 && nearest.abs_diff(cut.frameno) <= tolerance =>
+                {
+                    Cut {
+                        frameno: nearest,
+                        ..cut
+                    }
+                }
+                _ => cut,
+            }
+        })
+        .collect::<Vec<_>>();
+    result.sort_by_key(|cut| cut.frameno);
+    result.dedup_by_key(|cut| cut.frameno);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(steps: &[PostProcessStep], scene_changes: &[usize], frame_count: usize) -> Vec<usize> {
+        apply_post_processing(steps, scene_changes, &[], &[], frame_count)
+    }
+
+    #[test]
+    fn merge_below_chains_of_short_scenes() {
+        let step = [PostProcessStep::MergeBelow { frames: 10 }];
+        // Each short scene is merged into the scene before it,
+        // even if the short scenes are longer than `frames` together
+        assert_eq!(apply(&step, &[0, 50, 55, 58, 62, 100], 120), [0, 62, 100]);
+        assert_eq!(apply(&step, &[0, 50, 55, 60, 65, 100], 120), [0, 65, 100]);
+        // Short scenes at the start are merged into the first long scene
+        assert_eq!(apply(&step, &[0, 3, 6, 9, 40], 100), [0, 40]);
+        assert_eq!(apply(&step, &[0, 50, 95, 98], 100), [0, 50]);
+    }
+
+    #[test]
+    fn split_above() {
+        let split = |frames: usize, scene_changes: &[usize]| {
+            apply(
+                &[PostProcessStep::SplitAbove { frames }],
+                scene_changes,
+                100,
+            )
+        };
+        // Scenes an exact multiple of `frames` long split without a remainder
+        assert_eq!(split(50, &[0]), [0, 50]);
+        assert_eq!(split(25, &[0]), [0, 25, 50, 75]);
+        assert_eq!(split(50, &[0, 50]), [0, 50]);
+        assert_eq!(split(100, &[0]), [0]);
+        // Otherwise the parts are as equal as possible
+        assert_eq!(split(40, &[0]), [0, 33, 66]);
+        assert_eq!(split(45, &[0, 10]), [0, 10, 55]);
+    }
+
+    #[test]
+    fn snap_to_keyframe() {
+        let step = [PostProcessStep::SnapToKeyframe {
+            interval: 24,
+            tolerance: 3,
+        }];
+        assert_eq!(apply(&step, &[0, 22, 50, 60, 98], 100), [0, 24, 48, 60, 96]);
+        // Scene changes are not moved past the last frame
+        assert_eq!(apply(&step, &[0, 118], 119), [0, 118]);
+    }
+
+    #[test]
+    fn snap_to_keyframe_ties() {
+        // Scene changes halfway between two keyframes move to the later one
+        let step = [PostProcessStep::SnapToKeyframe {
+            interval: 24,
+            tolerance: 12,
+        }];
+        assert_eq!(apply(&step, &[0, 12, 36], 100), [0, 24, 48]);
+        // Scene changes snapping to the same keyframe are merged,
+        // keeping the strength of the earlier one
+        let steps = [
+            PostProcessStep::SnapToKeyframe {
+                interval: 24,
+                tolerance: 3,
+            },
+            PostProcessStep::DropWeak {
+                min_difference: 0.1,
+            },
+        ];
+        let strengths = [(23, 0.5), (25, 0.01)];
+        assert_eq!(
+            apply_post_processing(&steps, &[0, 23, 25], &strengths, &[], 100),
+            [0, 24]
+        );
+        let strengths = [(23, 0.01), (25, 0.5)];
+        assert_eq!(
+            apply_post_processing(&steps, &[0, 23, 25], &strengths, &[], 100),
+            [0]
+        );
+    }
+
+    #[test]
+    fn snap_to_source_keyframe() {
+        let step = [PostProcessStep::SnapToSourceKeyframe { tolerance: 5 }];
+        let snap = |scene_changes: &[usize], keyframes: &[usize]| {
+            apply_post_processing(&step, scene_changes, &[], keyframes, 100)
+        };
+        assert_eq!(snap(&[0, 28, 45, 61], &[0, 30, 60]), [0, 30, 45, 60]);
+        // A scene change halfway between two keyframes moves to the earlier one
+        assert_eq!(snap(&[0, 45], &[0, 40, 50]), [0, 40]);
+        assert_eq!(snap(&[0, 3, 99], &[]), [0, 3, 99]);
+        // The first frame always starts a scene
+        assert_eq!(snap(&[0, 97], &[2, 99]), [0, 99]);
+    }
+
+    #[test]
+    fn drop_weak() {
+        let strengths = [(0, 0.01), (30, 0.05), (60, 0.5)];
+        let steps = [PostProcessStep::DropWeak {
+            min_difference: 0.1,
+        }];
+        assert_eq!(
+            apply_post_processing(&steps, &[0, 30, 60, 90], &strengths, &[], 100),
+            [0, 60, 90]
+        );
+    }
+
+    #[test]
+    fn split_scene_changes_are_not_dropped() {
+        let steps = [
+            PostProcessStep::SplitAbove { frames: 50 },
+            PostProcessStep::DropWeak {
+                min_difference: 0.1,
+            },
+        ];
+        assert_eq!(apply_post_processing(&steps, &[0], &[], &[], 100), [0, 50]);
+    }
+}
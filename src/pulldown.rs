@@ -0,0 +1,53 @@
+/// The mean luma difference from the previous frame, from `0.0` to `1.0`,
+/// at or below which a frame is considered a repeat of it.
+pub(crate) const REPEAT_THRESHOLD: f32 = 0.002;
+/// The number of frames in each group of a 3:2 pulldown cadence,
+/// of which one repeats the frame before it.
+const CADENCE_LENGTH: usize = 5;
+/// The fraction of groups of frames which must contain a repeated frame
+/// at the same position for the cadence to be reported.
+const MIN_COVERAGE: f64 = 0.5;
+
+/// A 3:2 pulldown cadence, in which every fifth frame repeats the frame
+/// before it, as in telecined film content which has been field matched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct PulldownCadence {
+    /// The 0-indexed frame number of the first repeated frame in the cadence.
+    /// Every fifth frame after it is also expected to be a repeat.
+    pub first_repeat: usize,
+    /// The fraction of groups of five frames whose expected frame
+    /// was a repeat, from `0.0` to `1.0`.
+    pub coverage: f64,
+}
+
+/// Finds the 3:2 pulldown cadence, given whether each frame
+/// of the clip repeats the frame before it.
+///
+/// Returns `None` if too few frames are repeats at a consistent position,
+/// or if repeats are as common elsewhere, such as in a still scene.
+pub(crate) fn find_cadence(is_repeat: &[bool]) -> Option<PulldownCadence> {
+    let groups = is_repeat.len() / CADENCE_LENGTH;
+    if groups == 0 {
+        return None;
+    }
+    let mut counts = [0; CADENCE_LENGTH];
+    for (frameno, _) in is_repeat
+        .iter()
+        .take(groups * CADENCE_LENGTH)
+        .enumerate()
+        .filter(|(_, &repeat)| repeat)
+    {
+        counts[frameno % CADENCE_LENGTH] += 1;
+    }
+    let (phase, &count) = counts.iter().enumerate().max_by_key(|(_, &count)| count)?;
+    let other_repeats = counts.iter().sum::<usize>() - count;
+    let coverage = count as f64 / groups as f64;
+    if coverage < MIN_COVERAGE || other_repeats * 2 > count {
+        return None;
+    }
+    Some(PulldownCadence {
+        first_repeat: phase,
+        coverage,
+    })
+}
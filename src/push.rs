@@ -0,0 +1,135 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::VideoDetails;
+use crate::pipeline::DetectionPipeline;
+use crate::{check_options, DetectionOptions};
+use rav1e::prelude::{ChromaSampling, Frame, Pixel};
+use std::mem;
+use std::sync::Arc;
+
+/// Runs detection on luma planes supplied by the caller as raw bytes,
+/// for bindings where the bit depth is only known at runtime.
+pub(crate) struct PushPipeline {
+    video_details: VideoDetails,
+    pipeline: AnyPipeline,
+    finished: bool,
+}
+
+enum AnyPipeline {
+    U8(DetectionPipeline<u8>),
+    U16(DetectionPipeline<u16>),
+}
+
+impl PushPipeline {
+    /// Returns the reason if the size, bit depth or options are invalid.
+    pub fn new(
+        width: usize,
+        height: usize,
+        bit_depth: usize,
+        opts: DetectionOptions,
+    ) -> Result<Self, &'static str> {
+        if width == 0 || height == 0 {
+            return Err("width and height must be positive");
+        }
+        if !(8..=16).contains(&bit_depth) {
+            return Err("bit depth must be between 8 and 16");
+        }
+        check_options(&opts)?;
+        let video_details = VideoDetails {
+            width,
+            height,
+            bit_depth,
+            chroma_sampling: ChromaSampling::Cs400,
+            ..Default::default()
+        };
+        let pipeline = if bit_depth == 8 {
+            AnyPipeline::U8(DetectionPipeline::new(video_details, opts))
+        } else {
+            AnyPipeline::U16(DetectionPipeline::new(video_details, opts))
+        };
+        Ok(PushPipeline {
+            video_details,
+            pipeline,
+            finished: false,
+        })
+    }
+
+    pub fn video_details(&self) -> &VideoDetails {
+        &self.video_details
+    }
+
+    /// Pushes the luma plane of the next frame.
+    ///
+    /// Samples are single bytes for 8-bit video, and little-endian
+    /// 16-bit values otherwise. `stride` is the distance between
+    /// the starts of consecutive rows, in bytes. The last row only needs
+    /// to hold the samples of the row, not the full stride.
+    pub fn push_luma(&mut self, data: &[u8], stride: usize) -> Result<(), &'static str> {
+        if self.finished {
+            return Err("detection has already finished");
+        }
+        let details = &self.video_details;
+        let bytes_per_sample = if details.bit_depth == 8 { 1 } else { 2 };
+        if stride < details.width * bytes_per_sample {
+            return Err("stride is smaller than a row of samples");
+        }
+        if data.len() < stride * (details.height - 1) + details.width * bytes_per_sample {
+            return Err("buffer is smaller than a frame");
+        }
+        match self.pipeline {
+            AnyPipeline::U8(ref mut pipeline) => push_frame(pipeline, details, data, stride),
+            AnyPipeline::U16(ref mut pipeline) => push_frame(pipeline, details, data, stride),
+        }
+        Ok(())
+    }
+
+    /// Finishes analyzing the pushed frames, and returns
+    /// the 0-indexed frame numbers of the scene changes.
+    ///
+    /// This may be called again to retrieve the same results,
+    /// but no further frames may be pushed.
+    pub fn finish(&mut self) -> &[usize] {
+        if !self.finished {
+            // The decisions for the remaining frames are also recorded in the keyframes
+            match self.pipeline {
+                AnyPipeline::U8(ref mut pipeline) => mem::drop(pipeline.finish()),
+                AnyPipeline::U16(ref mut pipeline) => mem::drop(pipeline.finish()),
+            }
+            self.finished = true;
+        }
+        match self.pipeline {
+            AnyPipeline::U8(ref pipeline) => pipeline.keyframes(),
+            AnyPipeline::U16(ref pipeline) => pipeline.keyframes(),
+        }
+    }
+}
+
+fn push_frame<T: Pixel>(
+    pipeline: &mut DetectionPipeline<T>,
+    details: &VideoDetails,
+    data: &[u8],
+    stride: usize,
+) {
+    let mut frame: Frame<T> = Frame::new_with_padding(
+        details.width,
+        details.height,
+        ChromaSampling::Cs400,
+        LUMA_PADDING,
+    );
+    let bytes_per_sample = mem::size_of::<T>();
+    if data.len() >= stride * details.height {
+        frame.planes[0].copy_from_raw_u8(data, stride, bytes_per_sample);
+    } else {
+        // Only whole strides are copied, so a last row which ends
+        // before its stride does would be dropped unless the rows are packed
+        let row_len = details.width * bytes_per_sample;
+        let packed = data
+            .chunks(stride)
+            .take(details.height)
+            .flat_map(|row| &row[..row_len])
+            .copied()
+            .collect::<Vec<_>>();
+        frame.planes[0].copy_from_raw_u8(&packed, row_len, bytes_per_sample);
+    }
+    // Only the scene changes are reported, which the pipeline keeps track of
+    pipeline.push(Arc::new(frame));
+}
@@ -0,0 +1,213 @@
+//! Python bindings, built as the `av_scenechange` module
+//! with `maturin build --features python`.
+
+use crate::convert::LUMA_PADDING;
+use crate::pipeline::DetectionPipeline;
+use crate::{
+    check_options, Decoder, DetectionOptions, SceneDetectionSpeed, VideoDetails, Y4mDecoder,
+};
+use numpy::PyReadonlyArray2;
+use pyo3::exceptions::{PyIOError, PyRuntimeError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rav1e::prelude::{ChromaSampling, Frame, Pixel};
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+#[pymodule]
+fn av_scenechange(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(detect_scene_changes, m)?)?;
+    m.add_class::<Detector>()?;
+    Ok(())
+}
+
+/// Detects scene changes in the y4m file at `path`, returning
+/// the 0-indexed frame numbers of the scene changes.
+///
+/// The options are `speed` (`"standard"`, `"fast"`, `"histogram"`
+/// or `"ecr"`), `detect_flashes`, `min_scenecut`, `max_scenecut`
+/// and `lookahead_distance`, as in the CLI.
+#[pyfunction]
+#[pyo3(text_signature = "(path, **options)")]
+#[args(options = "**")]
+fn detect_scene_changes(py: Python, path: &str, options: Option<&PyDict>) -> PyResult<Vec<usize>> {
+    let opts = parse_options(options)?;
+    let file = File::open(path).map_err(|err| PyIOError::new_err(err.to_string()))?;
+    let mut dec =
+        Y4mDecoder::new(BufReader::new(file)).map_err(|err| PyIOError::new_err(err.to_string()))?;
+    let bit_depth = dec.get_video_details().bit_depth;
+    // Detection does not touch any Python objects, so other threads can run meanwhile
+    let results = py.allow_threads(move || {
+        let results = if bit_depth == 8 {
+            crate::detect_scene_changes::<_, u8>(&mut dec, opts, None, None, None)
+        } else {
+            crate::detect_scene_changes::<_, u16>(&mut dec, opts, None, None, None)
+        };
+        results.map_err(|err| err.to_string())
+    });
+    results
+        .map(|results| results.scene_changes)
+        .map_err(PyRuntimeError::new_err)
+}
+
+/// A scene change detector which the luma planes
+/// of frames are pushed to one at a time, as 2D numpy arrays
+/// of `uint8` for 8-bit video or `uint16` otherwise.
+#[pyclass(unsendable)]
+struct Detector {
+    video_details: VideoDetails,
+    pipeline: Pipeline,
+    finished: bool,
+}
+
+enum Pipeline {
+    U8(DetectionPipeline<u8>),
+    U16(DetectionPipeline<u16>),
+}
+
+#[pymethods]
+impl Detector {
+    #[new]
+    #[args(bit_depth = "8", options = "**")]
+    fn new(
+        width: usize,
+        height: usize,
+        bit_depth: usize,
+        options: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err("width and height must be positive"));
+        }
+        if !(8..=16).contains(&bit_depth) {
+            return Err(PyValueError::new_err("bit_depth must be between 8 and 16"));
+        }
+        let opts = parse_options(options)?;
+        check_options(&opts).map_err(PyValueError::new_err)?;
+        let video_details = VideoDetails {
+            width,
+            height,
+            bit_depth,
+            chroma_sampling: ChromaSampling::Cs400,
+            ..Default::default()
+        };
+        let pipeline = if bit_depth == 8 {
+            Pipeline::U8(DetectionPipeline::new(video_details, opts))
+        } else {
+            Pipeline::U16(DetectionPipeline::new(video_details, opts))
+        };
+        Ok(Detector {
+            video_details,
+            pipeline,
+            finished: false,
+        })
+    }
+
+    /// Pushes the luma plane of the next frame,
+    /// a `height` by `width` numpy array.
+    #[pyo3(text_signature = "($self, luma)")]
+    fn push_frame(&mut self, luma: &PyAny) -> PyResult<()> {
+        if self.finished {
+            return Err(PyRuntimeError::new_err("detector has already finished"));
+        }
+        let details = &self.video_details;
+        match self.pipeline {
+            Pipeline::U8(ref mut pipeline) => {
+                let luma: PyReadonlyArray2<u8> = luma
+                    .extract()
+                    .map_err(|_| PyTypeError::new_err("expected a 2D uint8 array"))?;
+                push_frame(pipeline, details, &luma)
+            }
+            Pipeline::U16(ref mut pipeline) => {
+                let luma: PyReadonlyArray2<u16> = luma
+                    .extract()
+                    .map_err(|_| PyTypeError::new_err("expected a 2D uint16 array"))?;
+                push_frame(pipeline, details, &luma)
+            }
+        }
+    }
+
+    /// Finishes analyzing the pushed frames, and returns
+    /// the 0-indexed frame numbers of the scene changes.
+    ///
+    /// No further frames may be pushed after this.
+    #[pyo3(text_signature = "($self)")]
+    fn finish(&mut self) -> Vec<usize> {
+        match self.pipeline {
+            Pipeline::U8(ref mut pipeline) => finish(pipeline, &mut self.finished),
+            Pipeline::U16(ref mut pipeline) => finish(pipeline, &mut self.finished),
+        }
+    }
+}
+
+fn push_frame<T: Pixel, P: numpy::Element + Into<u32> + Copy>(
+    pipeline: &mut DetectionPipeline<T>,
+    details: &VideoDetails,
+    luma: &PyReadonlyArray2<P>,
+) -> PyResult<()> {
+    let luma = luma.as_array();
+    if luma.dim() != (details.height, details.width) {
+        return Err(PyValueError::new_err(format!(
+            "expected a {}x{} array, got {}x{}",
+            details.height,
+            details.width,
+            luma.nrows(),
+            luma.ncols()
+        )));
+    }
+    let mut frame: Frame<T> = Frame::new_with_padding(
+        details.width,
+        details.height,
+        ChromaSampling::Cs400,
+        LUMA_PADDING,
+    );
+    let stride = frame.planes[0].cfg.stride;
+    let data = frame.planes[0].data_origin_mut();
+    for (y, row) in luma.rows().into_iter().enumerate() {
+        for (dst, &src) in data[y * stride..][..details.width].iter_mut().zip(row) {
+            *dst = T::cast_from(src.into());
+        }
+    }
+    // Only the scene changes are reported, which the pipeline keeps track of
+    pipeline.push(Arc::new(frame));
+    Ok(())
+}
+
+fn finish<T: Pixel>(pipeline: &mut DetectionPipeline<T>, finished: &mut bool) -> Vec<usize> {
+    if !*finished {
+        pipeline.finish();
+        *finished = true;
+    }
+    pipeline.keyframes().to_vec()
+}
+
+fn parse_options(options: Option<&PyDict>) -> PyResult<DetectionOptions> {
+    let mut opts = DetectionOptions::default();
+    let options = match options {
+        Some(options) => options,
+        None => return Ok(opts),
+    };
+    for (key, value) in options {
+        match key.extract::<&str>()? {
+            "speed" => {
+                opts.analysis_speed = match value.extract::<&str>()? {
+                    "standard" => SceneDetectionSpeed::Standard,
+                    "fast" => SceneDetectionSpeed::Fast,
+                    "histogram" => SceneDetectionSpeed::Histogram,
+                    "ecr" => SceneDetectionSpeed::EdgeChangeRatio,
+                    speed => {
+                        return Err(PyValueError::new_err(format!("unknown speed: {}", speed)));
+                    }
+                }
+            }
+            "detect_flashes" => opts.detect_flashes = value.extract()?,
+            "min_scenecut" => opts.min_scenecut_distance = value.extract()?,
+            "max_scenecut" => opts.max_scenecut_distance = value.extract()?,
+            "lookahead_distance" => opts.lookahead_distance = value.extract()?,
+            key => {
+                return Err(PyTypeError::new_err(format!("unknown option: {}", key)));
+            }
+        }
+    }
+    Ok(opts)
+}
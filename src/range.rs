@@ -0,0 +1,115 @@
+use crate::decoder::{DecodeError, Decoder, FrameGap, FrameMeta, VideoDetails};
+use crate::DetectionOptions;
+use rav1e::prelude::{Frame, Pixel};
+
+/// Restricts a decoder to `frame_range`, along with
+/// the warmup frames preceding it, as set in the options.
+pub(crate) struct RangeDecoder<'a, D: Decoder> {
+    dec: &'a mut D,
+    /// The first frame to read.
+    start: usize,
+    /// The frame after the last frame to read, if limited.
+    end: Option<usize>,
+    /// The frame which would be read next.
+    next_frame: usize,
+}
+
+/// Applies `opts.frame_range` to a decoder, returning the restricted decoder,
+/// and the options to run detection on it with, whose warmup frames
+/// are limited to the frames available before the range.
+pub(crate) fn restrict_to_range<'a, D: Decoder>(
+    dec: &'a mut D,
+    opts: &DetectionOptions,
+) -> (RangeDecoder<'a, D>, DetectionOptions) {
+    let range = match opts.frame_range {
+        Some(ref range) => range,
+        None => {
+            let dec = RangeDecoder {
+                dec,
+                start: 0,
+                end: None,
+                next_frame: 0,
+            };
+            return (dec, opts.clone());
+        }
+    };
+    let warmup_frames = opts.warmup_frames.min(range.start);
+    let dec = RangeDecoder {
+        dec,
+        start: range.start - warmup_frames,
+        end: Some(range.end),
+        next_frame: 0,
+    };
+    let opts = DetectionOptions {
+        warmup_frames,
+        ..opts.clone()
+    };
+    (dec, opts)
+}
+
+impl<D: Decoder> Decoder for RangeDecoder<'_, D> {
+    fn get_video_details(&self) -> VideoDetails {
+        self.dec.get_video_details()
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.dec.total_frames().map(|total| {
+            self.end
+                .map_or(total, |end| end.min(total))
+                .saturating_sub(self.start)
+        })
+    }
+
+    fn read_video_frame<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<T>, DecodeError> {
+        self.seek_to_next_frame()?;
+        let frame = self.dec.read_video_frame(video_details)?;
+        self.next_frame += 1;
+        Ok(frame)
+    }
+
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+        frame: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        self.seek_to_next_frame()?;
+        self.dec.read_video_frame_into(video_details, frame)?;
+        self.next_frame += 1;
+        Ok(())
+    }
+
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        let start = self.start;
+        self.dec
+            .take_frame_gaps()
+            .into_iter()
+            .filter(|gap| gap.frameno >= start)
+            .map(|gap| FrameGap {
+                frameno: gap.frameno - start,
+                ..gap
+            })
+            .collect()
+    }
+
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        self.dec.frame_meta()
+    }
+}
+
+impl<D: Decoder> RangeDecoder<'_, D> {
+    /// Skips to the start of the range if needed, and returns
+    /// `DecodeError::EndOfStream` once the end of the range is reached.
+    fn seek_to_next_frame(&mut self) -> Result<(), DecodeError> {
+        if self.next_frame < self.start {
+            self.dec.skip_frames(self.start - self.next_frame)?;
+            self.next_frame = self.start;
+        }
+        if self.end.is_some_and(|end| self.next_frame >= end) {
+            return Err(DecodeError::EndOfStream);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::{DecodeError, Decoder, FieldOrder, VideoDetails};
+use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel, Rational};
+use std::io::{self, Read};
+
+/// Decodes headerless planar video, such as `-f rawvideo` output from ffmpeg
+/// or the output of a capture tool, from any reader.
+///
+/// Since the input has no header, its properties must be given up front.
+/// Samples of high bit depth video are little-endian 16-bit values,
+/// unless another layout is given by the pixel format in `with_pix_fmt`.
+pub struct RawDecoder<R: Read> {
+    reader: R,
+    video_details: VideoDetails,
+    layout: SampleLayout,
+    /// The size of each plane of a frame, in bytes.
+    plane_sizes: [usize; 3],
+    /// The buffer each plane is read into, reused between frames.
+    buf: Vec<u8>,
+}
+
+/// How the samples of raw video are stored,
+/// if not in planes of little-endian values.
+#[derive(Debug, Clone, Copy, Default)]
+struct SampleLayout {
+    /// Whether 16-bit samples are big-endian.
+    big_endian: bool,
+    /// Whether the chroma samples are interleaved in a single plane,
+    /// starting with U, as in NV12 and P010.
+    semi_planar: bool,
+    /// The number of bits 16-bit samples are shifted right by to reach
+    /// the bit depth of the video, for samples aligned to the most
+    /// significant bit or with more bits than the detector supports.
+    shift: u32,
+}
+
+impl<R: Read> RawDecoder<R> {
+    /// Creates a decoder for raw video with the given properties.
+    pub fn new(reader: R, video_details: VideoDetails) -> Self {
+        Self::with_layout(reader, video_details, SampleLayout::default())
+    }
+
+    fn with_layout(reader: R, video_details: VideoDetails, layout: SampleLayout) -> Self {
+        let bytes = if video_details.bit_depth > 8 { 2 } else { 1 };
+        let luma_size = video_details.width * video_details.height * bytes;
+        let chroma_size = if video_details.chroma_sampling == ChromaSampling::Cs400 {
+            0
+        } else {
+            let (chroma_width, chroma_height) = video_details
+                .chroma_sampling
+                .get_chroma_dimensions(video_details.width, video_details.height);
+            chroma_width * chroma_height * bytes
+        };
+        let plane_sizes = if layout.semi_planar {
+            [luma_size, 2 * chroma_size, 0]
+        } else {
+            [luma_size, chroma_size, chroma_size]
+        };
+        RawDecoder {
+            reader,
+            video_details,
+            layout,
+            plane_sizes,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Creates a decoder for raw video in the given ffmpeg pixel format,
+    /// such as `yuv420p`, `yuv420p10be` or `p010le`.
+    ///
+    /// Planar YUV and gray formats are supported, as well as the semi-planar
+    /// NV12 and P010 families. Samples with more than 12 bits, such as in
+    /// `p016le` or `yuv420p16le`, are reduced to 12 bits.
+    /// `framerate` is the number of frames per second.
+    pub fn with_pix_fmt(
+        reader: R,
+        width: usize,
+        height: usize,
+        pix_fmt: &str,
+        framerate: Rational,
+    ) -> Result<Self, DecodeError> {
+        let (chroma_sampling, bit_depth, layout) = parse_pix_fmt(pix_fmt).ok_or_else(|| {
+            DecodeError::InvalidInput(format!("Unsupported pixel format {}", pix_fmt))
+        })?;
+        let video_details = VideoDetails {
+            width,
+            height,
+            bit_depth,
+            chroma_sampling,
+            chroma_sample_position: ChromaSamplePosition::Unknown,
+            time_base: Rational::new(framerate.den, framerate.num),
+            field_order: FieldOrder::Progressive,
+        };
+        Ok(Self::with_layout(reader, video_details, layout))
+    }
+
+    /// Reads the next plane of `size` bytes into the buffer, returning `false`
+    /// if the input ended before the first byte of the plane.
+    fn read_plane(&mut self, size: usize) -> Result<bool, DecodeError> {
+        self.buf.resize(size, 0);
+        let mut filled = 0;
+        while filled < size {
+            match self.reader.read(&mut self.buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => {
+                    return Err(DecodeError::InvalidInput(
+                        "Input ended partway through a frame".to_string(),
+                    ));
+                }
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Converts the 16-bit samples in the buffer to little-endian values
+    /// aligned to the least significant bit.
+    fn normalize_samples(&mut self) {
+        let layout = self.layout;
+        if !layout.big_endian && layout.shift == 0 {
+            return;
+        }
+        for sample in self.buf.chunks_exact_mut(2) {
+            let value = if layout.big_endian {
+                u16::from_be_bytes([sample[0], sample[1]])
+            } else {
+                u16::from_le_bytes([sample[0], sample[1]])
+            };
+            sample.copy_from_slice(&(value >> layout.shift).to_le_bytes());
+        }
+    }
+}
+
+impl<R: Read> Decoder for RawDecoder<R> {
+    fn get_video_details(&self) -> VideoDetails {
+        self.video_details
+    }
+
+    fn read_video_frame<T: Pixel>(&mut self, cfg: &VideoDetails) -> Result<Frame<T>, DecodeError> {
+        let mut f: Frame<T> =
+            Frame::new_with_padding(cfg.width, cfg.height, cfg.chroma_sampling, LUMA_PADDING);
+        self.read_video_frame_into(cfg, &mut f)?;
+        Ok(f)
+    }
+
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        cfg: &VideoDetails,
+        f: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        let bytes = if cfg.bit_depth > 8 { 2 } else { 1 };
+        let plane_sizes = self.plane_sizes;
+        for (i, &size) in plane_sizes.iter().enumerate() {
+            // Monochrome input has no chroma planes to read
+            if size == 0 {
+                continue;
+            }
+            if !self.read_plane(size)? {
+                if i == 0 {
+                    return Err(DecodeError::EndOfStream);
+                }
+                return Err(DecodeError::InvalidInput(
+                    "Input ended partway through a frame".to_string(),
+                ));
+            }
+            if bytes > 1 {
+                self.normalize_samples();
+            }
+            if self.layout.semi_planar && i == 1 {
+                let (u, v) = deinterleave(&self.buf, bytes);
+                let width = f.planes[1].cfg.width;
+                f.planes[1].copy_from_raw_u8(&u, width * bytes, bytes);
+                f.planes[2].copy_from_raw_u8(&v, width * bytes, bytes);
+            } else {
+                let width = f.planes[i].cfg.width;
+                f.planes[i].copy_from_raw_u8(&self.buf, width * bytes, bytes);
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_frames(&mut self, count: usize) -> Result<(), DecodeError> {
+        // Skipped frames are discarded without being converted
+        let frame_size = self.plane_sizes.iter().sum::<usize>() as u64;
+        let skipped = io::copy(
+            &mut self.reader.by_ref().take(frame_size * count as u64),
+            &mut io::sink(),
+        )?;
+        if skipped < frame_size * count as u64 {
+            return Err(DecodeError::EndOfStream);
+        }
+        Ok(())
+    }
+}
+
+/// Splits a plane of interleaved U and V samples into separate planes.
+fn deinterleave(buf: &[u8], bytes: usize) -> (Vec<u8>, Vec<u8>) {
+    let mut u = Vec::with_capacity(buf.len() / 2);
+    let mut v = Vec::with_capacity(buf.len() / 2);
+    for pair in buf.chunks_exact(2 * bytes) {
+        u.extend_from_slice(&pair[..bytes]);
+        v.extend_from_slice(&pair[bytes..]);
+    }
+    (u, v)
+}
+
+/// Returns the chroma sampling, bit depth and sample layout
+/// of an ffmpeg pixel format.
+fn parse_pix_fmt(pix_fmt: &str) -> Option<(ChromaSampling, usize, SampleLayout)> {
+    let (name, big_endian) = match pix_fmt.strip_suffix("be") {
+        Some(name) => (name, true),
+        None => (pix_fmt.strip_suffix("le").unwrap_or(pix_fmt), false),
+    };
+    let split = name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (layout, bits) = name.split_at(split);
+    let bits = if bits.is_empty() {
+        8
+    } else {
+        bits.parse().ok()?
+    };
+
+    let (chroma_sampling, bits, semi_planar, msb_aligned) = match (layout, bits) {
+        ("nv", 12) => (ChromaSampling::Cs420, 8, true, false),
+        ("nv", 16) => (ChromaSampling::Cs422, 8, true, false),
+        ("nv", 24) => (ChromaSampling::Cs444, 8, true, false),
+        // The first digit of the P010 family gives the chroma sampling,
+        // and the rest the number of significant bits
+        ("p", 10 | 12 | 16) => (ChromaSampling::Cs420, bits, true, true),
+        ("p", 210 | 212 | 216) => (ChromaSampling::Cs422, bits - 200, true, true),
+        ("p", 410 | 412 | 416) => (ChromaSampling::Cs444, bits - 400, true, true),
+        ("gray", _) => (ChromaSampling::Cs400, bits, false, false),
+        ("yuv420p" | "yuvj420p", _) => (ChromaSampling::Cs420, bits, false, false),
+        ("yuv422p" | "yuvj422p", _) => (ChromaSampling::Cs422, bits, false, false),
+        ("yuv444p" | "yuvj444p", _) => (ChromaSampling::Cs444, bits, false, false),
+        _ => return None,
+    };
+    if !(8..=16).contains(&bits) {
+        return None;
+    }
+    let bit_depth = bits.min(12);
+    let shift = if msb_aligned {
+        16 - bit_depth
+    } else {
+        bits - bit_depth
+    };
+    let layout = SampleLayout {
+        big_endian,
+        semi_planar,
+        shift: shift as u32,
+    };
+    Some((chroma_sampling, bit_depth, layout))
+}
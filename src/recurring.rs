@@ -0,0 +1,315 @@
+use crate::DetectionResults;
+use std::mem;
+
+/// The minimum number of consecutive similar scenes
+/// for a segment to be considered recurring.
+const MIN_COMMON_SCENES: usize = 3;
+
+/// A segment which recurs across several videos,
+/// such as the opening or ending sequence of the episodes of a series.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommonSegment {
+    /// Where the segment appears, ordered by video and then by frame.
+    pub occurrences: Vec<SegmentOccurrence>,
+}
+
+/// Where a recurring segment appears in one of the videos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SegmentOccurrence {
+    /// The index of the video in the results the segment was found in.
+    pub video: usize,
+    /// The 0-indexed frame number where the segment starts.
+    pub start_frame: usize,
+    /// The frame number after the last frame of the segment.
+    pub end_frame: usize,
+}
+
+/// A run of scenes in one video, from `first` to `last` inclusive.
+#[derive(Clone, Copy)]
+struct SceneRun {
+    video: usize,
+    first: usize,
+    last: usize,
+}
+
+/// Finds segments which recur across the given videos, such as the opening
+/// and ending sequences of several episodes, from the scene fingerprints
+/// in the results of each video.
+///
+/// The results must have been detected with `compute_fingerprints` enabled.
+/// A segment is found where at least three consecutive scenes of one video
+/// look like consecutive scenes of another, so segments whose scenes are
+/// split differently between videos may be missed or found in parts.
+/// Segments are ordered by their first occurrence.
+pub fn find_common_segments(results: &[DetectionResults]) -> Vec<CommonSegment> {
+    let mut runs = Vec::new();
+    let mut links = Vec::new();
+    for (a, results_a) in results.iter().enumerate() {
+        for (b, results_b) in results.iter().enumerate().skip(a + 1) {
+            for (run_a, run_b) in matching_runs(results_a, results_b) {
+                links.push((runs.len(), runs.len() + 1));
+                runs.push(SceneRun { video: a, ..run_a });
+                runs.push(SceneRun { video: b, ..run_b });
+            }
+        }
+    }
+    // Runs which overlap in the same video are the same segment
+    for (i, x) in runs.iter().enumerate() {
+        for (j, y) in runs.iter().enumerate().skip(i + 1) {
+            if x.video == y.video && x.first <= y.last && y.first <= x.last {
+                links.push((i, j));
+            }
+        }
+    }
+
+    let mut parents = (0..runs.len()).collect::<Vec<_>>();
+    for (i, j) in links {
+        let (i, j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+        parents[i] = j;
+    }
+    let mut groups = vec![Vec::new(); runs.len()];
+    for (i, &run) in runs.iter().enumerate() {
+        let root = find_root(&mut parents, i);
+        groups[root].push(run);
+    }
+
+    let mut segments = groups
+        .into_iter()
+        .filter(|group| !group.is_empty())
+        .map(|mut group| {
+            group.sort_by_key(|run| (run.video, run.first));
+            let mut merged: Vec<SceneRun> = Vec::with_capacity(group.len());
+            for run in group {
+                match merged.last_mut() {
+                    Some(last) if last.video == run.video && run.first <= last.last => {
+                        last.last = last.last.max(run.last);
+                    }
+                    _ => merged.push(run),
+                }
+            }
+            CommonSegment {
+                occurrences: merged
+                    .into_iter()
+                    .map(|run| {
+                        let video = &results[run.video];
+                        SegmentOccurrence {
+                            video: run.video,
+                            start_frame: video.scene_fingerprints[run.first].start_frame,
+                            end_frame: scene_end(video, run.last),
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    segments.sort_by_key(|segment| {
+        let first = segment.occurrences[0];
+        (first.video, first.start_frame)
+    });
+    segments
+}
+
+/// Returns the longest runs of consecutive similar scenes in two videos
+/// which are at least `MIN_COMMON_SCENES` long, with the video indices unset.
+fn matching_runs(a: &DetectionResults, b: &DetectionResults) -> Vec<(SceneRun, SceneRun)> {
+    let (a, b) = (&a.scene_fingerprints, &b.scene_fingerprints);
+    let similar = |i: usize, j: usize| a[i].is_similar(&b[j]);
+
+    let mut runs = Vec::new();
+    // The length of the run of similar scenes ending at each scene of `b`,
+    // for the previous and the current scene of `a`
+    let mut previous = vec![0; b.len()];
+    let mut current = vec![0; b.len()];
+    for i in 0..a.len() {
+        for (j, len) in current.iter_mut().enumerate() {
+            *len = if similar(i, j) {
+                j.checked_sub(1).map_or(0, |j| previous[j]) + 1
+            } else {
+                0
+            };
+            let len = *len;
+            let continues = i + 1 < a.len() && j + 1 < b.len() && similar(i + 1, j + 1);
+            if len >= MIN_COMMON_SCENES && !continues {
+                runs.push((
+                    SceneRun {
+                        video: 0,
+                        first: i + 1 - len,
+                        last: i,
+                    },
+                    SceneRun {
+                        video: 0,
+                        first: j + 1 - len,
+                        last: j,
+                    },
+                ));
+            }
+        }
+        mem::swap(&mut previous, &mut current);
+    }
+    runs
+}
+
+/// Returns the frame number after the last frame of a scene.
+fn scene_end(results: &DetectionResults, scene: usize) -> usize {
+    results
+        .scene_fingerprints
+        .get(scene + 1)
+        .map_or(results.first_frame + results.frame_count, |next| {
+            next.start_frame
+        })
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SceneFingerprint;
+
+    const SCENE_LENGTH: usize = 10;
+    /// The hashes of the scenes of a recurring segment.
+    const INTRO: [u64; 3] = [
+        0x0000_0000_0000_ffff,
+        0x0000_0000_ffff_0000,
+        0x0000_ffff_0000_0000,
+    ];
+    /// Hashes of scenes which differ from each other and from the intro.
+    const OTHER: [u64; 3] = [
+        0xffff_0000_0000_0000,
+        0xff00_ff00_ff00_ff00,
+        0x00ff_00ff_00ff_00ff,
+    ];
+
+    /// Returns the results of a video whose scenes have the given hashes,
+    /// each `SCENE_LENGTH` frames long.
+    fn video(hashes: &[u64]) -> DetectionResults {
+        DetectionResults {
+            frame_count: hashes.len() * SCENE_LENGTH,
+            scene_fingerprints: hashes
+                .iter()
+                .enumerate()
+                .map(|(i, &hash)| SceneFingerprint {
+                    start_frame: i * SCENE_LENGTH,
+                    first: hash,
+                    middle: hash,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn occurrence(video: usize, first_scene: usize, scenes: usize) -> SegmentOccurrence {
+        SegmentOccurrence {
+            video,
+            start_frame: first_scene * SCENE_LENGTH,
+            end_frame: (first_scene + scenes) * SCENE_LENGTH,
+        }
+    }
+
+    #[test]
+    fn no_videos() {
+        assert!(find_common_segments(&[]).is_empty());
+        assert!(find_common_segments(&[video(&[]), video(&[])]).is_empty());
+    }
+
+    #[test]
+    fn single_video() {
+        let intro = [INTRO[0], INTRO[1], INTRO[2], OTHER[0]];
+        assert!(find_common_segments(&[video(&intro)]).is_empty());
+    }
+
+    #[test]
+    fn single_scene_videos() {
+        let videos = [video(&[INTRO[0]]), video(&[INTRO[0]])];
+        assert!(find_common_segments(&videos).is_empty());
+    }
+
+    #[test]
+    fn too_few_common_scenes() {
+        let videos = [
+            video(&[INTRO[0], INTRO[1], OTHER[0]]),
+            video(&[OTHER[1], INTRO[0], INTRO[1]]),
+        ];
+        assert!(find_common_segments(&videos).is_empty());
+    }
+
+    #[test]
+    fn segment_at_first_and_last_frame() {
+        let videos = [
+            video(&[OTHER[0], INTRO[0], INTRO[1], INTRO[2]]),
+            video(&[INTRO[0], INTRO[1], INTRO[2], OTHER[1]]),
+        ];
+        let segments = find_common_segments(&videos);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            segments[0].occurrences,
+            [occurrence(0, 1, 3), occurrence(1, 0, 3)]
+        );
+    }
+
+    #[test]
+    fn segment_across_three_videos() {
+        let videos = [
+            video(&[INTRO[0], INTRO[1], INTRO[2], OTHER[0]]),
+            video(&[OTHER[1], INTRO[0], INTRO[1], INTRO[2]]),
+            video(&[OTHER[2], OTHER[0], INTRO[0], INTRO[1], INTRO[2]]),
+        ];
+        let segments = find_common_segments(&videos);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            segments[0].occurrences,
+            [
+                occurrence(0, 0, 3),
+                occurrence(1, 1, 3),
+                occurrence(2, 2, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn segment_recurring_within_a_video() {
+        let videos = [
+            video(&[
+                INTRO[0], INTRO[1], INTRO[2], OTHER[0], INTRO[0], INTRO[1], INTRO[2],
+            ]),
+            video(&[INTRO[0], INTRO[1], INTRO[2]]),
+        ];
+        let segments = find_common_segments(&videos);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            segments[0].occurrences,
+            [
+                occurrence(0, 0, 3),
+                occurrence(0, 4, 3),
+                occurrence(1, 0, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn offset_clip_ends_at_its_last_frame() {
+        let mut clip = video(&[OTHER[0], INTRO[0], INTRO[1], INTRO[2]]);
+        clip.first_frame = 100;
+        for fingerprint in &mut clip.scene_fingerprints {
+            fingerprint.start_frame += 100;
+        }
+        let segments = find_common_segments(&[video(&INTRO), clip]);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            segments[0].occurrences[1],
+            SegmentOccurrence {
+                video: 1,
+                start_frame: 110,
+                end_frame: 140,
+            }
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use crate::decoder::VideoDetails;
+use crate::histogram;
+use crate::pipeline::{DetectionPipeline, FrameDecision};
+use crate::{check_options, DetectionError, DetectionOptions};
+use rav1e::prelude::{Frame, Pixel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// A scene change reported by a `SceneDetector`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct CutDecision {
+    /// The 0-indexed frame number of the first frame of the new scene.
+    pub frameno: usize,
+    /// The histogram difference from the preceding frame, from `0.0` to `1.0`,
+    /// the same as `SceneCut::confidence`.
+    pub confidence: f32,
+    /// Whether the scene change was decided by `flush`,
+    /// with fewer than `lookahead_distance` frames after it,
+    /// so it may have been decided differently had more frames followed.
+    pub flush_decided: bool,
+}
+
+/// Detects scene changes in frames pushed one at a time,
+/// for callers which manage decoding themselves.
+///
+/// The detector keeps the frames it needs for lookahead,
+/// so scene changes are reported with a delay of `lookahead_distance` frames.
+/// Options which are applied once detection has finished,
+/// such as `post_process`, `flash_suppression` and `frame_range`,
+/// and additional analysis such as heatmaps, are not supported,
+/// and the minimum scenecut distance is always applied
+/// with the `KeepFirst` policy.
+pub struct SceneDetector<T: Pixel> {
+    video_details: VideoDetails,
+    pipeline: DetectionPipeline<T>,
+    /// Scene changes which were detected but not yet returned,
+    /// if several frames were analyzed at once.
+    pending: VecDeque<CutDecision>,
+}
+
+impl<T: Pixel> SceneDetector<T> {
+    /// Creates a detector for frames with the given details.
+    ///
+    /// Returns `DetectionError::InvalidOptions` if the options cannot be applied.
+    pub fn new(
+        video_details: VideoDetails,
+        opts: DetectionOptions,
+    ) -> Result<Self, DetectionError> {
+        check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+        Ok(SceneDetector {
+            video_details,
+            pipeline: DetectionPipeline::new(video_details, opts),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Creates a detector for a live stream of frames with the given details,
+    /// which may run indefinitely, such as for monitoring a broadcast.
+    ///
+    /// Unlike `new`, the detector does not keep the history of the stream,
+    /// so its memory use stays bounded by the lookahead, and the delay
+    /// before a scene change is reported is bounded the same way as with `new`.
+    /// Call `flush` once the stream ends or pauses to get the remaining scene changes.
+    pub fn live(
+        video_details: VideoDetails,
+        opts: DetectionOptions,
+    ) -> Result<Self, DetectionError> {
+        check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+        Ok(SceneDetector {
+            video_details,
+            pipeline: DetectionPipeline::live(video_details, opts),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Pushes the next frame, returning a scene change
+    /// if one was detected at an earlier frame.
+    ///
+    /// The first frame is always reported as a scene change.
+    /// If more than one scene change is detected at once, which can happen
+    /// with `adaptive_lookahead`, the later ones are returned by later calls.
+    pub fn push_frame(&mut self, frame: Frame<T>) -> Option<CutDecision> {
+        self.push_shared_frame(Arc::new(frame))
+    }
+
+    /// Pushes the next frame like `push_frame`, for callers which
+    /// still need the frame, such as to encode it, without copying it.
+    pub fn push_shared_frame(&mut self, frame: Arc<Frame<T>>) -> Option<CutDecision> {
+        for decision in self.pipeline.push(frame) {
+            self.record(decision, false);
+        }
+        self.pending.pop_front()
+    }
+
+    /// Analyzes the frames held for lookahead with the frames
+    /// received so far, returning the scene changes which have not been
+    /// returned yet, such as once the end of input is reached.
+    ///
+    /// Frames can still be pushed afterwards, such as after a pause
+    /// in a live stream, and are analyzed as continuing the same video.
+    pub fn flush(&mut self) -> Vec<CutDecision> {
+        for decision in self.pipeline.finish() {
+            self.record(decision, true);
+        }
+        self.pending.drain(..).collect()
+    }
+
+    fn record(&mut self, decision: FrameDecision<T>, flush_decided: bool) {
+        if !decision.is_keyframe {
+            return;
+        }
+        self.pending.push_back(CutDecision {
+            frameno: decision.frameno,
+            confidence: decision.previous.as_ref().map_or(1.0, |previous| {
+                histogram::frame_difference(previous, &decision.frame, &self.video_details) as f32
+            }),
+            flush_decided,
+        });
+    }
+}
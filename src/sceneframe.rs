@@ -0,0 +1,54 @@
+use rav1e::prelude::{Frame, Pixel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Holds the frames of the current scene which may turn out to be
+/// its middle frame, until the end of the scene is known.
+pub(crate) struct MiddleFrameBuffer<T: Pixel> {
+    /// The first frame of the current scene.
+    scene_start: usize,
+    /// The frames from the middle of the scene so far to its latest frame.
+    frames: VecDeque<(usize, Arc<Frame<T>>)>,
+}
+
+impl<T: Pixel> MiddleFrameBuffer<T> {
+    pub fn new() -> Self {
+        MiddleFrameBuffer {
+            scene_start: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Adds the next analyzed frame, returning the middle frame
+    /// of the previous scene and its frame number if the frame starts a new scene.
+    pub fn push(
+        &mut self,
+        frameno: usize,
+        frame: &Arc<Frame<T>>,
+        is_keyframe: bool,
+    ) -> Option<(usize, Arc<Frame<T>>)> {
+        let middle = if is_keyframe { self.finish() } else { None };
+        if is_keyframe {
+            self.scene_start = frameno;
+        }
+        self.frames.push_back((frameno, Arc::clone(frame)));
+        // The middle only moves forward as the scene grows, and is the later
+        // of the two middle frames for scenes with an even number of frames
+        let middle_frameno = self.scene_start + (frameno + 1 - self.scene_start) / 2;
+        while self
+            .frames
+            .front()
+            .is_some_and(|&(frameno, _)| frameno < middle_frameno)
+        {
+            self.frames.pop_front();
+        }
+        middle
+    }
+
+    /// Ends the current scene, returning its middle frame if it has any frames.
+    pub fn finish(&mut self) -> Option<(usize, Arc<Frame<T>>)> {
+        let middle = self.frames.pop_front();
+        self.frames.clear();
+        middle
+    }
+}
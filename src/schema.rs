@@ -0,0 +1,53 @@
+use crate::{DetectionOptions, DetectionResults};
+use serde::de::Error;
+use serde::{Deserialize, Serialize};
+
+/// The version of the JSON format written by `DetectionRun::to_json`.
+///
+/// This is incremented whenever a change is made to the format
+/// which older readers would misinterpret.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A detection pass, along with the options it was run with,
+/// in a form that can be persisted and reloaded later.
+///
+/// The JSON form is an object with a `version` field,
+/// an `options` field, and the fields of `DetectionResults`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectionRun {
+    pub version: u32,
+    pub options: DetectionOptions,
+    #[serde(flatten)]
+    pub results: DetectionResults,
+}
+
+impl DetectionRun {
+    pub fn new(options: DetectionOptions, results: DetectionResults) -> Self {
+        DetectionRun {
+            version: SCHEMA_VERSION,
+            options,
+            results,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Parses a detection run, failing if it was written
+    /// with a different version of the format.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        let run: DetectionRun = serde_json::from_str(json)?;
+        if run.version != SCHEMA_VERSION {
+            return Err(serde_json::Error::custom(format!(
+                "Unsupported schema version {}, expected {}",
+                run.version, SCHEMA_VERSION
+            )));
+        }
+        Ok(run)
+    }
+}
@@ -0,0 +1,94 @@
+use rav1e::prelude::{Frame, Pixel};
+use std::fs;
+use std::sync::{Arc, Weak};
+
+/// How often, in frames, memory usage is sampled and a frame is tracked for leaks.
+const SAMPLE_INTERVAL: usize = 100;
+
+/// Resource usage measured over a detection pass.
+///
+/// Memory and file descriptor counts are read from `/proc`,
+/// and are `None` on platforms without it.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SoakReport {
+    /// The peak resident memory of the process, in bytes.
+    pub peak_memory: Option<u64>,
+    /// The average resident memory over the second half of the pass, in bytes.
+    /// This should stay flat during a long run if nothing is leaking.
+    pub steady_state_memory: Option<u64>,
+    /// The number of open file descriptors before the pass.
+    pub open_fds_before: Option<usize>,
+    /// The number of open file descriptors after the pass.
+    pub open_fds_after: Option<usize>,
+    /// The number of frames sampled for leak tracking.
+    pub tracked_frames: usize,
+    /// The number of sampled frames which were still alive
+    /// after detection finished.
+    pub leaked_frames: usize,
+}
+
+/// Tracks resource usage during a detection pass.
+pub(crate) struct SoakMonitor<T: Pixel> {
+    open_fds_before: Option<usize>,
+    memory_samples: Vec<u64>,
+    tracked_frames: Vec<Weak<Frame<T>>>,
+}
+
+impl<T: Pixel> SoakMonitor<T> {
+    pub fn new() -> Self {
+        SoakMonitor {
+            open_fds_before: open_fds(),
+            memory_samples: Vec::new(),
+            tracked_frames: Vec::new(),
+        }
+    }
+
+    /// Samples resource usage after a frame is analyzed.
+    pub fn sample(&mut self, frameno: usize, frame: &Arc<Frame<T>>) {
+        if frameno % SAMPLE_INTERVAL != 0 {
+            return;
+        }
+        if let Some(rss) = status_field("VmRSS:") {
+            self.memory_samples.push(rss);
+        }
+        self.tracked_frames.push(Arc::downgrade(frame));
+    }
+
+    /// Produces the report once all frames have been released.
+    pub fn finish(self) -> SoakReport {
+        let steady_state = &self.memory_samples[self.memory_samples.len() / 2..];
+        SoakReport {
+            peak_memory: status_field("VmHWM:"),
+            steady_state_memory: if steady_state.is_empty() {
+                None
+            } else {
+                Some(steady_state.iter().sum::<u64>() / steady_state.len() as u64)
+            },
+            open_fds_before: self.open_fds_before,
+            open_fds_after: open_fds(),
+            tracked_frames: self.tracked_frames.len(),
+            leaked_frames: self
+                .tracked_frames
+                .iter()
+                .filter(|frame| frame.strong_count() > 0)
+                .count(),
+        }
+    }
+}
+
+/// Reads a memory field, given in kB, from `/proc/self/status` in bytes.
+fn status_field(name: &str) -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(name))?;
+    let kb = line[name.len()..]
+        .split_whitespace()
+        .next()?
+        .parse::<u64>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+fn open_fds() -> Option<usize> {
+    fs::read_dir("/proc/self/fd").ok().map(|dir| dir.count())
+}
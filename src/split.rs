@@ -0,0 +1,50 @@
+/// A desired split point which does not fall on a keyframe
+/// of the source, and so cannot be split at without re-encoding.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnachievableSplit {
+    /// The 0-indexed frame number of the desired split point.
+    pub frameno: usize,
+    /// The keyframe closest to the split point, if the source has any.
+    pub nearest_keyframe: Option<usize>,
+    /// The number of frames from the split point to `nearest_keyframe`.
+    /// Negative if the keyframe comes before the split point.
+    pub offset: isize,
+}
+
+/// Checks which of `split_points`, such as detected scene changes,
+/// can be split at losslessly by stream copying the source.
+///
+/// `keyframes` is the sorted list of frames that the source container
+/// marks as keyframes (IDR frames). A split point is only achievable
+/// without re-encoding if it is one of these. Returns the split points
+/// that are not achievable, along with the nearest keyframe to each.
+pub fn find_unachievable_splits(
+    split_points: &[usize],
+    keyframes: &[usize],
+) -> Vec<UnachievableSplit> {
+    split_points
+        .iter()
+        .filter_map(|&frameno| {
+            let index = match keyframes.binary_search(&frameno) {
+                Ok(_) => return None,
+                Err(index) => index,
+            };
+            let before = index.checked_sub(1).map(|i| keyframes[i]);
+            let after = keyframes.get(index).copied();
+            let nearest_keyframe = match (before, after) {
+                (Some(before), Some(after)) => Some(if frameno - before <= after - frameno {
+                    before
+                } else {
+                    after
+                }),
+                (before, after) => before.or(after),
+            };
+            Some(UnachievableSplit {
+                frameno,
+                nearest_keyframe,
+                offset: nearest_keyframe.map_or(0, |keyframe| keyframe as isize - frameno as isize),
+            })
+        })
+        .collect()
+}
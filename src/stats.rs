@@ -0,0 +1,124 @@
+use crate::{DetectionResults, Scene};
+use rav1e::prelude::Rational;
+
+/// Summary statistics of the scenes of a video,
+/// such as for classifying content by its pacing.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneStatistics {
+    /// The number of scenes.
+    pub scene_count: usize,
+    /// The mean length of the scenes, in frames.
+    pub mean_length: f64,
+    /// The median length of the scenes, in frames.
+    pub median_length: f64,
+    /// The 10th percentile length of the scenes, in frames.
+    pub p10_length: usize,
+    /// The 90th percentile length of the scenes, in frames.
+    pub p90_length: usize,
+    /// The shortest scene, if there are any.
+    pub shortest_scene: Option<Scene>,
+    /// The longest scene, if there are any.
+    pub longest_scene: Option<Scene>,
+    /// The number of scene changes per minute of video,
+    /// not counting the start of the first scene.
+    pub cuts_per_minute: f64,
+}
+
+/// Computes summary statistics of the scenes in `results`,
+/// for a video with frames of duration `time_base`.
+///
+/// Percentiles use the nearest-rank method.
+pub fn scene_statistics(results: &DetectionResults, time_base: Rational) -> SceneStatistics {
+    let scenes = results.scenes();
+    let mut lengths = scenes.iter().map(|scene| scene.len).collect::<Vec<_>>();
+    lengths.sort_unstable();
+
+    let minutes = results.frame_count as f64 * time_base.num as f64 / time_base.den as f64 / 60.0;
+    SceneStatistics {
+        scene_count: scenes.len(),
+        mean_length: if lengths.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<usize>() as f64 / lengths.len() as f64
+        },
+        median_length: if lengths.is_empty() {
+            0.0
+        } else {
+            (lengths[(lengths.len() - 1) / 2] + lengths[lengths.len() / 2]) as f64 / 2.0
+        },
+        p10_length: percentile_length(&lengths, 0.1),
+        p90_length: percentile_length(&lengths, 0.9),
+        // The first of several scenes of equal length is reported
+        shortest_scene: scenes.iter().min_by_key(|scene| scene.len).copied(),
+        longest_scene: scenes.iter().rev().max_by_key(|scene| scene.len).copied(),
+        cuts_per_minute: if minutes > 0.0 {
+            scenes.len().saturating_sub(1) as f64 / minutes
+        } else {
+            0.0
+        },
+    }
+}
+
+/// Returns the length at `percentile` of the sorted `lengths`.
+fn percentile_length(lengths: &[usize], percentile: f64) -> usize {
+    if lengths.is_empty() {
+        return 0;
+    }
+    let rank = (percentile * lengths.len() as f64).ceil() as usize;
+    lengths[rank.clamp(1, lengths.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::results;
+
+    /// 25 frames per second.
+    const TIME_BASE: Rational = Rational { num: 1, den: 25 };
+
+    fn statistics(scene_changes: &[usize], frame_count: usize) -> SceneStatistics {
+        scene_statistics(&results(scene_changes, frame_count), TIME_BASE)
+    }
+
+    #[test]
+    fn even_number_of_scenes() {
+        // Scenes of 5, 1, 9, 2, 8, 3, 7, 4, 6 and 10 frames
+        let stats = statistics(&[0, 5, 6, 15, 17, 25, 28, 35, 39, 45], 55);
+        assert_eq!(stats.scene_count, 10);
+        assert_eq!(stats.mean_length, 5.5);
+        // The mean of the two middle lengths
+        assert_eq!(stats.median_length, 5.5);
+        assert_eq!((stats.p10_length, stats.p90_length), (1, 9));
+        assert_eq!(stats.shortest_scene.map(|scene| scene.start_frame), Some(5));
+        assert_eq!(stats.longest_scene.map(|scene| scene.start_frame), Some(45));
+    }
+
+    #[test]
+    fn odd_number_of_scenes() {
+        // Scenes of 10, 30 and 60 frames
+        let stats = statistics(&[0, 10, 40], 100);
+        assert_eq!(stats.scene_count, 3);
+        assert!((stats.mean_length - 100.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.median_length, 30.0);
+        // Nearest-rank percentiles round the rank up
+        assert_eq!((stats.p10_length, stats.p90_length), (10, 60));
+    }
+
+    #[test]
+    fn cuts_per_minute() {
+        // Three cuts in four seconds
+        let stats = statistics(&[0, 10, 30, 60], 100);
+        assert!((stats.cuts_per_minute - 45.0).abs() < 1e-9);
+        // The start of the only scene is not a cut
+        assert_eq!(statistics(&[0], 1500).cuts_per_minute, 0.0);
+        assert_eq!(statistics(&[], 0).cuts_per_minute, 0.0);
+    }
+
+    #[test]
+    fn ties_report_the_first_scene() {
+        let stats = statistics(&[0, 10, 20], 30);
+        assert_eq!(stats.shortest_scene.map(|scene| scene.start_frame), Some(0));
+        assert_eq!(stats.longest_scene.map(|scene| scene.start_frame), Some(0));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::parallel::apply_scenecut_distances;
+use crate::pipeline::DetectionPipeline;
+use crate::postprocess;
+use crate::range;
+use crate::{
+    check_options, scenecut_distances, DecodeError, Decoder, DetectionError, DetectionOptions,
+    DetectionResults, VideoDetails,
+};
+use rav1e::prelude::{Frame, Pixel};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Runs through a video clip, analyzing only every `step`th frame
+/// in a coarse pass, and refining each scene change it finds
+/// by analyzing every frame since the previous analyzed frame.
+/// The frames after the last analyzed frame are always analyzed in full.
+///
+/// Since most frames are far from any scene change, this analyzes
+/// several times fewer frames on long-form content, at the cost of missing
+/// scene changes the coarse pass does not see, such as short scenes
+/// between two analyzed frames, or cuts between similar-looking scenes
+/// which are harder to tell apart from motion over `step` frames.
+/// Every frame is still decoded, and only the last `2 * step + 1` frames
+/// are held for refinement.
+///
+/// Flash detection is only performed during refinement, and the scenecut
+/// distances are applied to the refined scene changes, without strengths,
+/// so `MinDistancePolicy::KeepStronger` keeps the first scene change.
+/// `warmup_frames` are skipped rather than analyzed, and black bars are
+/// not detected. As with `detect_scene_changes_parallel`, only the list
+/// of scene changes is reported.
+///
+/// If the decoder fails partway through the clip, the scene changes found
+/// in the frames decoded until then are returned as part of the error.
+pub fn detect_scene_changes_subsampled<D: Decoder, T: Pixel>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    step: usize,
+) -> Result<DetectionResults, DetectionError> {
+    assert!(step >= 1);
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+
+    let first_frame = opts.frame_range.as_ref().map_or(0, |range| range.start);
+    let (mut dec, opts) = range::restrict_to_range(dec, &opts);
+    let dec = &mut dec;
+    let video_details = dec.get_video_details();
+    let refine_opts = DetectionOptions {
+        min_scenecut_distance: None,
+        max_scenecut_distance: None,
+        min_scenecut_duration: None,
+        max_scenecut_duration: None,
+        warmup_frames: 0,
+        detect_letterbox: None,
+        ..opts.clone()
+    };
+    // Flashes cannot be told apart from scene changes this far apart,
+    // and a short lookahead keeps fewer frames held for refinement
+    let coarse_opts = DetectionOptions {
+        detect_flashes: false,
+        lookahead_distance: 1,
+        ..refine_opts.clone()
+    };
+
+    let start_time = Instant::now();
+    let mut coarse = DetectionPipeline::new(video_details, coarse_opts);
+    // The frames since the analyzed frame before the last one,
+    // starting at `window_start`
+    let mut window = VecDeque::with_capacity(2 * step + 1);
+    let mut window_start = 0;
+    let mut frames_read = 0;
+    let mut frame_count = 0;
+    let mut scene_changes = Vec::new();
+    let mut decode_error = None;
+    loop {
+        let frame = match dec.read_video_frame::<T>(&video_details) {
+            Ok(frame) => frame,
+            Err(DecodeError::EndOfStream) => break,
+            Err(err) => {
+                decode_error = Some(err);
+                break;
+            }
+        };
+        frames_read += 1;
+        if frames_read <= opts.warmup_frames {
+            continue;
+        }
+
+        let frame = Arc::new(frame);
+        window.push_back(Arc::clone(&frame));
+        if frame_count % step == 0 {
+            for decision in coarse.push(frame) {
+                scene_changes.extend(refine(
+                    &window,
+                    window_start,
+                    decision.frameno,
+                    decision.is_keyframe,
+                    step,
+                    &video_details,
+                    &refine_opts,
+                ));
+            }
+        }
+        frame_count += 1;
+        while window_start + 2 * step < frame_count {
+            window.pop_front();
+            window_start += 1;
+        }
+    }
+    if decode_error.is_none() {
+        for decision in coarse.finish() {
+            scene_changes.extend(refine(
+                &window,
+                window_start,
+                decision.frameno,
+                decision.is_keyframe,
+                step,
+                &video_details,
+                &refine_opts,
+            ));
+        }
+        // The coarse pass cannot see scene changes after its last frame
+        let last_sample = frame_count.saturating_sub(1) / step * step;
+        if last_sample + 1 < frame_count {
+            scene_changes.extend(analyze(
+                &window,
+                window_start,
+                last_sample,
+                frame_count - 1,
+                &video_details,
+                &refine_opts,
+            ));
+        }
+    }
+
+    let (min_scenecut_distance, max_scenecut_distance) = scenecut_distances(&video_details, &opts);
+    let scene_changes = apply_scenecut_distances(
+        &scene_changes,
+        &[],
+        frame_count,
+        min_scenecut_distance,
+        max_scenecut_distance,
+        opts.min_distance_policy,
+    );
+    let mut results = DetectionResults {
+        scene_changes: postprocess::apply_post_processing(
+            &opts.post_process,
+            &scene_changes,
+            &[],
+            &[],
+            frame_count,
+        ),
+        frame_count,
+        speed: frame_count as f64 / start_time.elapsed().as_secs_f64(),
+        ..Default::default()
+    };
+    results.offset_frames(first_frame);
+    match decode_error {
+        Some(err) => Err(DetectionError::from_decode_error(err, results)),
+        None => Ok(results),
+    }
+}
+
+/// Returns the scene changes between the analyzed frame before `sample`
+/// and `sample` itself, if the coarse pass found one there, by analyzing
+/// every frame between them. The scene change is kept at the analyzed frame
+/// if none is found between them.
+fn refine<T: Pixel>(
+    window: &VecDeque<Arc<Frame<T>>>,
+    window_start: usize,
+    sample: usize,
+    is_keyframe: bool,
+    step: usize,
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> Vec<usize> {
+    if !is_keyframe {
+        return Vec::new();
+    }
+    if sample == 0 {
+        return vec![0];
+    }
+    let end = sample * step;
+    let start = end - step;
+    let refined = analyze(window, window_start, start, end, video_details, opts);
+    if refined.is_empty() {
+        vec![end]
+    } else {
+        refined
+    }
+}
+
+/// Returns the scene changes after `start` up to and including `end`,
+/// found by analyzing every frame between them.
+fn analyze<T: Pixel>(
+    window: &VecDeque<Arc<Frame<T>>>,
+    window_start: usize,
+    start: usize,
+    end: usize,
+    video_details: &VideoDetails,
+    opts: &DetectionOptions,
+) -> Vec<usize> {
+    let mut pipeline = DetectionPipeline::new(*video_details, opts.clone());
+    for frame in window.range(start - window_start..=end - window_start) {
+        pipeline.push(Arc::clone(frame));
+    }
+    pipeline.finish();
+    pipeline
+        .keyframes()
+        .iter()
+        .filter(|&&frameno| frameno > 0)
+        .map(|&frameno| start + frameno)
+        .collect()
+}
@@ -0,0 +1,158 @@
+use crate::pipeline::{DetectionPipeline, FrameDecision};
+use crate::y4m::RawFrame;
+use crate::{
+    check_options, DecodeError, Decoder, DetectionError, DetectionOptions, DetectionResults,
+    Y4mDecoder,
+};
+use rav1e::prelude::Pixel;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The y4m frame parameter added to the first frame of each scene
+/// by `tag_scene_changes`.
+pub const SCENE_CHANGE_PARAM: &str = "XSCENECHANGE=1";
+
+/// Copies y4m video from `dec` to `writer`, adding `SCENE_CHANGE_PARAM`
+/// to the frame parameters of the first frame of each scene.
+///
+/// This lets the detector sit in the middle of a pipe, such as between
+/// `vspipe` and an encoder, tagging frames for the tools downstream.
+/// Frames are written as soon as they have been analyzed, so the output
+/// lags behind the input by the lookahead distance. The header and
+/// existing frame parameters are kept, and warmup frames are copied untagged.
+///
+/// Only the list of scene changes is returned. Additional analysis
+/// enabled in `opts`, as well as `frame_range` and `post_process`,
+/// is not performed, since it cannot be reflected in the tags.
+/// For the same reason, the minimum scenecut distance is always
+/// applied with the `KeepFirst` policy.
+///
+/// If decoding fails partway through the clip, the frames read until then
+/// are still written, and the results for them are returned as part of the error.
+pub fn tag_scene_changes<R: Read, W: Write, T: Pixel>(
+    dec: &mut Y4mDecoder<R>,
+    writer: W,
+    opts: DetectionOptions,
+) -> Result<DetectionResults, DetectionError> {
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+    let video_details = dec.get_video_details();
+    let start_time = Instant::now();
+    let mut pipeline = DetectionPipeline::new(video_details, opts.clone());
+    let mut output = match dec.encoder_builder().write_header(writer) {
+        Ok(enc) => TaggedOutput {
+            enc,
+            pending: VecDeque::new(),
+            frames_written: 0,
+            warmup_frames: opts.warmup_frames,
+        },
+        Err(err) => {
+            return Err(DetectionError::from_output_error(
+                map_y4m_write_error(err),
+                DetectionResults::default(),
+            ));
+        }
+    };
+
+    let mut decode_error = None;
+    let mut output_error = None;
+    loop {
+        let (frame, raw) = match dec.read_video_frame_with_raw::<T>(&video_details) {
+            Ok(frame) => frame,
+            Err(DecodeError::EndOfStream) => break,
+            Err(err) => {
+                decode_error = Some(err);
+                break;
+            }
+        };
+        output.pending.push_back(raw);
+        if let Err(err) = pipeline
+            .push(Arc::new(frame))
+            .into_iter()
+            .try_for_each(|decision| output.write_decision(&decision))
+        {
+            output_error = Some(err);
+            break;
+        }
+    }
+    if output_error.is_none() {
+        let result = if decode_error.is_none() {
+            pipeline
+                .finish()
+                .into_iter()
+                .try_for_each(|decision| output.write_decision(&decision))
+        } else {
+            Ok(())
+        };
+        output_error = result.and_then(|_| output.write_pending()).err();
+    }
+
+    let frame_count = pipeline.frames_analyzed();
+    let results = DetectionResults {
+        scene_changes: pipeline.keyframes().to_vec(),
+        frame_count,
+        speed: frame_count as f64 / start_time.elapsed().as_secs_f64(),
+        ..Default::default()
+    };
+    match (output_error, decode_error) {
+        (Some(err), _) => Err(DetectionError::from_output_error(err, results)),
+        (None, Some(err)) => Err(DetectionError::from_decode_error(err, results)),
+        (None, None) => Ok(results),
+    }
+}
+
+/// The y4m output of `tag_scene_changes`,
+/// along with the frames which have not been written yet.
+struct TaggedOutput<W: Write> {
+    enc: y4m::Encoder<W>,
+    /// Starts at frame `frames_written`, counting the warmup frames.
+    pending: VecDeque<RawFrame>,
+    frames_written: usize,
+    warmup_frames: usize,
+}
+
+impl<W: Write> TaggedOutput<W> {
+    /// Writes the frames up to and including the analyzed frame,
+    /// tagging it if it starts a new scene.
+    fn write_decision<T: Pixel>(&mut self, decision: &FrameDecision<T>) -> io::Result<()> {
+        let frameno = decision.frameno + self.warmup_frames;
+        while self.frames_written < frameno {
+            self.write_next(false)?;
+        }
+        self.write_next(decision.is_keyframe)
+    }
+
+    /// Writes all remaining frames untagged.
+    fn write_pending(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            self.write_next(false)?;
+        }
+        Ok(())
+    }
+
+    fn write_next(&mut self, is_scene_change: bool) -> io::Result<()> {
+        let RawFrame { planes, mut params } = match self.pending.pop_front() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        if is_scene_change {
+            let params = params.get_or_insert_with(Vec::new);
+            if !params.is_empty() {
+                params.push(b' ');
+            }
+            params.extend_from_slice(SCENE_CHANGE_PARAM.as_bytes());
+        }
+        let frame = y4m::Frame::new([&planes[0], &planes[1], &planes[2]], params);
+        self.enc.write_frame(&frame).map_err(map_y4m_write_error)?;
+        self.frames_written += 1;
+        Ok(())
+    }
+}
+
+fn map_y4m_write_error(err: y4m::Error) -> io::Error {
+    match err {
+        y4m::Error::IoError(err) => err,
+        err => io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)),
+    }
+}
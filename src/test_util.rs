@@ -0,0 +1,11 @@
+use crate::DetectionResults;
+
+/// Returns the results of a clip of `frame_count` frames
+/// with the given scene changes.
+pub(crate) fn results(scene_changes: &[usize], frame_count: usize) -> DetectionResults {
+    DetectionResults {
+        scene_changes: scene_changes.to_vec(),
+        frame_count,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,188 @@
+use crate::decoder::{DecodeError, Decoder, FrameGap, FrameMeta, VideoDetails};
+use rav1e::prelude::{Frame, Pixel, PixelType};
+use std::any::Any;
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// The size of each read from the underlying reader.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Wraps a reader, such as a network stream or a pipe from another process,
+/// so that reads which take longer than a timeout fail instead of hanging.
+///
+/// The underlying reader is read on a background thread. A read which
+/// times out returns an error of kind `io::ErrorKind::TimedOut`,
+/// which detection reports as `DetectionError::DecoderStalled`.
+pub struct TimeoutReader {
+    chunk_rx: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    timeout: Duration,
+}
+
+impl TimeoutReader {
+    pub fn new<R: Read + Send + 'static>(mut reader: R, timeout: Duration) -> Self {
+        let (chunk_tx, chunk_rx) = mpsc::sync_channel(2);
+        // If the reader hangs, this thread is left behind
+        // until the read returns, since it cannot be interrupted
+        thread::spawn(move || loop {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let result = match reader.read(&mut chunk) {
+                Ok(0) => return,
+                Ok(len) => {
+                    chunk.truncate(len);
+                    Ok(chunk)
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => Err(err),
+            };
+            let failed = result.is_err();
+            if chunk_tx.send(result).is_err() || failed {
+                return;
+            }
+        });
+        TimeoutReader {
+            chunk_rx,
+            chunk: Vec::new(),
+            pos: 0,
+            timeout,
+        }
+    }
+}
+
+impl Read for TimeoutReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.chunk.len() {
+            self.chunk = match self.chunk_rx.recv_timeout(self.timeout) {
+                Ok(chunk) => chunk?,
+                Err(RecvTimeoutError::Timeout) => return Err(timed_out()),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pos = 0;
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Wraps a decoder, such as one reading a growing file or frames
+/// sent by another thread, so that reading a frame which takes longer
+/// than a timeout fails instead of hanging, whichever backend it uses.
+///
+/// The decoder runs on a background thread. A read which times out returns
+/// an error of kind `io::ErrorKind::TimedOut`, which detection reports
+/// as `DetectionError::DecoderStalled`, and so does every read after it.
+pub struct TimeoutDecoder {
+    video_details: VideoDetails,
+    total_frames: Option<usize>,
+    request_tx: Sender<(PixelType, VideoDetails)>,
+    frame_rx: Receiver<DecodedFrame>,
+    timeout: Duration,
+    frame_meta: Option<FrameMeta>,
+    frame_gaps: Vec<FrameGap>,
+    stalled: bool,
+}
+
+/// The outcome of a read on the background thread of a `TimeoutDecoder`.
+struct DecodedFrame {
+    /// The `Result<Frame<T>, DecodeError>` for the requested pixel type.
+    result: Box<dyn Any + Send>,
+    meta: Option<FrameMeta>,
+    gaps: Vec<FrameGap>,
+}
+
+impl TimeoutDecoder {
+    pub fn new<D: Decoder + Send + 'static>(mut dec: D, timeout: Duration) -> Self {
+        let video_details = dec.get_video_details();
+        let total_frames = dec.total_frames();
+        let (request_tx, request_rx) = mpsc::channel::<(PixelType, VideoDetails)>();
+        let (frame_tx, frame_rx) = mpsc::channel();
+        // As with `TimeoutReader`, a hung decoder leaves this thread behind
+        thread::spawn(move || {
+            for (pixel_type, details) in request_rx {
+                let result: Box<dyn Any + Send> = match pixel_type {
+                    PixelType::U8 => Box::new(dec.read_video_frame::<u8>(&details)),
+                    PixelType::U16 => Box::new(dec.read_video_frame::<u16>(&details)),
+                };
+                let frame = DecodedFrame {
+                    result,
+                    meta: dec.frame_meta(),
+                    gaps: dec.take_frame_gaps(),
+                };
+                if frame_tx.send(frame).is_err() {
+                    return;
+                }
+            }
+        });
+        TimeoutDecoder {
+            video_details,
+            total_frames,
+            request_tx,
+            frame_rx,
+            timeout,
+            frame_meta: None,
+            frame_gaps: Vec::new(),
+            stalled: false,
+        }
+    }
+}
+
+impl Decoder for TimeoutDecoder {
+    fn get_video_details(&self) -> VideoDetails {
+        self.video_details
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.total_frames
+    }
+
+    fn read_video_frame<T: Pixel>(
+        &mut self,
+        video_details: &VideoDetails,
+    ) -> Result<Frame<T>, DecodeError> {
+        // A frame which arrives after a timeout would be out of order
+        if self.stalled {
+            return Err(timed_out().into());
+        }
+        // The background thread only ends early if the decoder panicked
+        let panicked =
+            || -> DecodeError { io::Error::new(io::ErrorKind::Other, "Decoder panicked").into() };
+        if self
+            .request_tx
+            .send((T::type_enum(), *video_details))
+            .is_err()
+        {
+            return Err(panicked());
+        }
+        let frame = match self.frame_rx.recv_timeout(self.timeout) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => {
+                self.stalled = true;
+                return Err(timed_out().into());
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(panicked()),
+        };
+        self.frame_meta = frame.meta;
+        self.frame_gaps.extend(frame.gaps);
+        *frame
+            .result
+            .downcast::<Result<Frame<T>, DecodeError>>()
+            .expect("Pixel type should match its type_enum")
+    }
+
+    fn take_frame_gaps(&mut self) -> Vec<FrameGap> {
+        std::mem::take(&mut self.frame_gaps)
+    }
+
+    fn frame_meta(&self) -> Option<FrameMeta> {
+        self.frame_meta
+    }
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "Timed out waiting for input")
+}
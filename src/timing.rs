@@ -0,0 +1,230 @@
+//! Conversion between frame numbers and timestamps.
+//!
+//! All conversions in this crate follow the same rounding rules:
+//! - The timestamp of a frame is its presentation time,
+//!   rounded to the nearest nanosecond.
+//! - A timestamp maps to the frame being displayed at that time,
+//!   which is the last frame whose timestamp is at or before it.
+//!
+//! Converting a frame to a timestamp and back always gives the same frame.
+
+use rav1e::prelude::Rational;
+use std::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// The presentation timing of each frame of a video.
+#[derive(Debug, Clone)]
+pub enum FrameTiming {
+    /// Every frame lasts for `time_base` seconds.
+    Constant { time_base: Rational },
+    /// Each frame starts at the matching entry of `pts`,
+    /// given in units of `time_base` seconds.
+    Variable { time_base: Rational, pts: Vec<u64> },
+}
+
+impl FrameTiming {
+    /// Creates the timing of a constant frame rate video
+    /// where each frame lasts for `time_base` seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `time_base` is zero or has a zero denominator.
+    pub fn constant(time_base: Rational) -> Self {
+        assert_valid_time_base(time_base);
+        FrameTiming::Constant { time_base }
+    }
+
+    /// Creates the timing of a variable frame rate video
+    /// from the presentation timestamp of each frame, in units of `time_base` seconds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `time_base` is zero or has a zero denominator,
+    /// or if `pts` is not sorted in increasing order.
+    pub fn variable(time_base: Rational, pts: Vec<u64>) -> Self {
+        assert_valid_time_base(time_base);
+        assert!(pts.windows(2).all(|pair| pair[0] < pair[1]));
+        FrameTiming::Variable { time_base, pts }
+    }
+
+    /// Returns the presentation time of a frame.
+    ///
+    /// For variable frame rate video, this is `None` for frames
+    /// past the end of the timestamp list.
+    pub fn frame_to_timestamp(&self, frameno: usize) -> Option<Duration> {
+        match self {
+            FrameTiming::Constant { time_base } => Some(Duration::from_nanos(ticks_to_nanos(
+                frameno as u64,
+                *time_base,
+            ))),
+            FrameTiming::Variable { time_base, pts } => pts
+                .get(frameno)
+                .map(|&pts| Duration::from_nanos(ticks_to_nanos(pts, *time_base))),
+        }
+    }
+
+    /// Returns the frame being displayed at `timestamp`.
+    ///
+    /// Timestamps before the first frame map to the first frame.
+    pub fn timestamp_to_frame(&self, timestamp: Duration) -> usize {
+        let nanos = timestamp.as_nanos();
+        match self {
+            FrameTiming::Constant { time_base } => {
+                let (num, den) = (u128::from(time_base.num), u128::from(time_base.den));
+                let mut frameno = (nanos * den / (num * NANOS_PER_SEC)) as u64;
+                // Account for the rounding of frame timestamps to the nearest nanosecond
+                if u128::from(ticks_to_nanos(frameno + 1, *time_base)) <= nanos {
+                    frameno += 1;
+                } else if frameno > 0 && u128::from(ticks_to_nanos(frameno, *time_base)) > nanos {
+                    frameno -= 1;
+                }
+                frameno as usize
+            }
+            FrameTiming::Variable { time_base, pts } => pts
+                .partition_point(|&pts| u128::from(ticks_to_nanos(pts, *time_base)) <= nanos)
+                .saturating_sub(1),
+        }
+    }
+
+    /// Returns the presentation time of a frame to the nearest nanosecond.
+    ///
+    /// Past the end of the timestamp list of a variable frame rate video,
+    /// the frames are assumed to last as long as the last listed frame,
+    /// so that the end of the last frame can be given.
+    pub(crate) fn frame_to_nanos(&self, frameno: usize) -> u64 {
+        match self {
+            FrameTiming::Constant { time_base } => ticks_to_nanos(frameno as u64, *time_base),
+            FrameTiming::Variable { time_base, pts } => {
+                let ticks = match pts.get(frameno) {
+                    Some(&pts) => pts,
+                    None => match *pts.as_slice() {
+                        [] => 0,
+                        [last] => last,
+                        [.., previous, last] => {
+                            last + (last - previous) * (frameno + 1 - pts.len()) as u64
+                        }
+                    },
+                };
+                ticks_to_nanos(ticks, *time_base)
+            }
+        }
+    }
+
+    /// Returns the frame rate rounded to the nearest integer, and at least 1.
+    ///
+    /// For variable frame rate video, this is the average frame rate.
+    pub(crate) fn rounded_fps(&self) -> usize {
+        let (frames, ticks, time_base) = match self {
+            FrameTiming::Constant { time_base } => (1, 1, time_base),
+            FrameTiming::Variable { time_base, pts } => match (pts.first(), pts.last()) {
+                (Some(&first), Some(&last)) if last > first => {
+                    (pts.len() as u64 - 1, last - first, time_base)
+                }
+                _ => return 1,
+            },
+        };
+        let (num, den) = (ticks * time_base.num, frames * time_base.den);
+        ((den + num / 2) / num).max(1) as usize
+    }
+}
+
+/// Frame numbers cannot be found from timestamps in a zero time base,
+/// and timestamps cannot be computed in a time base with a zero denominator.
+fn assert_valid_time_base(time_base: Rational) {
+    assert!(
+        time_base.num > 0 && time_base.den > 0,
+        "time base must be greater than zero"
+    );
+}
+
+/// Converts a number of `time_base` ticks to the nearest nanosecond.
+fn ticks_to_nanos(ticks: u64, time_base: Rational) -> u64 {
+    let (num, den) = (u128::from(time_base.num), u128::from(time_base.den));
+    ((u128::from(ticks) * num * NANOS_PER_SEC + den / 2) / den) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 23.976 frames per second.
+    const FILM_NTSC: Rational = Rational {
+        num: 1001,
+        den: 24000,
+    };
+
+    #[test]
+    fn constant_frame_rate_round_trip() {
+        let timing = FrameTiming::constant(FILM_NTSC);
+        assert_eq!(
+            timing.frame_to_timestamp(24),
+            Some(Duration::from_millis(1001))
+        );
+        // 1/3 ns is rounded down, 2/3 ns up
+        assert_eq!(
+            timing.frame_to_timestamp(1),
+            Some(Duration::from_nanos(41_708_333))
+        );
+        assert_eq!(
+            timing.frame_to_timestamp(2),
+            Some(Duration::from_nanos(83_416_667))
+        );
+        for frameno in (0..10_000_000).step_by(9973).chain(1..100) {
+            let timestamp = timing.frame_to_timestamp(frameno).unwrap();
+            assert_eq!(timing.timestamp_to_frame(timestamp), frameno);
+            assert_eq!(
+                timing.timestamp_to_frame(timestamp + Duration::from_nanos(1)),
+                frameno
+            );
+            // The previous frame is displayed until the timestamp of the next
+            if frameno > 0 {
+                assert_eq!(
+                    timing.timestamp_to_frame(timestamp - Duration::from_nanos(1)),
+                    frameno - 1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn variable_frame_rate_round_trip() {
+        // Frames at 30 and 60 frames per second, and a dropped frame
+        let pts = vec![0, 1001, 2002, 2502, 3003, 5005];
+        let timing = FrameTiming::variable(Rational { num: 1, den: 30000 }, pts.clone());
+        for frameno in 0..pts.len() {
+            let timestamp = timing.frame_to_timestamp(frameno).unwrap();
+            assert_eq!(timing.timestamp_to_frame(timestamp), frameno);
+            assert_eq!(
+                timing.timestamp_to_frame(timestamp + Duration::from_nanos(1)),
+                frameno
+            );
+            if frameno > 0 {
+                assert_eq!(
+                    timing.timestamp_to_frame(timestamp - Duration::from_nanos(1)),
+                    frameno - 1
+                );
+            }
+        }
+        assert_eq!(
+            timing.frame_to_timestamp(3),
+            Some(Duration::from_nanos(83_400_000))
+        );
+        // The dropped frame is covered by the frame before it
+        assert_eq!(timing.timestamp_to_frame(Duration::from_millis(150)), 4);
+        assert_eq!(timing.timestamp_to_frame(Duration::from_secs(10)), 5);
+        assert_eq!(timing.frame_to_timestamp(6), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "time base must be greater than zero")]
+    fn zero_time_base() {
+        FrameTiming::constant(Rational { num: 0, den: 1 });
+    }
+
+    #[test]
+    #[should_panic(expected = "time base must be greater than zero")]
+    fn zero_time_base_denominator() {
+        FrameTiming::variable(Rational { num: 1, den: 0 }, vec![0, 1]);
+    }
+}
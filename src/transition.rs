@@ -0,0 +1,126 @@
+use crate::black::BLACK_FRAME_THRESHOLD;
+use crate::decoder::VideoDetails;
+use crate::metrics;
+use rav1e::prelude::{Frame, Pixel};
+use std::sync::Arc;
+
+/// The normalized mean difference between the frames on either side
+/// of a frame above which the frame may be part of a transition.
+const MIN_CHANGE: f32 = 0.004;
+/// The largest deviation from a linear blend of the frames on either side,
+/// relative to the difference between them, for a frame
+/// to be considered part of a transition.
+const MAX_RELATIVE_DEVIATION: f32 = 0.25;
+/// The minimum number of frames in a transition.
+const MIN_TRANSITION_FRAMES: usize = 4;
+
+/// The kind of a gradual transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransitionKind {
+    /// A transition from a black frame.
+    FadeIn,
+    /// A transition to a black frame.
+    FadeOut,
+    /// A transition between two scenes.
+    Dissolve,
+}
+
+/// A gradual transition, such as a fade or dissolve,
+/// during which each frame is a blend of the frames before and after it.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serialize", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransitionEvent {
+    /// The 0-indexed frame number of the first blended frame.
+    pub start: usize,
+    /// The 0-indexed frame number of the first frame after the transition,
+    /// which is where an encoder should place a keyframe.
+    pub end: usize,
+    pub kind: TransitionKind,
+}
+
+/// Detects fades and dissolves as runs of frames which are each
+/// a linear blend of the frames on either side of them.
+pub(crate) struct TransitionTracker<T: Pixel> {
+    video_details: VideoDetails,
+    /// The frame two frames before the current one,
+    /// and whether it is black.
+    before: Option<(Arc<Frame<T>>, bool)>,
+    /// Whether the previous frame is black.
+    previous_is_black: bool,
+    /// The first frame of the current run of blended frames,
+    /// and whether the frame preceding it is black.
+    run: Option<(usize, bool)>,
+}
+
+impl<T: Pixel> TransitionTracker<T> {
+    pub fn new(video_details: VideoDetails) -> Self {
+        TransitionTracker {
+            video_details,
+            before: None,
+            previous_is_black: false,
+            run: None,
+        }
+    }
+
+    /// Adds a frame and the one preceding it, if any.
+    /// Returns a transition which ended at this frame, if any.
+    pub fn push(
+        &mut self,
+        frameno: usize,
+        previous: Option<&Arc<Frame<T>>>,
+        frame: &Arc<Frame<T>>,
+    ) -> Option<TransitionEvent> {
+        let is_black = metrics::mean_value(
+            &frame.planes[0],
+            self.video_details.width,
+            self.video_details.height,
+            self.video_details.bit_depth,
+        ) < BLACK_FRAME_THRESHOLD;
+        let previous = match previous {
+            Some(previous) => previous,
+            None => {
+                self.before = None;
+                self.previous_is_black = is_black;
+                self.run = None;
+                return None;
+            }
+        };
+
+        // Whether the previous frame is a blend of its neighbors
+        let is_blended = self.before.as_ref().is_some_and(|(before, _)| {
+            let (change, deviation) = metrics::blend_deviation(
+                &before.planes[0],
+                &previous.planes[0],
+                &frame.planes[0],
+                self.video_details.width,
+                self.video_details.height,
+                self.video_details.bit_depth,
+            );
+            change >= MIN_CHANGE && deviation <= change * MAX_RELATIVE_DEVIATION
+        });
+
+        let mut event = None;
+        if is_blended {
+            if self.run.is_none() {
+                let before_is_black = self.before.as_ref().is_some_and(|&(_, black)| black);
+                self.run = Some((frameno - 1, before_is_black));
+            }
+        } else if let Some((start, from_black)) = self.run.take() {
+            // The previous frame is the first one after the transition
+            let end = frameno - 1;
+            if end - start >= MIN_TRANSITION_FRAMES {
+                let kind = match (from_black, self.previous_is_black) {
+                    (true, false) => TransitionKind::FadeIn,
+                    (false, true) => TransitionKind::FadeOut,
+                    _ => TransitionKind::Dissolve,
+                };
+                event = Some(TransitionEvent { start, end, kind });
+            }
+        }
+
+        self.before = Some((Arc::clone(previous), self.previous_is_black));
+        self.previous_is_black = is_black;
+        event
+    }
+}
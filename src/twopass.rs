@@ -0,0 +1,155 @@
+use crate::histogram::{self, FrameScore};
+use crate::parallel::apply_scenecut_distances;
+use crate::postprocess;
+use crate::range;
+use crate::{
+    check_options, scenecut_distances, DecodeError, Decoder, DetectionError, DetectionOptions,
+    DetectionResults, SceneDetectionSpeed,
+};
+use rav1e::prelude::{Frame, Pixel};
+use std::time::Instant;
+
+/// How many times the calibrated baseline score
+/// the score of a frame must reach to be considered a scene change.
+const BASELINE_RATIO: f64 = 3.0;
+/// The minimum score for a frame to be considered a scene change,
+/// so that noise is not reported as scene changes in clips without any.
+const MIN_SCORE: f64 = 0.05;
+
+/// Runs through a video clip in two passes, detecting scene changes
+/// against a threshold calibrated for the whole clip.
+///
+/// The first pass scores every frame by the histogram difference
+/// from the frame before it. The score at `percentile`, from `0.0` to `1.0`,
+/// of all frames is then used as the baseline, and frames scoring well above
+/// it are reported as scene changes in the second pass. Because the baseline
+/// adapts to the clip as a whole, grainy sources do not over-trigger,
+/// and flat sources such as CG animation do not under-trigger, the way
+/// they can against a fixed threshold. A `percentile` around `0.9` works
+/// for most content, since scene changes are far rarer than other frames.
+///
+/// The threshold is calibrated for histogram differences, so `analysis_speed`
+/// must be `SceneDetectionSpeed::Histogram`, or
+/// `DetectionError::InvalidOptions` is returned.
+///
+/// The frames are only decoded once, and only their scores are kept
+/// for the second pass. Flash detection is not performed, and, as with
+/// `detect_scene_changes_parallel`, only the list of scene changes
+/// and the frame scores, if `record_scores` is enabled, are reported.
+///
+/// If the decoder fails partway through the clip, the second pass is run
+/// on the frames decoded until then, and the results are returned
+/// as part of the error.
+pub fn detect_scene_changes_two_pass<D: Decoder, T: Pixel>(
+    dec: &mut D,
+    opts: DetectionOptions,
+    percentile: f64,
+) -> Result<DetectionResults, DetectionError> {
+    assert!((0.0..=1.0).contains(&percentile));
+    check_options(&opts).map_err(DetectionError::from_invalid_options)?;
+    if opts.analysis_speed != SceneDetectionSpeed::Histogram {
+        return Err(DetectionError::from_invalid_options(
+            "two-pass detection only supports the histogram algorithm",
+        ));
+    }
+
+    let first_frame = opts.frame_range.as_ref().map_or(0, |range| range.start);
+    let (mut dec, opts) = range::restrict_to_range(dec, &opts);
+    let dec = &mut dec;
+    let video_details = dec.get_video_details();
+    let start_time = Instant::now();
+    let mut scores = Vec::new();
+    let mut previous: Option<Frame<T>> = None;
+    let mut decode_error = None;
+    loop {
+        let frame = match dec.read_video_frame::<T>(&video_details) {
+            Ok(frame) => frame,
+            Err(DecodeError::EndOfStream) => break,
+            Err(err) => {
+                decode_error = Some(err);
+                break;
+            }
+        };
+        if let Some(ref previous) = previous {
+            scores.push(histogram::frame_difference(
+                previous,
+                &frame,
+                &video_details,
+            ));
+        }
+        previous = Some(frame);
+    }
+    let frames_decoded = if previous.is_some() {
+        scores.len() + 1
+    } else {
+        0
+    };
+    let frame_count = frames_decoded.saturating_sub(opts.warmup_frames);
+    // `scores[i]` is now the score of frame `i + 1` after the warmup frames
+    let scores = &scores[scores.len().min(opts.warmup_frames)..];
+
+    let baseline = percentile_score(scores, percentile);
+    let threshold = (baseline * BASELINE_RATIO).max(MIN_SCORE);
+    let candidates = scores
+        .iter()
+        .enumerate()
+        .filter(|&(_, &score)| score >= threshold)
+        .map(|(i, _)| i + 1)
+        .collect::<Vec<_>>();
+    let (min_scenecut_distance, max_scenecut_distance) = scenecut_distances(&video_details, &opts);
+    let cut_strengths = candidates
+        .iter()
+        .map(|&frameno| (frameno, scores[frameno - 1] as f32))
+        .collect::<Vec<_>>();
+    let scene_changes = apply_scenecut_distances(
+        &candidates,
+        &cut_strengths,
+        frame_count,
+        min_scenecut_distance,
+        max_scenecut_distance,
+        opts.min_distance_policy,
+    );
+
+    let mut results = DetectionResults {
+        scene_changes: postprocess::apply_post_processing(
+            &opts.post_process,
+            &scene_changes,
+            &cut_strengths,
+            &[],
+            frame_count,
+        ),
+        frame_count,
+        speed: frame_count as f64 / start_time.elapsed().as_secs_f64(),
+        frame_scores: if opts.record_scores {
+            scores
+                .iter()
+                .enumerate()
+                .map(|(i, &score)| FrameScore {
+                    frameno: i + 1,
+                    raw: score,
+                    smoothed: baseline,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        },
+        ..Default::default()
+    };
+    results.offset_frames(first_frame);
+    match decode_error {
+        Some(err) => Err(DetectionError::from_decode_error(err, results)),
+        None => Ok(results),
+    }
+}
+
+/// Returns the score at `percentile` of all scores,
+/// using the nearest-rank method.
+fn percentile_score(scores: &[f64], percentile: f64) -> f64 {
+    if scores.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = (percentile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
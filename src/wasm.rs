@@ -0,0 +1,86 @@
+//! JavaScript API for running scene change detection in the browser,
+//! built as a cdylib with `--features wasm` and bound with `wasm-bindgen`,
+//! as described in the README.
+
+use crate::push::PushPipeline;
+use crate::{check_options, DetectionOptions, SceneDetectionSpeed};
+use wasm_bindgen::prelude::*;
+
+/// A scene change detector which decoded frames,
+/// such as from a `VideoFrame` or a canvas, are pushed to one at a time.
+#[wasm_bindgen]
+pub struct SceneDetector {
+    opts: DetectionOptions,
+    /// Created once the size and bit depth are known from the first frame.
+    pipeline: Option<PushPipeline>,
+}
+
+#[wasm_bindgen]
+impl SceneDetector {
+    /// Creates a detector using the given algorithm,
+    /// which is one of `"standard"`, `"fast"`, `"histogram"` or `"ecr"`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(speed: &str) -> Result<SceneDetector, JsValue> {
+        let analysis_speed = match speed {
+            "standard" => SceneDetectionSpeed::Standard,
+            "fast" => SceneDetectionSpeed::Fast,
+            "histogram" => SceneDetectionSpeed::Histogram,
+            "ecr" => SceneDetectionSpeed::EdgeChangeRatio,
+            _ => return Err(JsValue::from_str("unknown speed")),
+        };
+        let opts = DetectionOptions {
+            analysis_speed,
+            ..Default::default()
+        };
+        check_options(&opts).map_err(JsValue::from_str)?;
+        Ok(SceneDetector {
+            opts,
+            pipeline: None,
+        })
+    }
+
+    /// Pushes the luma plane of the next frame, with rows packed
+    /// one after another. Samples are single bytes for 8-bit video,
+    /// and little-endian 16-bit values otherwise.
+    ///
+    /// All frames must have the same size and bit depth.
+    pub fn push_frame(
+        &mut self,
+        luma: &[u8],
+        width: usize,
+        height: usize,
+        bit_depth: usize,
+    ) -> Result<(), JsValue> {
+        let pipeline = match self.pipeline {
+            Some(ref mut pipeline) => pipeline,
+            None => self.pipeline.insert(
+                PushPipeline::new(width, height, bit_depth, self.opts.clone())
+                    .map_err(JsValue::from_str)?,
+            ),
+        };
+        let details = pipeline.video_details();
+        if (width, height, bit_depth) != (details.width, details.height, details.bit_depth) {
+            return Err(JsValue::from_str(
+                "frame size or bit depth differs from the first frame",
+            ));
+        }
+        let stride = if bit_depth == 8 { width } else { width * 2 };
+        pipeline.push_luma(luma, stride).map_err(JsValue::from_str)
+    }
+
+    /// Finishes analyzing the pushed frames, and returns
+    /// the 0-indexed frame numbers of the scene changes.
+    ///
+    /// This may be called again to retrieve the same results,
+    /// but no further frames may be pushed.
+    pub fn finish(&mut self) -> Vec<u32> {
+        match self.pipeline {
+            Some(ref mut pipeline) => pipeline
+                .finish()
+                .iter()
+                .map(|&frameno| frameno as u32)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
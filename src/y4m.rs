@@ -1,22 +1,158 @@
+use crate::convert::LUMA_PADDING;
+use crate::decoder::{DecodeError, Decoder, FieldOrder, VideoDetails};
 use rav1e::prelude::{ChromaSamplePosition, ChromaSampling, Frame, Pixel, Rational};
 use std::io::Read;
 
-pub(crate) fn get_video_details<R: Read>(dec: &y4m::Decoder<R>) -> VideoDetails {
-    let width = dec.get_width();
-    let height = dec.get_height();
-    let color_space = dec.get_colorspace();
-    let bit_depth = color_space.get_bit_depth();
-    let (chroma_sampling, chroma_sample_position) = map_y4m_color_space(color_space);
-    let framerate = dec.get_framerate();
-    let time_base = Rational::new(framerate.den as u64, framerate.num as u64);
-
-    VideoDetails {
-        width,
-        height,
-        bit_depth,
-        chroma_sampling,
-        chroma_sample_position,
-        time_base,
+/// Decodes uncompressed y4m video from any reader,
+/// such as a file or the output of `vspipe -c y4m` or
+/// `ffmpeg -f yuv4mpegpipe` piped to stdin.
+///
+/// Scene change detection only looks at luma, so the fastest input
+/// is a luma-only clip, such as one produced by
+/// `ffmpeg -i input -vf extractplanes=y -f yuv4mpegpipe -strict -1 -`.
+/// This halves the amount of data to read for 4:2:0 input,
+/// and is decoded without any chroma handling.
+/// For input that still has chroma planes, `Y4mDecoder::luma_only`
+/// gives the same analysis without copying them.
+pub struct Y4mDecoder<R: Read> {
+    dec: y4m::Decoder<R>,
+    luma_only: bool,
+    total_frames: Option<usize>,
+}
+
+impl<R: Read> Y4mDecoder<R> {
+    /// Reads the y4m header from `reader`.
+    pub fn new(reader: R) -> Result<Self, DecodeError> {
+        let dec = y4m::Decoder::new(reader).map_err(map_y4m_error)?;
+        Ok(Y4mDecoder {
+            dec,
+            luma_only: false,
+            total_frames: None,
+        })
+    }
+
+    /// Reads the y4m header from `reader`,
+    /// and discards the chroma planes of each frame.
+    ///
+    /// The video is reported as monochrome, so that the detector
+    /// never has to allocate or look at chroma.
+    pub fn luma_only(reader: R) -> Result<Self, DecodeError> {
+        let mut dec = Self::new(reader)?;
+        dec.luma_only = true;
+        Ok(dec)
+    }
+
+    /// Sets the total number of frames in the input,
+    /// which y4m does not record, for use in progress reports.
+    pub fn with_total_frames(mut self, total_frames: usize) -> Self {
+        self.total_frames = Some(total_frames);
+        self
+    }
+
+    /// Returns a builder for writing y4m with the same properties as the input.
+    pub(crate) fn encoder_builder(&self) -> y4m::EncoderBuilder {
+        y4m::encode(
+            self.dec.get_width(),
+            self.dec.get_height(),
+            self.dec.get_framerate(),
+        )
+        .with_colorspace(self.dec.get_colorspace())
+        .with_pixel_aspect(self.dec.get_pixel_aspect())
+    }
+
+    /// Decodes the next frame, along with a copy of it as read from the input.
+    pub(crate) fn read_video_frame_with_raw<T: Pixel>(
+        &mut self,
+        cfg: &VideoDetails,
+    ) -> Result<(Frame<T>, RawFrame), DecodeError> {
+        let bytes = self.dec.get_bytes_per_sample();
+        let frame = self.dec.read_frame().map_err(map_y4m_error)?;
+        let raw = RawFrame {
+            planes: [
+                frame.get_y_plane().to_vec(),
+                frame.get_u_plane().to_vec(),
+                frame.get_v_plane().to_vec(),
+            ],
+            params: frame.get_raw_params().map(<[u8]>::to_vec),
+        };
+        Ok((convert_frame(&frame, cfg, bytes), raw))
+    }
+}
+
+/// A y4m frame as read from the input, for writing it back out unchanged.
+pub(crate) struct RawFrame {
+    pub planes: [Vec<u8>; 3],
+    /// The frame parameters, without the leading separator.
+    pub params: Option<Vec<u8>>,
+}
+
+impl<R: Read> Decoder for Y4mDecoder<R> {
+    fn get_video_details(&self) -> VideoDetails {
+        let width = self.dec.get_width();
+        let height = self.dec.get_height();
+        let color_space = self.dec.get_colorspace();
+        let bit_depth = color_space.get_bit_depth();
+        let (chroma_sampling, chroma_sample_position) = if self.luma_only {
+            (ChromaSampling::Cs400, ChromaSamplePosition::Unknown)
+        } else {
+            map_y4m_color_space(color_space)
+        };
+        let framerate = self.dec.get_framerate();
+        let time_base = Rational::new(framerate.den as u64, framerate.num as u64);
+
+        VideoDetails {
+            width,
+            height,
+            bit_depth,
+            chroma_sampling,
+            chroma_sample_position,
+            time_base,
+            field_order: parse_field_order(self.dec.get_raw_params()),
+        }
+    }
+
+    fn total_frames(&self) -> Option<usize> {
+        self.total_frames
+    }
+
+    fn read_video_frame<T: Pixel>(&mut self, cfg: &VideoDetails) -> Result<Frame<T>, DecodeError> {
+        let bytes = self.dec.get_bytes_per_sample();
+        self.dec
+            .read_frame()
+            .map(|frame| convert_frame(&frame, cfg, bytes))
+            .map_err(map_y4m_error)
+    }
+
+    fn read_video_frame_into<T: Pixel>(
+        &mut self,
+        cfg: &VideoDetails,
+        f: &mut Frame<T>,
+    ) -> Result<(), DecodeError> {
+        let bytes = self.dec.get_bytes_per_sample();
+        let frame = self.dec.read_frame().map_err(map_y4m_error)?;
+        copy_frame(&frame, cfg, bytes, f);
+        Ok(())
+    }
+}
+
+/// Copies a y4m frame into a new frame for the detector.
+fn convert_frame<T: Pixel>(frame: &y4m::Frame, cfg: &VideoDetails, bytes: usize) -> Frame<T> {
+    let mut f: Frame<T> =
+        Frame::new_with_padding(cfg.width, cfg.height, cfg.chroma_sampling, LUMA_PADDING);
+    copy_frame(frame, cfg, bytes, &mut f);
+    f
+}
+
+/// Copies a y4m frame into an existing frame for the detector.
+fn copy_frame<T: Pixel>(frame: &y4m::Frame, cfg: &VideoDetails, bytes: usize, f: &mut Frame<T>) {
+    f.planes[0].copy_from_raw_u8(frame.get_y_plane(), cfg.width * bytes, bytes);
+    // Monochrome input has no chroma planes to copy
+    if cfg.chroma_sampling != ChromaSampling::Cs400 {
+        let (chroma_width, _) = cfg
+            .chroma_sampling
+            .get_chroma_dimensions(cfg.width, cfg.height);
+        f.planes[1].copy_from_raw_u8(frame.get_u_plane(), chroma_width * bytes, bytes);
+        f.planes[2].copy_from_raw_u8(frame.get_v_plane(), chroma_width * bytes, bytes);
     }
 }
 
@@ -34,53 +170,23 @@ fn map_y4m_color_space(color_space: y4m::Colorspace) -> (ChromaSampling, ChromaS
     }
 }
 
-pub(crate) fn read_video_frame<R: Read, T: Pixel>(
-    dec: &mut y4m::Decoder<R>,
-    cfg: &VideoDetails,
-) -> Result<Frame<T>, ()> {
-    const SB_SIZE_LOG2: usize = 6;
-    const SB_SIZE: usize = 1 << SB_SIZE_LOG2;
-    const SUBPEL_FILTER_SIZE: usize = 8;
-    const FRAME_MARGIN: usize = 16 + SUBPEL_FILTER_SIZE;
-    const LUMA_PADDING: usize = SB_SIZE + FRAME_MARGIN;
-
-    let bytes = dec.get_bytes_per_sample();
-    dec.read_frame()
-        .map(|frame| {
-            let mut f: Frame<T> =
-                Frame::new_with_padding(cfg.width, cfg.height, cfg.chroma_sampling, LUMA_PADDING);
-
-            let (chroma_width, _) = cfg
-                .chroma_sampling
-                .get_chroma_dimensions(cfg.width, cfg.height);
-
-            f.planes[0].copy_from_raw_u8(frame.get_y_plane(), cfg.width * bytes, bytes);
-            f.planes[1].copy_from_raw_u8(frame.get_u_plane(), chroma_width * bytes, bytes);
-            f.planes[2].copy_from_raw_u8(frame.get_v_plane(), chroma_width * bytes, bytes);
-            f
-        })
-        .map_err(|_| ())
-}
-
-#[derive(Debug, Clone, Copy)]
-pub(crate) struct VideoDetails {
-    pub width: usize,
-    pub height: usize,
-    pub bit_depth: usize,
-    pub chroma_sampling: ChromaSampling,
-    pub chroma_sample_position: ChromaSamplePosition,
-    pub time_base: Rational,
+/// Reads the field order from the interlacing parameter of a y4m header.
+/// Mixed interlacing, which is signaled per frame, is treated as progressive.
+fn parse_field_order(raw_params: &[u8]) -> FieldOrder {
+    let interlacing = raw_params
+        .split(|&b| b == b' ')
+        .find(|param| param.first() == Some(&b'I'));
+    match interlacing {
+        Some(b"It") => FieldOrder::TopFieldFirst,
+        Some(b"Ib") => FieldOrder::BottomFieldFirst,
+        _ => FieldOrder::Progressive,
+    }
 }
 
-impl Default for VideoDetails {
-    fn default() -> Self {
-        VideoDetails {
-            width: 640,
-            height: 480,
-            bit_depth: 8,
-            chroma_sampling: ChromaSampling::Cs420,
-            chroma_sample_position: ChromaSamplePosition::Unknown,
-            time_base: Rational { num: 30, den: 1 },
-        }
+fn map_y4m_error(err: y4m::Error) -> DecodeError {
+    match err {
+        y4m::Error::EOF => DecodeError::EndOfStream,
+        y4m::Error::IoError(err) => DecodeError::Io(err),
+        err => DecodeError::InvalidInput(format!("{:?}", err)),
     }
 }
@@ -0,0 +1,115 @@
+//! Synthetic clips with known scene changes,
+//! shared by the regression tests and the benchmarks.
+
+#![allow(dead_code)]
+
+use av_scenechange::{
+    detect_scene_changes, detect_scene_changes_parallel, DetectionOptions, SceneDetectionSpeed,
+    Y4mDecoder,
+};
+
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 144;
+/// The number of frames in each scene of `hard_cuts_clip`.
+pub const SCENE_LENGTH: usize = 30;
+
+/// The content of a synthetic scene, slowly panning over time
+/// so that consecutive frames within the scene are not identical.
+#[derive(Clone, Copy)]
+pub enum Pattern {
+    /// A dark horizontal gradient.
+    Gradient,
+    /// A bright checkerboard of 16x16 blocks.
+    Checkerboard,
+    /// Mid-gray diagonal stripes.
+    Stripes,
+    /// A bright vertical gradient.
+    VerticalGradient,
+}
+
+impl Pattern {
+    fn luma(self, x: usize, y: usize, t: usize) -> u8 {
+        match self {
+            Pattern::Gradient => (16 + (x + t) % WIDTH * 80 / WIDTH) as u8,
+            Pattern::Checkerboard => {
+                if ((x + t) / 16 + y / 16) % 2 == 0 {
+                    180
+                } else {
+                    235
+                }
+            }
+            Pattern::Stripes => {
+                if (x + y + t) % 24 < 12 {
+                    100
+                } else {
+                    150
+                }
+            }
+            Pattern::VerticalGradient => (160 + (y + t) % HEIGHT * 90 / HEIGHT) as u8,
+        }
+    }
+}
+
+/// Encodes `scenes` of the given patterns and lengths as 8-bit 4:2:0 y4m.
+pub fn clip(scenes: &[(Pattern, usize)]) -> Vec<u8> {
+    let mut data =
+        format!("YUV4MPEG2 W{} H{} F25:1 Ip A1:1 C420jpeg\n", WIDTH, HEIGHT).into_bytes();
+    for &(pattern, len) in scenes {
+        for t in 0..len {
+            data.extend_from_slice(b"FRAME\n");
+            for y in 0..HEIGHT {
+                data.extend((0..WIDTH).map(|x| pattern.luma(x, y, t)));
+            }
+            data.resize(data.len() + WIDTH * HEIGHT / 2, 128);
+        }
+    }
+    data
+}
+
+/// A clip of four scenes of `SCENE_LENGTH` frames, separated by hard cuts.
+pub fn hard_cuts_clip() -> Vec<u8> {
+    clip(&[
+        (Pattern::Gradient, SCENE_LENGTH),
+        (Pattern::Checkerboard, SCENE_LENGTH),
+        (Pattern::Stripes, SCENE_LENGTH),
+        (Pattern::VerticalGradient, SCENE_LENGTH),
+    ])
+}
+
+/// The scene changes of `hard_cuts_clip`.
+pub fn hard_cuts() -> Vec<usize> {
+    (0..4).map(|scene| scene * SCENE_LENGTH).collect()
+}
+
+/// A clip of a single scene without any scene changes.
+pub fn single_scene_clip() -> Vec<u8> {
+    clip(&[(Pattern::Stripes, 4 * SCENE_LENGTH)])
+}
+
+/// Runs detection on a y4m clip with the default options and the given speed.
+pub fn detect(clip: &[u8], speed: SceneDetectionSpeed) -> Vec<usize> {
+    detect_with_options(
+        clip,
+        DetectionOptions {
+            analysis_speed: speed,
+            ..Default::default()
+        },
+    )
+}
+
+/// Runs detection on a y4m clip with the given options.
+pub fn detect_with_options(clip: &[u8], opts: DetectionOptions) -> Vec<usize> {
+    let mut dec = Y4mDecoder::new(clip).unwrap();
+    detect_scene_changes::<_, u8>(&mut dec, opts, None, None, None)
+        .unwrap()
+        .scene_changes
+}
+
+/// Runs parallel detection on a y4m clip with the given options,
+/// in chunks of `chunk_size` frames.
+pub fn detect_parallel(clip: &[u8], opts: DetectionOptions, chunk_size: usize) -> Vec<usize> {
+    let mut dec = Y4mDecoder::new(clip).unwrap();
+    detect_scene_changes_parallel::<_, u8>(&mut dec, opts, 2, chunk_size)
+        .unwrap()
+        .scene_changes
+}
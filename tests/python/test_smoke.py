@@ -0,0 +1,33 @@
+"""Smoke test of the Python bindings, run after building them with
+`maturin develop` from the root of the repository."""
+
+import numpy as np
+
+import av_scenechange
+
+WIDTH = 256
+HEIGHT = 144
+SCENE_LENGTH = 30
+
+
+def gradient(t):
+    x = np.arange(WIDTH)
+    row = 16 + (x + t) % WIDTH * 80 // WIDTH
+    return np.tile(row, (HEIGHT, 1)).astype(np.uint8)
+
+
+def checkerboard(t):
+    y, x = np.mgrid[0:HEIGHT, 0:WIDTH]
+    return np.where(((x + t) // 16 + y // 16) % 2 == 0, 180, 235).astype(np.uint8)
+
+
+def test_hard_cut():
+    detector = av_scenechange.Detector(WIDTH, HEIGHT)
+    for pattern in (gradient, checkerboard):
+        for t in range(SCENE_LENGTH):
+            detector.push_frame(pattern(t))
+    assert detector.finish() == [0, SCENE_LENGTH]
+
+
+if __name__ == "__main__":
+    test_hard_cut()
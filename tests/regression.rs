@@ -0,0 +1,221 @@
+mod common;
+
+use av_scenechange::{
+    detect_scene_changes, detect_scene_changes_multi, detect_scene_changes_subsampled, Decoder,
+    DetectionError, DetectionOptions, MinDistancePolicy, SceneDetectionSpeed, SceneDetector,
+    TimeoutDecoder, Y4mDecoder,
+};
+use common::{
+    clip, detect, detect_parallel, detect_with_options, hard_cuts, hard_cuts_clip,
+    single_scene_clip, Pattern, HEIGHT, SCENE_LENGTH, WIDTH,
+};
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn hard_cuts_standard() {
+    assert_eq!(
+        detect(&hard_cuts_clip(), SceneDetectionSpeed::Standard),
+        hard_cuts()
+    );
+}
+
+#[test]
+fn hard_cuts_fast() {
+    assert_eq!(
+        detect(&hard_cuts_clip(), SceneDetectionSpeed::Fast),
+        hard_cuts()
+    );
+}
+
+#[test]
+fn hard_cuts_histogram() {
+    assert_eq!(
+        detect(&hard_cuts_clip(), SceneDetectionSpeed::Histogram),
+        hard_cuts()
+    );
+}
+
+#[test]
+fn single_scene() {
+    for &speed in &[
+        SceneDetectionSpeed::Standard,
+        SceneDetectionSpeed::Fast,
+        SceneDetectionSpeed::Histogram,
+    ] {
+        assert_eq!(detect(&single_scene_clip(), speed), vec![0]);
+    }
+}
+
+#[test]
+fn parallel_matches_serial_across_chunk_boundaries() {
+    // Chunks of 30 frames, with cuts just before, on and just after their boundaries
+    let boundary_cuts = clip(&[
+        (Pattern::Gradient, 29),
+        (Pattern::Checkerboard, 31),
+        (Pattern::Stripes, 31),
+        (Pattern::VerticalGradient, 29),
+    ]);
+    // Two cuts closer than the minimum distance on either side of a boundary
+    let close_cuts = clip(&[
+        (Pattern::Gradient, 25),
+        (Pattern::Checkerboard, 8),
+        (Pattern::Stripes, 30),
+        (Pattern::VerticalGradient, 27),
+    ]);
+    let min_distance = |min_distance_policy| DetectionOptions {
+        min_scenecut_distance: Some(12),
+        min_distance_policy,
+        ..Default::default()
+    };
+
+    let opts = DetectionOptions::default();
+    assert_eq!(
+        detect_parallel(&boundary_cuts, opts.clone(), 30),
+        [0, 29, 60, 91]
+    );
+    assert_eq!(
+        detect_parallel(&boundary_cuts, opts.clone(), 30),
+        detect_with_options(&boundary_cuts, opts.clone())
+    );
+    assert_eq!(
+        detect_parallel(&close_cuts, opts.clone(), 30),
+        detect_with_options(&close_cuts, opts)
+    );
+    for policy in [
+        MinDistancePolicy::KeepFirst,
+        MinDistancePolicy::KeepLast,
+        MinDistancePolicy::KeepStronger,
+    ] {
+        assert_eq!(
+            detect_parallel(&close_cuts, min_distance(policy), 30),
+            detect_with_options(&close_cuts, min_distance(policy))
+        );
+    }
+}
+
+#[test]
+fn multi_matches_serial() {
+    let close_cuts = clip(&[
+        (Pattern::Gradient, 25),
+        (Pattern::Checkerboard, 8),
+        (Pattern::Stripes, 30),
+        (Pattern::VerticalGradient, 27),
+    ]);
+    for policy in [
+        MinDistancePolicy::KeepFirst,
+        MinDistancePolicy::KeepLast,
+        MinDistancePolicy::KeepStronger,
+    ] {
+        let opts = DetectionOptions {
+            min_scenecut_distance: Some(12),
+            min_distance_policy: policy,
+            ..Default::default()
+        };
+        let mut dec = Y4mDecoder::new(&close_cuts[..]).unwrap();
+        let results = detect_scene_changes_multi::<_, u8>(
+            &mut dec,
+            opts.clone(),
+            &[SceneDetectionSpeed::Standard, SceneDetectionSpeed::Fast],
+        );
+        for (result, speed) in results
+            .into_iter()
+            .zip([SceneDetectionSpeed::Standard, SceneDetectionSpeed::Fast])
+        {
+            let opts = DetectionOptions {
+                analysis_speed: speed,
+                ..opts.clone()
+            };
+            assert_eq!(
+                result.unwrap().scene_changes,
+                detect_with_options(&close_cuts, opts)
+            );
+        }
+    }
+}
+
+#[test]
+fn subsampled_analyzes_frames_after_last_sample() {
+    // Coarse samples every 16 frames, the last one at frame 64,
+    // with a cut between two samples and another after the last one
+    let clip = clip(&[
+        (Pattern::Gradient, 30),
+        (Pattern::Checkerboard, 40),
+        (Pattern::Stripes, 10),
+    ]);
+    let mut dec = Y4mDecoder::new(&clip[..]).unwrap();
+    let results =
+        detect_scene_changes_subsampled::<_, u8>(&mut dec, DetectionOptions::default(), 16)
+            .unwrap();
+    assert_eq!(results.scene_changes, [0, 30, 70]);
+}
+
+#[test]
+fn scene_detector_continues_after_flush() {
+    let clip = hard_cuts_clip();
+    let mut dec = Y4mDecoder::new(&clip[..]).unwrap();
+    let video_details = dec.get_video_details();
+    let mut detector =
+        SceneDetector::<u8>::new(video_details, DetectionOptions::default()).unwrap();
+    let mut cuts = Vec::new();
+    for frameno in 0..4 * SCENE_LENGTH {
+        let frame = dec.read_video_frame::<u8>(&video_details).unwrap();
+        if let Some(cut) = detector.push_frame(frame) {
+            assert!(!cut.flush_decided);
+            cuts.push(cut);
+        }
+        // Pause in the middle of the second scene
+        if frameno == 44 {
+            let flushed = detector.flush();
+            assert!(flushed.iter().all(|cut| cut.flush_decided));
+            cuts.extend(flushed);
+        }
+    }
+    cuts.extend(detector.flush());
+    assert_eq!(
+        cuts.iter().map(|cut| cut.frameno).collect::<Vec<_>>(),
+        hard_cuts()
+    );
+}
+
+/// Reads the given data, then waits forever for more.
+struct StallingReader {
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for StallingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.data.len() {
+            thread::sleep(Duration::from_secs(60));
+        }
+        let len = buf.len().min(self.data.len() - self.pos);
+        buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn timeout_decoder_reports_stalls() {
+    // The input stops arriving in the middle of the second scene
+    let mut data = hard_cuts_clip();
+    let header_len = data.iter().position(|&b| b == b'\n').unwrap() + 1;
+    data.truncate(header_len + 45 * (6 + WIDTH * HEIGHT * 3 / 2));
+    let dec = Y4mDecoder::new(StallingReader { data, pos: 0 }).unwrap();
+    let mut dec = TimeoutDecoder::new(dec, Duration::from_millis(200));
+    match detect_scene_changes::<_, u8>(&mut dec, DetectionOptions::default(), None, None, None) {
+        Err(DetectionError::DecoderStalled {
+            last_analyzed_frame,
+            partial_results,
+        }) => {
+            assert_eq!(partial_results.scene_changes, [0, SCENE_LENGTH]);
+            assert!(last_analyzed_frame.is_some_and(|frameno| frameno < 45));
+        }
+        result => panic!(
+            "expected a stall, got {:?}",
+            result.map(|r| r.scene_changes)
+        ),
+    }
+}